@@ -0,0 +1,1021 @@
+//! The syntax tree as data, for tools that read or generate programs
+//! without parsing Fnky themselves. `to_json` and `to_sexpr` dump a parsed
+//! program and `from_json` reads the JSON form back.
+//!
+//! Every node has a `kind`, its `span` and one field per child, in a fixed
+//! order. Names and operators are strings, and a literal's value is the
+//! JSON number, string, boolean or null it stands for. A span runs from the
+//! first to the last token the tree keeps for the node: closing brackets
+//! and semicolons are not kept, so they fall outside it. A `for` loop comes
+//! out as the blocks and `while` loop it stands for; the `while` spans from
+//! the `for` keyword, and the blocks, which have no braces, span their
+//! statements. A `var` without an initialiser has a null `initialiser`, and
+//! the `while` of a `for` loop without a condition a null `condition`.
+
+use std::rc::Rc;
+
+use crate::expr::{Expr, LiteralValue};
+use crate::json;
+use crate::map::{Key, Map};
+use crate::scanner::{self, Scanner, Token, TokenType};
+use crate::stmt::Stmt;
+
+/// The program as a JSON array of statements, indented by two spaces.
+pub fn to_json(stmts: &[Stmt]) -> String {
+    let program = LiteralValue::list(stmts.iter().map(|stmt| dump_stmt(stmt).0).collect());
+    json::stringify(&program, Some(2)).expect("syntax trees hold only JSON values")
+}
+
+/// The program as S-expressions, one statement per line. A node prints as
+/// `(kind (span start end line) (field value)...)`, a list as `(items...)`
+/// and a missing child as `nil`.
+pub fn to_sexpr(stmts: &[Stmt]) -> String {
+    let mut out = String::new();
+    for stmt in stmts {
+        sexpr(&dump_stmt(stmt).0, &mut out);
+        out.push('\n');
+    }
+    out
+}
+
+/// Reads a program in the form `to_json` writes. Spans may be left out;
+/// the tokens of a node are placed at the start and line of its span, or
+/// of the nearest enclosing node that has one. The tree must be one the
+/// parser could have built: names must be identifiers, operators must fit
+/// their node, `return` must be inside a function and `export` at the top
+/// level. Errors give the JSON path of the offending node.
+///
+/// The JSON may nest at most 512 levels deep, as for `json::parse`. Each
+/// node takes a level and each list of statements another, so this limits
+/// how deeply the program can nest.
+pub fn from_json(source: &str) -> Result<Vec<Stmt>, String> {
+    let program = json::parse(source)?;
+    let mut reader = Reader { function_depth: 0 };
+    reader.program(&program)
+}
+
+#[derive(Clone, Copy)]
+struct Span {
+    start: usize,
+    end: usize,
+    line: u64,
+}
+
+impl Span {
+    fn of(token: &Token) -> Self {
+        Span {
+            start: token.offset,
+            end: token.offset + token.lexeme.len(),
+            line: token.line_number,
+        }
+    }
+
+    fn join(self, other: Option<Span>) -> Span {
+        match other {
+            Some(other) => Span {
+                start: self.start.min(other.start),
+                end: self.end.max(other.end),
+                line: if other.start < self.start {
+                    other.line
+                } else {
+                    self.line
+                },
+            },
+            None => self,
+        }
+    }
+}
+
+fn join(span: Option<Span>, other: Option<Span>) -> Option<Span> {
+    match span {
+        Some(span) => Some(span.join(other)),
+        None => other,
+    }
+}
+
+// A dumped node or list of nodes, and the span it covers if it holds any
+// tokens.
+type Dumped = (LiteralValue, Option<Span>);
+
+struct Node {
+    kind: &'static str,
+    span: Option<Span>,
+    fields: Vec<(&'static str, LiteralValue)>,
+}
+
+impl Node {
+    fn new(kind: &'static str) -> Self {
+        Node {
+            kind,
+            span: None,
+            fields: vec![],
+        }
+    }
+
+    // A token of the node that is not a field of its own.
+    fn token(mut self, token: &Token) -> Self {
+        self.span = join(self.span, Some(Span::of(token)));
+        self
+    }
+
+    fn text(self, field: &'static str, token: &Token) -> Self {
+        let text = match &token.literal {
+            Some(scanner::LiteralValue::StringValue(text)) if token.token_type == TokenType::STRING => text.clone(),
+            _ => token.lexeme.clone(),
+        };
+        self.token(token).value(field, LiteralValue::StringValue(text))
+    }
+
+    fn names(mut self, field: &'static str, tokens: &[Token]) -> Self {
+        let names = tokens
+            .iter()
+            .map(|token| LiteralValue::StringValue(token.lexeme.clone()))
+            .collect();
+        for token in tokens {
+            self = self.token(token);
+        }
+        self.value(field, LiteralValue::list(names))
+    }
+
+    fn value(mut self, field: &'static str, value: LiteralValue) -> Self {
+        self.fields.push((field, value));
+        self
+    }
+
+    fn child(mut self, field: &'static str, (value, span): Dumped) -> Self {
+        self.span = join(self.span, span);
+        self.value(field, value)
+    }
+
+    fn finish(self) -> Dumped {
+        let span = self.span.expect("every node holds a token");
+        let mut map = Map::new();
+        map.insert(
+            Key::String("kind".to_string()),
+            LiteralValue::StringValue(self.kind.to_string()),
+        );
+        map.insert(
+            Key::String("span".to_string()),
+            object(vec![
+                ("start", LiteralValue::Number(span.start as f64)),
+                ("end", LiteralValue::Number(span.end as f64)),
+                ("line", LiteralValue::Number(span.line as f64)),
+            ]),
+        );
+        for (field, value) in self.fields {
+            map.insert(Key::String(field.to_string()), value);
+        }
+        (LiteralValue::map(map), Some(span))
+    }
+}
+
+fn object(entries: Vec<(&str, LiteralValue)>) -> LiteralValue {
+    let mut map = Map::new();
+    for (key, value) in entries {
+        map.insert(Key::String(key.to_string()), value);
+    }
+    LiteralValue::map(map)
+}
+
+fn list(items: impl Iterator<Item = Dumped>) -> Dumped {
+    let mut values = vec![];
+    let mut span = None;
+    for (value, item_span) in items {
+        values.push(value);
+        span = join(span, item_span);
+    }
+    (LiteralValue::list(values), span)
+}
+
+fn optional(item: Option<Dumped>) -> Dumped {
+    item.unwrap_or((LiteralValue::Nil, None))
+}
+
+fn dump_stmts(stmts: &[Stmt]) -> Dumped {
+    list(stmts.iter().map(dump_stmt))
+}
+
+fn dump_stmt(stmt: &Stmt) -> Dumped {
+    let node = match stmt {
+        Stmt::Expression { expression } => Node::new("expression").child("expression", dump_expr(expression)),
+        Stmt::Print { keyword, expression } => Node::new("print")
+            .token(keyword)
+            .child("expression", dump_expr(expression)),
+        Stmt::Var {
+            keyword,
+            name,
+            initialiser,
+        } => Node::new("var")
+            .token(keyword)
+            .text("name", name)
+            .child("initialiser", optional(declared(name, initialiser).map(dump_expr))),
+        // The blocks a `for` loop stands for have the `for` keyword as their
+        // brace, which is not part of them.
+        Stmt::Block { brace, statements } if brace.token_type != TokenType::LEFT_BRACE => {
+            Node::new("block").child("statements", dump_stmts(statements))
+        }
+        Stmt::Block { brace, statements } => Node::new("block")
+            .token(brace)
+            .child("statements", dump_stmts(statements)),
+        Stmt::Function {
+            keyword,
+            name,
+            params,
+            body,
+        } => Node::new("function")
+            .token(keyword)
+            .text("name", name)
+            .names("params", params)
+            .child("body", dump_stmts(body)),
+        Stmt::If {
+            keyword,
+            condition,
+            then_branch,
+            else_branch,
+        } => Node::new("if")
+            .token(keyword)
+            .child("condition", dump_expr(condition))
+            .child("then_branch", dump_stmt(then_branch))
+            .child("else_branch", optional(else_branch.as_deref().map(dump_stmt))),
+        Stmt::While {
+            keyword,
+            condition,
+            body,
+        } => Node::new("while")
+            .token(keyword)
+            .child("condition", optional(loop_condition(condition).map(dump_expr)))
+            .child("body", dump_stmt(body)),
+        Stmt::Return { keyword, value } => Node::new("return")
+            .token(keyword)
+            .child("value", optional(value.as_ref().map(dump_expr))),
+        Stmt::Throw { keyword, value } => Node::new("throw").token(keyword).child("value", dump_expr(value)),
+        Stmt::Import { keyword, path, alias } => Node::new("import")
+            .token(keyword)
+            .text("path", path)
+            .text("alias", alias),
+        Stmt::ImportFrom { keyword, path, names } => Node::new("import_from")
+            .token(keyword)
+            .text("path", path)
+            .names("names", names),
+        Stmt::Export { keyword, declaration } => Node::new("export")
+            .token(keyword)
+            .child("declaration", dump_stmt(declaration)),
+        Stmt::Try {
+            keyword,
+            body,
+            catch_clause,
+            finally_body,
+        } => {
+            let catch_clause = catch_clause.as_ref().map(|(name, statements)| {
+                Node::new("catch")
+                    .text("name", name)
+                    .child("body", dump_stmts(statements))
+                    .finish()
+            });
+            Node::new("try")
+                .token(keyword)
+                .child("body", dump_stmts(body))
+                .child("catch", optional(catch_clause))
+                .child("finally", optional(finally_body.as_deref().map(dump_stmts)))
+        }
+        Stmt::Error { token, message } => Node::new("error")
+            .token(token)
+            .value("message", LiteralValue::StringValue(message.clone())),
+    };
+    node.finish()
+}
+
+// The initialiser written in a `var` declaration. Without one the parser
+// stands in a nil literal at the variable's name.
+fn declared<'a>(name: &Token, initialiser: &'a Expr) -> Option<&'a Expr> {
+    match initialiser {
+        Expr::Literal { token, .. } if token.offset == name.offset && token.token_type == name.token_type => None,
+        initialiser => Some(initialiser),
+    }
+}
+
+// The condition written in a loop. For `for (;;)` the parser stands in a
+// `true` literal at the second `;`.
+fn loop_condition(condition: &Expr) -> Option<&Expr> {
+    match condition {
+        Expr::Literal { token, .. } if token.token_type == TokenType::SEMICOLON => None,
+        condition => Some(condition),
+    }
+}
+
+fn dump_exprs(exprs: &[Expr]) -> Dumped {
+    list(exprs.iter().map(dump_expr))
+}
+
+fn dump_expr(expr: &Expr) -> Dumped {
+    let node = match expr {
+        Expr::Assign { name, value } => Node::new("assign").text("name", name).child("value", dump_expr(value)),
+        Expr::Binary { left, operator, right } => Node::new("binary")
+            .child("left", dump_expr(left))
+            .text("operator", operator)
+            .child("right", dump_expr(right)),
+        Expr::Call {
+            callee,
+            paren,
+            arguments,
+        } => Node::new("call")
+            .token(paren)
+            .child("callee", dump_expr(callee))
+            .child("arguments", dump_exprs(arguments)),
+        Expr::Get { object, name } => Node::new("get").child("object", dump_expr(object)).text("name", name),
+        Expr::Index { object, bracket, index } => Node::new("index")
+            .token(bracket)
+            .child("object", dump_expr(object))
+            .child("index", dump_expr(index)),
+        Expr::SetIndex {
+            object,
+            bracket,
+            index,
+            value,
+        } => Node::new("set_index")
+            .token(bracket)
+            .child("object", dump_expr(object))
+            .child("index", dump_expr(index))
+            .child("value", dump_expr(value)),
+        Expr::Lambda { keyword, params, body } => Node::new("lambda")
+            .token(keyword)
+            .names("params", params)
+            .child("body", dump_stmts(body)),
+        Expr::List { bracket, elements } => Node::new("list").token(bracket).child("elements", dump_exprs(elements)),
+        Expr::Map { brace, entries } => {
+            let entries = entries.iter().map(|(key, value)| {
+                Node::new("entry")
+                    .child("key", dump_expr(key))
+                    .child("value", dump_expr(value))
+                    .finish()
+            });
+            Node::new("map").token(brace).child("entries", list(entries))
+        }
+        Expr::Grouping { paren, expression } => Node::new("grouping")
+            .token(paren)
+            .child("expression", dump_expr(expression)),
+        Expr::Literal { token, value } => Node::new("literal").token(token).value("value", value.clone()),
+        Expr::Logical { left, operator, right } => Node::new("logical")
+            .child("left", dump_expr(left))
+            .text("operator", operator)
+            .child("right", dump_expr(right)),
+        Expr::Unary { operator, right } => Node::new("unary")
+            .text("operator", operator)
+            .child("right", dump_expr(right)),
+        Expr::Variable { name } => Node::new("variable").text("name", name),
+    };
+    node.finish()
+}
+
+fn sexpr(value: &LiteralValue, out: &mut String) {
+    match value {
+        LiteralValue::Map(map) => {
+            let map = map.borrow();
+            out.push('(');
+            for (index, (key, value)) in map.iter().enumerate() {
+                if index > 0 {
+                    out.push(' ');
+                }
+                match (key, value) {
+                    (Key::String(key), LiteralValue::StringValue(kind)) if key == "kind" => out.push_str(kind),
+                    (Key::String(key), span) if key == "span" => {
+                        let numbers: Vec<String> = ["start", "end", "line"]
+                            .iter()
+                            .map(|field| get(span, field).map_or("nil".to_string(), |value| value.to_string()))
+                            .collect();
+                        out.push_str(&format!("(span {})", numbers.join(" ")));
+                    }
+                    (key, value) => {
+                        out.push('(');
+                        out.push_str(&key.to_value().to_string());
+                        out.push(' ');
+                        sexpr(value, out);
+                        out.push(')');
+                    }
+                }
+            }
+            out.push(')');
+        }
+        LiteralValue::List(items) => {
+            out.push('(');
+            for (index, item) in items.borrow().iter().enumerate() {
+                if index > 0 {
+                    out.push(' ');
+                }
+                sexpr(item, out);
+            }
+            out.push(')');
+        }
+        LiteralValue::StringValue(_) => {
+            out.push_str(&json::stringify(value, None).expect("strings convert to JSON"));
+        }
+        other => out.push_str(&other.to_string()),
+    }
+}
+
+fn get(value: &LiteralValue, key: &str) -> Option<LiteralValue> {
+    match value {
+        LiteralValue::Map(map) => map.borrow().get(&Key::String(key.to_string())).cloned(),
+        _ => None,
+    }
+}
+
+// Where the tokens of a node read from JSON are placed.
+#[derive(Clone, Copy)]
+struct Position {
+    offset: usize,
+    line: u64,
+}
+
+// A JSON node being read: its fields, where it is in the document and where
+// its tokens go.
+struct Fields {
+    value: LiteralValue,
+    kind: String,
+    path: String,
+    position: Position,
+}
+
+impl Fields {
+    fn error(&self, message: &str) -> String {
+        error(&self.path, message)
+    }
+
+    fn path(&self, field: &str) -> String {
+        format!("{}.{}", self.path, field)
+    }
+
+    fn get(&self, field: &str) -> Result<LiteralValue, String> {
+        get(&self.value, field).ok_or_else(|| self.error(&format!("a {} node needs the field '{}'", self.kind, field)))
+    }
+
+    fn string(&self, field: &str) -> Result<String, String> {
+        match self.get(field)? {
+            LiteralValue::StringValue(text) => Ok(text),
+            _ => Err(error(&self.path(field), "expected a string")),
+        }
+    }
+
+    fn list(&self, field: &str) -> Result<Vec<LiteralValue>, String> {
+        items(&self.get(field)?, &self.path(field))
+    }
+
+    // A token the node holds without a field for it, such as a keyword.
+    fn token(&self, token_type: TokenType, lexeme: &str) -> Token {
+        Token::new(
+            token_type,
+            lexeme.to_string(),
+            None,
+            self.position.line,
+            self.position.offset,
+        )
+    }
+
+    // Scans `text`, which must be a single token of one of the given types.
+    fn scan(&self, text: &str, path: &str, types: &[TokenType], expected: &str) -> Result<Token, String> {
+        let tokens = Scanner::new(text).scan_tokens().unwrap_or_default();
+        match &tokens[..] {
+            [token, _] if types.contains(&token.token_type) && token.lexeme == text => {
+                let mut token = token.clone();
+                token.line_number = self.position.line;
+                token.offset = self.position.offset;
+                Ok(token)
+            }
+            _ => Err(error(path, &format!("{:?} is not {}", text, expected))),
+        }
+    }
+
+    fn name(&self, field: &str) -> Result<Token, String> {
+        let name = self.string(field)?;
+        self.scan(&name, &self.path(field), &[TokenType::IDENTIFIER], "a name")
+    }
+
+    fn names(&self, field: &str) -> Result<Vec<Token>, String> {
+        let mut names = vec![];
+        for (index, item) in self.list(field)?.iter().enumerate() {
+            let path = format!("{}[{}]", self.path(field), index);
+            match item {
+                LiteralValue::StringValue(name) => {
+                    names.push(self.scan(name, &path, &[TokenType::IDENTIFIER], "a name")?)
+                }
+                _ => return Err(error(&path, "expected a string")),
+            }
+        }
+        Ok(names)
+    }
+
+    fn operator(&self, types: &[TokenType]) -> Result<Token, String> {
+        let operator = self.string("operator")?;
+        let expected = format!("an operator of a {} node", self.kind);
+        self.scan(&operator, &self.path("operator"), types, &expected)
+    }
+
+    fn module_path(&self) -> Result<Token, String> {
+        let path = self.string("path")?;
+        if path.contains('"') {
+            return Err(error(&self.path("path"), "strings cannot contain '\"'"));
+        }
+        self.scan(
+            &format!("\"{}\"", path),
+            &self.path("path"),
+            &[TokenType::STRING],
+            "a module path",
+        )
+    }
+}
+
+fn error(path: &str, message: &str) -> String {
+    format!("Invalid syntax tree at {}: {}", path, message)
+}
+
+fn items(value: &LiteralValue, path: &str) -> Result<Vec<LiteralValue>, String> {
+    match value {
+        LiteralValue::List(items) => Ok(items.borrow().clone()),
+        _ => Err(error(path, "expected a list")),
+    }
+}
+
+struct Reader {
+    function_depth: usize,
+}
+
+impl Reader {
+    fn program(&mut self, program: &LiteralValue) -> Result<Vec<Stmt>, String> {
+        let position = Position { offset: 0, line: 1 };
+        let mut stmts = vec![];
+        for (index, item) in items(program, "$")?.iter().enumerate() {
+            let path = format!("$[{}]", index);
+            let fields = self.fields(item, path, position)?;
+            if fields.kind == "export" {
+                let declaration =
+                    self.stmt(&fields.get("declaration")?, fields.path("declaration"), fields.position)?;
+                if !matches!(declaration, Stmt::Var { .. } | Stmt::Function { .. }) {
+                    return Err(fields.error("only a var or a function can be exported"));
+                }
+                stmts.push(Stmt::Export {
+                    keyword: fields.token(TokenType::EXPORT, "export"),
+                    declaration: Box::new(declaration),
+                });
+            } else {
+                stmts.push(self.stmt_fields(fields)?);
+            }
+        }
+        Ok(stmts)
+    }
+
+    fn fields(&self, value: &LiteralValue, path: String, parent: Position) -> Result<Fields, String> {
+        if !matches!(value, LiteralValue::Map(_)) {
+            return Err(error(&path, "expected a node"));
+        }
+        let kind = match get(value, "kind") {
+            Some(LiteralValue::StringValue(kind)) => kind,
+            _ => return Err(error(&path, "a node needs a 'kind' string")),
+        };
+        let position = match get(value, "span") {
+            None | Some(LiteralValue::Nil) => parent,
+            Some(span) => {
+                let number = |field: &str| match get(&span, field) {
+                    Some(LiteralValue::Number(x)) if x >= 0.0 && x.fract() == 0.0 => Ok(x as u64),
+                    _ => Err(error(&format!("{}.span.{}", path, field), "expected a whole number")),
+                };
+                number("end")?;
+                Position {
+                    offset: number("start")? as usize,
+                    line: number("line")?,
+                }
+            }
+        };
+        Ok(Fields {
+            value: value.clone(),
+            kind,
+            path,
+            position,
+        })
+    }
+
+    fn stmts(&mut self, value: &LiteralValue, path: String, parent: Position) -> Result<Vec<Stmt>, String> {
+        let mut stmts = vec![];
+        for (index, item) in items(value, &path)?.iter().enumerate() {
+            stmts.push(self.stmt(item, format!("{}[{}]", path, index), parent)?);
+        }
+        Ok(stmts)
+    }
+
+    fn stmt(&mut self, value: &LiteralValue, path: String, parent: Position) -> Result<Stmt, String> {
+        let fields = self.fields(value, path, parent)?;
+        if fields.kind == "export" {
+            return Err(fields.error("'export' is only allowed at the top level"));
+        }
+        self.stmt_fields(fields)
+    }
+
+    // The body of an `if` or `while`, which cannot be a declaration.
+    fn branch(&mut self, value: &LiteralValue, path: String, parent: Position) -> Result<Box<Stmt>, String> {
+        let fields = self.fields(value, path, parent)?;
+        if matches!(
+            fields.kind.as_str(),
+            "var" | "function" | "import" | "import_from" | "export"
+        ) {
+            return Err(fields.error(&format!(
+                "a {} declaration cannot be a branch or loop body",
+                fields.kind
+            )));
+        }
+        Ok(Box::new(self.stmt_fields(fields)?))
+    }
+
+    fn body(&mut self, fields: &Fields, field: &str) -> Result<Vec<Stmt>, String> {
+        self.stmts(&fields.get(field)?, fields.path(field), fields.position)
+    }
+
+    fn function_body(&mut self, fields: &Fields) -> Result<Rc<Vec<Stmt>>, String> {
+        self.function_depth += 1;
+        let body = self.body(fields, "body");
+        self.function_depth -= 1;
+        Ok(Rc::new(body?))
+    }
+
+    fn child_expr(&mut self, fields: &Fields, field: &str) -> Result<Expr, String> {
+        self.expr(&fields.get(field)?, fields.path(field), fields.position)
+    }
+
+    fn optional_expr(&mut self, fields: &Fields, field: &str) -> Result<Option<Expr>, String> {
+        match fields.get(field)? {
+            LiteralValue::Nil => Ok(None),
+            value => Ok(Some(self.expr(&value, fields.path(field), fields.position)?)),
+        }
+    }
+
+    fn stmt_fields(&mut self, fields: Fields) -> Result<Stmt, String> {
+        use TokenType::*;
+        let stmt = match fields.kind.as_str() {
+            "expression" => Stmt::Expression {
+                expression: self.child_expr(&fields, "expression")?,
+            },
+            "print" => Stmt::Print {
+                keyword: fields.token(PRINT, "print"),
+                expression: self.child_expr(&fields, "expression")?,
+            },
+            "var" => {
+                let name = fields.name("name")?;
+                let initialiser = match self.optional_expr(&fields, "initialiser")? {
+                    Some(initialiser) => initialiser,
+                    None => Expr::Literal {
+                        token: name.clone(),
+                        value: LiteralValue::Nil,
+                    },
+                };
+                Stmt::Var {
+                    keyword: fields.token(VAR, "var"),
+                    name,
+                    initialiser,
+                }
+            }
+            "block" => Stmt::Block {
+                brace: fields.token(LEFT_BRACE, "{"),
+                statements: self.body(&fields, "statements")?,
+            },
+            "function" => Stmt::Function {
+                keyword: fields.token(FUN, "fun"),
+                name: fields.name("name")?,
+                params: fields.names("params")?,
+                body: self.function_body(&fields)?,
+            },
+            "if" => Stmt::If {
+                keyword: fields.token(IF, "if"),
+                condition: self.child_expr(&fields, "condition")?,
+                then_branch: self.branch(&fields.get("then_branch")?, fields.path("then_branch"), fields.position)?,
+                else_branch: match fields.get("else_branch")? {
+                    LiteralValue::Nil => None,
+                    value => Some(self.branch(&value, fields.path("else_branch"), fields.position)?),
+                },
+            },
+            "while" => Stmt::While {
+                keyword: fields.token(WHILE, "while"),
+                condition: match self.optional_expr(&fields, "condition")? {
+                    Some(condition) => condition,
+                    None => Expr::Literal {
+                        token: fields.token(SEMICOLON, ";"),
+                        value: LiteralValue::True,
+                    },
+                },
+                body: self.branch(&fields.get("body")?, fields.path("body"), fields.position)?,
+            },
+            "return" => {
+                if self.function_depth == 0 {
+                    return Err(fields.error("can't return from top-level code"));
+                }
+                Stmt::Return {
+                    keyword: fields.token(RETURN, "return"),
+                    value: self.optional_expr(&fields, "value")?,
+                }
+            }
+            "throw" => Stmt::Throw {
+                keyword: fields.token(THROW, "throw"),
+                value: self.child_expr(&fields, "value")?,
+            },
+            "import" => Stmt::Import {
+                keyword: fields.token(IMPORT, "import"),
+                path: fields.module_path()?,
+                alias: fields.name("alias")?,
+            },
+            "import_from" => Stmt::ImportFrom {
+                keyword: fields.token(IDENTIFIER, "from"),
+                path: fields.module_path()?,
+                names: fields.names("names")?,
+            },
+            "try" => {
+                let body = self.body(&fields, "body")?;
+                let catch_clause = match fields.get("catch")? {
+                    LiteralValue::Nil => None,
+                    value => {
+                        let catch = self.fields(&value, fields.path("catch"), fields.position)?;
+                        if catch.kind != "catch" {
+                            return Err(catch.error("expected a catch node"));
+                        }
+                        Some((catch.name("name")?, self.body(&catch, "body")?))
+                    }
+                };
+                let finally_body = match fields.get("finally")? {
+                    LiteralValue::Nil => None,
+                    _ => Some(self.body(&fields, "finally")?),
+                };
+                if catch_clause.is_none() && finally_body.is_none() {
+                    return Err(fields.error("a try node needs a catch or a finally"));
+                }
+                Stmt::Try {
+                    keyword: fields.token(TRY, "try"),
+                    body,
+                    catch_clause,
+                    finally_body,
+                }
+            }
+            "error" => Stmt::Error {
+                token: fields.token(UNKNOWN, ""),
+                message: fields.string("message")?,
+            },
+            kind => return Err(fields.error(&format!("unknown statement kind {:?}", kind))),
+        };
+        Ok(stmt)
+    }
+
+    fn exprs(&mut self, fields: &Fields, field: &str) -> Result<Vec<Expr>, String> {
+        let mut exprs = vec![];
+        for (index, item) in fields.list(field)?.iter().enumerate() {
+            exprs.push(self.expr(item, format!("{}[{}]", fields.path(field), index), fields.position)?);
+        }
+        Ok(exprs)
+    }
+
+    fn expr(&mut self, value: &LiteralValue, path: String, parent: Position) -> Result<Expr, String> {
+        use TokenType::*;
+        let fields = self.fields(value, path, parent)?;
+        let boxed = |reader: &mut Self, field: &str| reader.child_expr(&fields, field).map(Box::new);
+        let expr = match fields.kind.as_str() {
+            "assign" => Expr::Assign {
+                name: fields.name("name")?,
+                value: boxed(self, "value")?,
+            },
+            "binary" => Expr::Binary {
+                left: boxed(self, "left")?,
+                operator: fields.operator(&[
+                    BANG_EQUAL,
+                    EQUAL_EQUAL,
+                    GREATER,
+                    GREATER_EQUAL,
+                    LESS,
+                    LESS_EQUAL,
+                    MINUS,
+                    PLUS,
+                    SLASH,
+                    STAR,
+                ])?,
+                right: boxed(self, "right")?,
+            },
+            "call" => Expr::Call {
+                callee: boxed(self, "callee")?,
+                paren: fields.token(RIGHT_PAREN, ")"),
+                arguments: self.exprs(&fields, "arguments")?,
+            },
+            "get" => Expr::Get {
+                object: boxed(self, "object")?,
+                name: fields.name("name")?,
+            },
+            "index" => Expr::Index {
+                object: boxed(self, "object")?,
+                bracket: fields.token(LEFT_BRACKET, "["),
+                index: boxed(self, "index")?,
+            },
+            "set_index" => Expr::SetIndex {
+                object: boxed(self, "object")?,
+                bracket: fields.token(LEFT_BRACKET, "["),
+                index: boxed(self, "index")?,
+                value: boxed(self, "value")?,
+            },
+            "lambda" => Expr::Lambda {
+                keyword: fields.token(FUN, "fun"),
+                params: fields.names("params")?,
+                body: self.function_body(&fields)?,
+            },
+            "list" => Expr::List {
+                bracket: fields.token(LEFT_BRACKET, "["),
+                elements: self.exprs(&fields, "elements")?,
+            },
+            "map" => {
+                let mut entries = vec![];
+                for (index, item) in fields.list("entries")?.iter().enumerate() {
+                    let entry = self.fields(item, format!("{}[{}]", fields.path("entries"), index), fields.position)?;
+                    if entry.kind != "entry" {
+                        return Err(entry.error("expected an entry node"));
+                    }
+                    entries.push((self.child_expr(&entry, "key")?, self.child_expr(&entry, "value")?));
+                }
+                Expr::Map {
+                    brace: fields.token(LEFT_BRACE, "{"),
+                    entries,
+                }
+            }
+            "grouping" => Expr::Grouping {
+                paren: fields.token(LEFT_PAREN, "("),
+                expression: boxed(self, "expression")?,
+            },
+            "literal" => {
+                let value = fields.get("value")?;
+                let token = match &value {
+                    LiteralValue::Number(x) => Token::new(
+                        NUMBER,
+                        x.to_string(),
+                        Some(scanner::LiteralValue::FValue(*x)),
+                        fields.position.line,
+                        fields.position.offset,
+                    ),
+                    LiteralValue::StringValue(text) if text.contains('"') => {
+                        return Err(error(&fields.path("value"), "strings cannot contain '\"'"));
+                    }
+                    LiteralValue::StringValue(text) => Token::new(
+                        STRING,
+                        format!("\"{}\"", text),
+                        Some(scanner::LiteralValue::StringValue(text.clone())),
+                        fields.position.line,
+                        fields.position.offset,
+                    ),
+                    LiteralValue::True => fields.token(TRUE, "true"),
+                    LiteralValue::False => fields.token(FALSE, "false"),
+                    LiteralValue::Nil => fields.token(NIL, "nil"),
+                    _ => {
+                        return Err(error(
+                            &fields.path("value"),
+                            "expected a number, string, boolean or null",
+                        ));
+                    }
+                };
+                Expr::Literal { token, value }
+            }
+            "logical" => Expr::Logical {
+                left: boxed(self, "left")?,
+                operator: fields.operator(&[AND, OR])?,
+                right: boxed(self, "right")?,
+            },
+            "unary" => Expr::Unary {
+                operator: fields.operator(&[BANG, MINUS])?,
+                right: boxed(self, "right")?,
+            },
+            "variable" => Expr::Variable {
+                name: fields.name("name")?,
+            },
+            kind => return Err(fields.error(&format!("unknown expression kind {:?}", kind))),
+        };
+        Ok(expr)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::Parser;
+    use crate::printer;
+
+    fn parse(source: &str) -> Vec<Stmt> {
+        Parser::new(Scanner::new(source).scan_tokens().unwrap())
+            .parse()
+            .unwrap()
+    }
+
+    const PROGRAM: &str = "import \"lib/util.fnky\" as util;
+from \"lib/math.fnky\" import max, min;
+var nothing;
+export fun add(a, b) {
+    return a + b * -(1);
+}
+var f = fun (x) { return {\"k\": [x, true, nil], 1: x[0]}; };
+for (var i = 0; i < 3; i = i + 1) { if (!(i == 1) and i != 2) print i; else print f(i).k; }
+try { throw \"e\"; } catch (e) { nothing = e; } finally { nothing[0] = 1; }
+while (true) {}
+";
+
+    #[test]
+    fn json_round_trips() {
+        let stmts = parse(PROGRAM);
+        let dumped = to_json(&stmts);
+        let read = from_json(&dumped).unwrap();
+        assert_eq!(printer::program(&read), printer::program(&stmts));
+        // Tokens are placed at the start of their node, so ends can shrink
+        // on the first read, but the tree no longer changes after it.
+        let dumped = to_json(&read);
+        assert_eq!(to_json(&from_json(&dumped).unwrap()), dumped);
+    }
+
+    #[test]
+    fn nodes_have_spans() {
+        let source = "print 1 + (2);\nvar x;";
+        assert_eq!(
+            to_sexpr(&parse(source)),
+            "(print (span 0 12 1) (expression (binary (span 6 12 1) (left (literal (span 6 7 1) (value 1))) \
+             (operator \"+\") (right (grouping (span 10 12 1) (expression (literal (span 11 12 1) (value 2))))))))\n\
+             (var (span 15 20 2) (name \"x\") (initialiser nil))\n"
+        );
+
+        // The block around a `for` loop's body and increment has no brace.
+        let looped = to_sexpr(&parse("for (var i = 0; i < 1; i = i + 1) x;"));
+        assert!(looped.starts_with("(block (span 0 35 1)"));
+        assert!(looped.contains("(body (block (span 23 35 1)"));
+
+        // The condition the parser stands in for `for (;;)` is not dumped.
+        assert_eq!(
+            to_sexpr(&parse("for (;;) x;")),
+            "(while (span 0 10 1) (condition nil) (body (expression (span 9 10 1) \
+             (expression (variable (span 9 10 1) (name \"x\"))))))\n"
+        );
+        let dumped = to_json(&parse("for (;;) x;"));
+        assert!(dumped.contains("\"condition\": null"));
+        assert_eq!(to_json(&from_json(&dumped).unwrap()), dumped);
+
+        let json = to_json(&parse("x;"));
+        assert_eq!(
+            json::parse(&json).unwrap(),
+            json::parse(
+                r#"[{"kind": "expression", "span": {"start": 0, "end": 1, "line": 1},
+                     "expression": {"kind": "variable", "span": {"start": 0, "end": 1, "line": 1}, "name": "x"}}]"#
+            )
+            .unwrap()
+        );
+    }
+
+    #[test]
+    fn read_without_spans() {
+        let source = r#"[
+            {"kind": "function", "name": "greet", "params": ["who"], "body": [
+                {"kind": "return", "value": {"kind": "binary", "operator": "+",
+                    "left": {"kind": "literal", "value": "hello, "},
+                    "right": {"kind": "variable", "name": "who"}}}
+            ]},
+            {"kind": "print", "span": {"start": 0, "end": 0, "line": 7},
+             "expression": {"kind": "call", "callee": {"kind": "variable", "name": "greet"},
+                            "arguments": [{"kind": "literal", "value": 42}]}}
+        ]"#;
+        let stmts = from_json(source).unwrap();
+        assert_eq!(
+            printer::program(&stmts),
+            "fun greet(who) {\n    return \"hello, \" + who;\n}\nprint greet(42);\n"
+        );
+        match &stmts[1] {
+            Stmt::Print { keyword, .. } => assert_eq!(keyword.line_number, 7),
+            other => panic!("expected a print statement, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn reject_invalid_trees() {
+        let cases = [
+            ("{}", "Invalid syntax tree at $: expected a list"),
+            (
+                r#"[{"kind": "loop"}]"#,
+                "Invalid syntax tree at $[0]: unknown statement kind \"loop\"",
+            ),
+            (
+                r#"[{"kind": "var", "name": "if", "initialiser": {"kind": "literal", "value": null}}]"#,
+                "Invalid syntax tree at $[0].name: \"if\" is not a name",
+            ),
+            (
+                r#"[{"kind": "expression", "expression": {"kind": "binary", "operator": "and",
+                    "left": {"kind": "variable", "name": "a"}, "right": {"kind": "variable", "name": "b"}}}]"#,
+                "Invalid syntax tree at $[0].expression.operator: \"and\" is not an operator of a binary node",
+            ),
+            (
+                r#"[{"kind": "return", "value": null}]"#,
+                "Invalid syntax tree at $[0]: can't return from top-level code",
+            ),
+            (
+                r#"[{"kind": "block", "statements": [{"kind": "export", "declaration": null}]}]"#,
+                "Invalid syntax tree at $[0].statements[0]: 'export' is only allowed at the top level",
+            ),
+            (
+                r#"[{"kind": "print"}]"#,
+                "Invalid syntax tree at $[0]: a print node needs the field 'expression'",
+            ),
+        ];
+        for (source, message) in cases {
+            assert_eq!(from_json(source).unwrap_err(), message);
+        }
+    }
+}
@@ -0,0 +1,92 @@
+use std::cell::RefCell;
+use std::fmt;
+use std::rc::Rc;
+
+use crate::environment::Environment;
+use crate::error::RuntimeError;
+use crate::expr::LiteralValue;
+use crate::interpreter::Interpreter;
+use crate::scanner::Token;
+use crate::stmt::Stmt;
+
+/// A function declared in a script with `fun`, together with the
+/// environment it closes over and the file it was declared in.
+pub struct Function {
+    pub name: Token,
+    pub params: Vec<Token>,
+    pub body: Rc<Vec<Stmt>>,
+    pub closure: Rc<RefCell<Environment>>,
+    pub file: String,
+}
+
+impl Function {
+    pub fn arity(&self) -> usize {
+        self.params.len()
+    }
+}
+
+// Functions are compared by identity: two declarations with the same body
+// are still different values.
+impl PartialEq for Function {
+    fn eq(&self, other: &Self) -> bool {
+        std::ptr::eq(self, other)
+    }
+}
+
+impl fmt::Debug for Function {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "<fn {}>", self.name.lexeme)
+    }
+}
+
+/// The signature of a function implemented in Rust. It receives the
+/// interpreter (to call back into scripts or read its configuration) and
+/// the already evaluated arguments.
+pub type NativeFn = dyn Fn(&mut Interpreter, Vec<LiteralValue>) -> Result<LiteralValue, RuntimeError>;
+
+/// How many arguments a native function accepts.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Arity {
+    Exact(usize),
+    Range(usize, usize),
+    AtLeast(usize),
+}
+
+impl Arity {
+    pub fn accepts(&self, count: usize) -> bool {
+        match *self {
+            Arity::Exact(n) => count == n,
+            Arity::Range(min, max) => (min..=max).contains(&count),
+            Arity::AtLeast(min) => count >= min,
+        }
+    }
+}
+
+impl fmt::Display for Arity {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Arity::Exact(n) => write!(f, "{}", n),
+            Arity::Range(min, max) => write!(f, "{} to {}", min, max),
+            Arity::AtLeast(min) => write!(f, "at least {}", min),
+        }
+    }
+}
+
+/// A function provided by the host instead of declared in a script.
+pub struct NativeFunction {
+    pub name: String,
+    pub arity: Arity,
+    pub fun: Rc<NativeFn>,
+}
+
+impl PartialEq for NativeFunction {
+    fn eq(&self, other: &Self) -> bool {
+        std::ptr::eq(self, other)
+    }
+}
+
+impl fmt::Debug for NativeFunction {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "<native fn {}>", self.name)
+    }
+}
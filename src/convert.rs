@@ -0,0 +1,284 @@
+use std::rc::Rc;
+
+use crate::callable::NativeFn;
+use crate::error::RuntimeError;
+use crate::expr::LiteralValue;
+
+/// Converts a Rust value into a runtime value.
+pub trait IntoValue {
+    fn into_value(self) -> LiteralValue;
+}
+
+/// Converts a runtime value into a Rust value. On failure it returns a
+/// description of what was expected, such as "Number" or "integer".
+pub trait FromValue: Sized {
+    fn from_value(value: &LiteralValue) -> Result<Self, String>;
+}
+
+impl IntoValue for LiteralValue {
+    fn into_value(self) -> LiteralValue {
+        self
+    }
+}
+
+impl FromValue for LiteralValue {
+    fn from_value(value: &LiteralValue) -> Result<Self, String> {
+        Ok(value.clone())
+    }
+}
+
+impl IntoValue for f64 {
+    fn into_value(self) -> LiteralValue {
+        LiteralValue::Number(self)
+    }
+}
+
+impl FromValue for f64 {
+    fn from_value(value: &LiteralValue) -> Result<Self, String> {
+        match value {
+            LiteralValue::Number(x) => Ok(*x),
+            _ => Err("Number".to_string()),
+        }
+    }
+}
+
+impl IntoValue for f32 {
+    fn into_value(self) -> LiteralValue {
+        LiteralValue::Number(self as f64)
+    }
+}
+
+impl FromValue for f32 {
+    fn from_value(value: &LiteralValue) -> Result<Self, String> {
+        f64::from_value(value).map(|x| x as f32)
+    }
+}
+
+// Integers convert from numbers without a fractional part that fit the
+// target type.
+macro_rules! integer_conversions {
+    ($($int:ty),*) => {
+        $(
+            impl IntoValue for $int {
+                fn into_value(self) -> LiteralValue {
+                    LiteralValue::Number(self as f64)
+                }
+            }
+
+            impl FromValue for $int {
+                fn from_value(value: &LiteralValue) -> Result<Self, String> {
+                    match value {
+                        LiteralValue::Number(x)
+                            if x.fract() == 0.0 && *x >= <$int>::MIN as f64 && *x <= <$int>::MAX as f64 =>
+                        {
+                            Ok(*x as $int)
+                        }
+                        _ => Err("integer".to_string()),
+                    }
+                }
+            }
+        )*
+    };
+}
+
+integer_conversions!(i32, i64, u32, u64, usize);
+
+impl IntoValue for bool {
+    fn into_value(self) -> LiteralValue {
+        LiteralValue::from_bool(self)
+    }
+}
+
+impl FromValue for bool {
+    fn from_value(value: &LiteralValue) -> Result<Self, String> {
+        match value {
+            LiteralValue::True => Ok(true),
+            LiteralValue::False => Ok(false),
+            _ => Err("Boolean".to_string()),
+        }
+    }
+}
+
+impl IntoValue for String {
+    fn into_value(self) -> LiteralValue {
+        LiteralValue::StringValue(self)
+    }
+}
+
+impl IntoValue for &str {
+    fn into_value(self) -> LiteralValue {
+        LiteralValue::StringValue(self.to_string())
+    }
+}
+
+impl FromValue for String {
+    fn from_value(value: &LiteralValue) -> Result<Self, String> {
+        match value {
+            LiteralValue::StringValue(s) => Ok(s.clone()),
+            _ => Err("String".to_string()),
+        }
+    }
+}
+
+impl IntoValue for () {
+    fn into_value(self) -> LiteralValue {
+        LiteralValue::Nil
+    }
+}
+
+impl<T: IntoValue> IntoValue for Option<T> {
+    fn into_value(self) -> LiteralValue {
+        match self {
+            Some(value) => value.into_value(),
+            None => LiteralValue::Nil,
+        }
+    }
+}
+
+impl<T: FromValue> FromValue for Option<T> {
+    fn from_value(value: &LiteralValue) -> Result<Self, String> {
+        match value {
+            LiteralValue::Nil => Ok(None),
+            other => T::from_value(other).map(Some).map_err(|expected| format!("{} or nil", expected)),
+        }
+    }
+}
+
+impl<T: IntoValue> IntoValue for Vec<T> {
+    fn into_value(self) -> LiteralValue {
+        LiteralValue::list(self.into_iter().map(IntoValue::into_value).collect())
+    }
+}
+
+impl<T: FromValue> FromValue for Vec<T> {
+    fn from_value(value: &LiteralValue) -> Result<Self, String> {
+        match value {
+            LiteralValue::List(items) => items
+                .borrow()
+                .iter()
+                .map(T::from_value)
+                .collect::<Result<Vec<T>, String>>()
+                .map_err(|expected| format!("List of {}", expected)),
+            _ => Err("List".to_string()),
+        }
+    }
+}
+
+/// What a registered Rust closure may return: a plain value, or a
+/// `Result` whose error becomes a catchable runtime error.
+pub trait IntoNativeResult {
+    fn into_native_result(self) -> Result<LiteralValue, RuntimeError>;
+}
+
+impl<T: IntoValue> IntoNativeResult for T {
+    fn into_native_result(self) -> Result<LiteralValue, RuntimeError> {
+        Ok(self.into_value())
+    }
+}
+
+impl<T: IntoValue> IntoNativeResult for Result<T, RuntimeError> {
+    fn into_native_result(self) -> Result<LiteralValue, RuntimeError> {
+        self.map(IntoValue::into_value)
+    }
+}
+
+impl<T: IntoValue> IntoNativeResult for Result<T, String> {
+    fn into_native_result(self) -> Result<LiteralValue, RuntimeError> {
+        self.map(IntoValue::into_value)
+            .map_err(|message| RuntimeError::new("Error", message, 0))
+    }
+}
+
+/// Converts argument `index` (0-based) of native function `name`, reporting
+/// a TypeError that names the argument when it has the wrong type.
+pub fn argument<T: FromValue>(name: &str, index: usize, value: &LiteralValue) -> Result<T, RuntimeError> {
+    T::from_value(value).map_err(|expected| {
+        RuntimeError::type_error(
+            format!(
+                "{}() argument {} must be {}, not {}",
+                name,
+                index + 1,
+                expected,
+                value.to_type()
+            ),
+            0,
+        )
+    })
+}
+
+/// Rust closures that can be registered as native functions. The arity and
+/// argument types come from the closure's signature.
+pub trait IntoNative<Args> {
+    fn arity() -> usize;
+    fn into_native(self, name: &str) -> Rc<NativeFn>;
+}
+
+macro_rules! impl_into_native {
+    ($count:expr; $($arg:ident $value:ident),*) => {
+        impl<F, R, $($arg),*> IntoNative<($($arg,)*)> for F
+        where
+            F: Fn($($arg),*) -> R + 'static,
+            R: IntoNativeResult,
+            $($arg: FromValue,)*
+        {
+            fn arity() -> usize {
+                $count
+            }
+
+            #[allow(unused_variables, unused_mut)]
+            fn into_native(self, name: &str) -> Rc<NativeFn> {
+                let name = name.to_string();
+                Rc::new(move |_interpreter, arguments| {
+                    let mut arguments = arguments.iter().enumerate();
+                    $(
+                        let $value: $arg = {
+                            let (index, value) = arguments.next().expect("arity is checked before the call");
+                            argument(&name, index, value)?
+                        };
+                    )*
+                    self($($value),*).into_native_result()
+                })
+            }
+        }
+    };
+}
+
+impl_into_native!(0;);
+impl_into_native!(1; A a);
+impl_into_native!(2; A a, B b);
+impl_into_native!(3; A a, B b, C c);
+impl_into_native!(4; A a, B b, C c, D d);
+impl_into_native!(5; A a, B b, C c, D d, E e);
+impl_into_native!(6; A a, B b, C c, D d, E e, G g);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn integers_reject_fractions() {
+        assert_eq!(i64::from_value(&LiteralValue::Number(4.0)), Ok(4));
+        assert_eq!(i64::from_value(&LiteralValue::Number(4.5)), Err("integer".to_string()));
+        assert_eq!(usize::from_value(&LiteralValue::Number(-1.0)), Err("integer".to_string()));
+    }
+
+    #[test]
+    fn option_maps_nil() {
+        assert_eq!(Option::<String>::from_value(&LiteralValue::Nil), Ok(None));
+        assert_eq!(
+            Option::<String>::from_value(&LiteralValue::True),
+            Err("String or nil".to_string())
+        );
+        assert_eq!(None::<f64>.into_value(), LiteralValue::Nil);
+        assert_eq!(Some("hi").into_value(), LiteralValue::StringValue("hi".to_string()));
+    }
+
+    #[test]
+    fn vectors_map_to_lists() {
+        let list = vec![1, 2].into_value();
+        assert_eq!(list, LiteralValue::list(vec![LiteralValue::Number(1.0), LiteralValue::Number(2.0)]));
+        assert_eq!(Vec::<i64>::from_value(&list), Ok(vec![1, 2]));
+        assert_eq!(Vec::<String>::from_value(&list), Err("List of String".to_string()));
+        assert_eq!(Vec::<i64>::from_value(&LiteralValue::Nil), Err("List".to_string()));
+    }
+}
@@ -0,0 +1,289 @@
+use std::fmt;
+
+const MS_PER_DAY: i64 = 86_400_000;
+// A little more than the 292 million years either side of 1970 that fit.
+const MAX_YEAR: u64 = 300_000_000;
+
+/// A point in time with millisecond precision, always in UTC. It is stored
+/// as milliseconds since the Unix epoch, so arithmetic is plain addition.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Date {
+    pub millis: i64,
+}
+
+/// A date split into calendar fields. `month` and `day` start at 1.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Fields {
+    pub year: i64,
+    pub month: u32,
+    pub day: u32,
+    pub hour: u32,
+    pub minute: u32,
+    pub second: u32,
+    pub millisecond: u32,
+}
+
+impl Date {
+    pub fn from_millis(millis: i64) -> Self {
+        Self { millis }
+    }
+
+    /// Builds a date from calendar fields, rejecting impossible ones like
+    /// February 30th or hour 24 and ones too far from 1970 to store.
+    pub fn from_fields(fields: Fields) -> Result<Self, String> {
+        let Fields {
+            year,
+            month,
+            day,
+            hour,
+            minute,
+            second,
+            millisecond,
+        } = fields;
+        if !(1..=12).contains(&month) {
+            return Err(format!("month {} is out of range", month));
+        }
+        if day < 1 || day > days_in_month(year, month) {
+            return Err(format!("day {} is out of range for {}-{:02}", day, year, month));
+        }
+        if hour > 23 || minute > 59 || second > 59 || millisecond > 999 {
+            return Err(format!(
+                "time {:02}:{:02}:{:02}.{:03} is out of range",
+                hour, minute, second, millisecond
+            ));
+        }
+        // Years this far out are beyond the range of `millis`, and would
+        // overflow inside `days_from_civil`.
+        let out_of_range = || format!("year {} is out of range", year);
+        if year.unsigned_abs() > MAX_YEAR {
+            return Err(out_of_range());
+        }
+        let time = ((hour as i64 * 60 + minute as i64) * 60 + second as i64) * 1000 + millisecond as i64;
+        days_from_civil(year, month, day)
+            .checked_mul(MS_PER_DAY)
+            .and_then(|millis| millis.checked_add(time))
+            .map(Self::from_millis)
+            .ok_or_else(out_of_range)
+    }
+
+    pub fn fields(&self) -> Fields {
+        let days = self.millis.div_euclid(MS_PER_DAY);
+        let time = self.millis.rem_euclid(MS_PER_DAY);
+        let (year, month, day) = civil_from_days(days);
+        Fields {
+            year,
+            month,
+            day,
+            hour: (time / 3_600_000) as u32,
+            minute: (time / 60_000 % 60) as u32,
+            second: (time / 1000 % 60) as u32,
+            millisecond: (time % 1000) as u32,
+        }
+    }
+
+    /// 1 for Monday through 7 for Sunday, as in ISO-8601.
+    pub fn weekday(&self) -> u32 {
+        // 1970-01-01 was a Thursday.
+        ((self.millis.div_euclid(MS_PER_DAY) + 3).rem_euclid(7) + 1) as u32
+    }
+
+    /// Parses an ISO-8601 date (`2024-03-01`) or date and time
+    /// (`2024-03-01T12:30:00Z`, `2024-03-01 12:30:00.250+02:00`). Times
+    /// without an offset are taken to be UTC; dates with an offset are
+    /// converted to UTC.
+    pub fn parse(text: &str) -> Result<Self, String> {
+        let invalid = |reason: &str| format!("Invalid ISO-8601 date '{}': {}", text, reason);
+        let mut cursor = Cursor { text, position: 0 };
+
+        let negative = cursor.eat('-');
+        let year = cursor.digits(4).ok_or_else(|| invalid("expected a four digit year"))? as i64;
+        let year = if negative { -year } else { year };
+        let month = cursor.field('-', 2).ok_or_else(|| invalid("expected '-MM'"))?;
+        let day = cursor.field('-', 2).ok_or_else(|| invalid("expected '-DD'"))?;
+
+        let mut fields = Fields {
+            year,
+            month,
+            day,
+            hour: 0,
+            minute: 0,
+            second: 0,
+            millisecond: 0,
+        };
+        let mut offset_minutes = 0;
+        if cursor.eat('T') || cursor.eat('t') || cursor.eat(' ') {
+            fields.hour = cursor.digits(2).ok_or_else(|| invalid("expected an hour"))?;
+            fields.minute = cursor.field(':', 2).ok_or_else(|| invalid("expected ':MM'"))?;
+            if cursor.peek() == Some(':') {
+                fields.second = cursor.field(':', 2).ok_or_else(|| invalid("expected ':SS'"))?;
+                if cursor.eat('.') {
+                    fields.millisecond = cursor.fraction_millis().ok_or_else(|| invalid("expected fractional seconds"))?;
+                }
+            }
+            match cursor.peek() {
+                Some('Z' | 'z') => {
+                    cursor.position += 1;
+                }
+                Some(sign @ ('+' | '-')) => {
+                    cursor.position += 1;
+                    let hours = cursor.digits(2).ok_or_else(|| invalid("expected an offset hour"))?;
+                    let minutes = cursor.field(':', 2).ok_or_else(|| invalid("expected an offset ':MM'"))?;
+                    if hours > 23 || minutes > 59 {
+                        return Err(invalid("offset is out of range"));
+                    }
+                    offset_minutes = (hours * 60 + minutes) as i64 * if sign == '-' { -1 } else { 1 };
+                }
+                _ => (),
+            }
+        }
+        if cursor.position != text.len() {
+            return Err(invalid("unexpected trailing characters"));
+        }
+
+        let local = Self::from_fields(fields).map_err(|reason| invalid(&reason))?;
+        local.add_millis(-offset_minutes * 60_000).ok_or_else(|| invalid("date is out of range"))
+    }
+
+    /// The date `millis` later, or `None` if that is out of range.
+    pub fn add_millis(&self, millis: i64) -> Option<Self> {
+        self.millis.checked_add(millis).map(Self::from_millis)
+    }
+}
+
+/// Formats as ISO-8601 in UTC, with milliseconds only when there are any:
+/// `2024-03-01T12:30:00Z` or `2024-03-01T12:30:00.250Z`.
+impl fmt::Display for Date {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let fields = self.fields();
+        if fields.year < 0 {
+            write!(f, "-{:04}", -fields.year)?;
+        } else {
+            write!(f, "{:04}", fields.year)?;
+        }
+        write!(
+            f,
+            "-{:02}-{:02}T{:02}:{:02}:{:02}",
+            fields.month, fields.day, fields.hour, fields.minute, fields.second
+        )?;
+        if fields.millisecond != 0 {
+            write!(f, ".{:03}", fields.millisecond)?;
+        }
+        write!(f, "Z")
+    }
+}
+
+struct Cursor<'a> {
+    text: &'a str,
+    position: usize,
+}
+
+impl Cursor<'_> {
+    fn peek(&self) -> Option<char> {
+        self.text[self.position..].chars().next()
+    }
+
+    fn eat(&mut self, expected: char) -> bool {
+        if self.peek() == Some(expected) {
+            self.position += expected.len_utf8();
+            true
+        } else {
+            false
+        }
+    }
+
+    // Exactly `count` ASCII digits.
+    fn digits(&mut self, count: usize) -> Option<u32> {
+        let digits = self.text.get(self.position..self.position + count)?;
+        if !digits.bytes().all(|byte| byte.is_ascii_digit()) {
+            return None;
+        }
+        self.position += count;
+        digits.parse().ok()
+    }
+
+    fn field(&mut self, separator: char, count: usize) -> Option<u32> {
+        if self.eat(separator) { self.digits(count) } else { None }
+    }
+
+    // One or more digits of a fraction of a second, truncated to
+    // milliseconds.
+    fn fraction_millis(&mut self) -> Option<u32> {
+        let digits: String = self.text[self.position..].chars().take_while(char::is_ascii_digit).collect();
+        if digits.is_empty() {
+            return None;
+        }
+        self.position += digits.len();
+        let millis: String = digits.chars().chain("00".chars()).take(3).collect();
+        millis.parse().ok()
+    }
+}
+
+fn is_leap_year(year: i64) -> bool {
+    year % 4 == 0 && (year % 100 != 0 || year % 400 == 0)
+}
+
+fn days_in_month(year: i64, month: u32) -> u32 {
+    match month {
+        2 if is_leap_year(year) => 29,
+        2 => 28,
+        4 | 6 | 9 | 11 => 30,
+        _ => 31,
+    }
+}
+
+// Days since 1970-01-01 of a proleptic Gregorian date, and back. These are
+// Howard Hinnant's `days_from_civil` and `civil_from_days` algorithms.
+fn days_from_civil(year: i64, month: u32, day: u32) -> i64 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let year_of_era = year.rem_euclid(400);
+    let month_from_march = (month as i64 + 9) % 12;
+    let day_of_year = (153 * month_from_march + 2) / 5 + day as i64 - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    era * 146_097 + day_of_era - 719_468
+}
+
+fn civil_from_days(days: i64) -> (i64, u32, u32) {
+    let days = days + 719_468;
+    let era = days.div_euclid(146_097);
+    let day_of_era = days.rem_euclid(146_097);
+    let year_of_era = (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month_from_march = (5 * day_of_year + 2) / 153;
+    let day = (day_of_year - (153 * month_from_march + 2) / 5 + 1) as u32;
+    let month = if month_from_march < 10 { month_from_march + 3 } else { month_from_march - 9 } as u32;
+    let year = year_of_era + era * 400 + if month <= 2 { 1 } else { 0 };
+    (year, month, day)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_and_format_iso_8601() {
+        assert_eq!(Date::parse("1970-01-01").unwrap(), Date::from_millis(0));
+        assert_eq!(Date::parse("2000-02-29T12:30:15.5Z").unwrap().to_string(), "2000-02-29T12:30:15.500Z");
+        assert_eq!(Date::parse("2024-03-01 01:00+02:00").unwrap().to_string(), "2024-02-29T23:00:00Z");
+        assert_eq!(Date::parse("1969-12-31T23:59:59.999Z").unwrap().millis, -1);
+        assert_eq!(Date::from_millis(-1).to_string(), "1969-12-31T23:59:59.999Z");
+        assert_eq!(Date::parse("2024-01-15").unwrap().weekday(), 1);
+    }
+
+    #[test]
+    fn reject_invalid_dates() {
+        assert!(Date::parse("2023-02-29").unwrap_err().contains("day 29 is out of range"));
+        assert!(Date::parse("2024-01-01T24:00").unwrap_err().contains("out of range"));
+        assert!(Date::parse("2024-1-01").unwrap_err().contains("expected '-MM'"));
+        assert!(Date::parse("2024-01-01Tnoon").is_err());
+        assert!(Date::parse("2024-01-01 extra").is_err());
+    }
+
+    #[test]
+    fn calendar_round_trips() {
+        for days in [-719_468, -1, 0, 59, 10_956, 2_932_896] {
+            let (year, month, day) = civil_from_days(days);
+            assert_eq!(days_from_civil(year, month, day), days);
+        }
+    }
+}
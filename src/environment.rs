@@ -1,10 +1,11 @@
-use std::{collections::HashMap, hash::Hash};
+use std::cell::RefCell;
+use std::collections::HashMap;
 use crate::expr::LiteralValue;
 use std::rc::Rc;
 
 pub struct Environment {
     values: HashMap<String, LiteralValue>,
-    pub enclosing: Option<Rc<Environment>>,
+    pub enclosing: Option<Rc<RefCell<Environment>>>,
 }
 
 impl Environment{
@@ -15,39 +16,69 @@ impl Environment{
         }
     }
 
+    pub fn new_enclosed(enclosing: Rc<RefCell<Environment>>) -> Self {
+        Self {
+            values: HashMap::new(),
+            enclosing: Some(enclosing),
+        }
+    }
+
     pub fn define(&mut self, name: String, value: LiteralValue) {
         self.values.insert(name, value);
     }
 
-    pub fn get(&self, name: &str) -> Option<&LiteralValue> {
+    pub fn get(&self, name: &str) -> Option<LiteralValue> {
         let value = self.values.get(name);
 
         match (value, &self.enclosing) {
-            (Some(val), _) => Some(val),
-            (None, Some(env)) => env.get(name),
+            (Some(val), _) => Some(val.clone()),
+            (None, Some(env)) => env.borrow().get(name),
             (None, None) => None,
         }
     }
 
+    /// The variables defined directly in this scope, in no particular order.
+    pub fn iter(&self) -> impl Iterator<Item = (&String, &LiteralValue)> {
+        self.values.iter()
+    }
+
     pub fn assign(&mut self, name: &str, value: LiteralValue) -> bool {
         let old_value = self.values.get(name);
 
-        match (old_value, &mut self.enclosing) {
+        match (old_value, &self.enclosing) {
             (Some(_), _) => {
                 self.values.insert(name.to_string(), value);
                 true
             }
-            (None, Some(env)) => Rc::get_mut(&mut env.clone()).expect("Could not get mutable ref to env").assign(name, value),
+            (None, Some(env)) => env.borrow_mut().assign(name, value),
             (None, None) => false
         }
     }
 }
 
-#[cfg(test)] 
+impl Default for Environment {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
 mod tests {
     use super::*;
     #[test]
     fn try_init() {
-        let environment = Environment::new();
+        let _environment = Environment::new();
+    }
+
+    #[test]
+    fn assign_reaches_enclosing_scope() {
+        let globals = Rc::new(RefCell::new(Environment::new()));
+        globals.borrow_mut().define("a".to_string(), LiteralValue::Number(1.0));
+
+        let mut inner = Environment::new_enclosed(globals.clone());
+        assert!(inner.assign("a", LiteralValue::Number(2.0)));
+        assert!(!inner.assign("b", LiteralValue::Nil));
+
+        assert_eq!(globals.borrow().get("a"), Some(LiteralValue::Number(2.0)));
     }
-}
\ No newline at end of file
+}
@@ -0,0 +1,72 @@
+use std::fmt;
+
+/// A failure raised while running a program, either by `throw` or by the
+/// interpreter itself (undefined variables, bad operand types, ...).
+///
+/// This is also the value a `catch (e)` clause binds, so scripts can read
+/// `e.kind`, `e.message`, `e.line` and `e.trace`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RuntimeError {
+    pub kind: String,
+    pub message: String,
+    pub line: u64,
+    pub trace: Vec<String>,
+}
+
+impl RuntimeError {
+    pub fn new(kind: &str, message: String, line: u64) -> Self {
+        Self {
+            kind: kind.to_string(),
+            message,
+            line,
+            trace: vec![],
+        }
+    }
+
+    pub fn name_error(message: String, line: u64) -> Self {
+        Self::new("NameError", message, line)
+    }
+
+    pub fn type_error(message: String, line: u64) -> Self {
+        Self::new("TypeError", message, line)
+    }
+}
+
+impl fmt::Display for RuntimeError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}: {}", self.kind, self.message)?;
+        for frame in &self.trace {
+            write!(f, "\n    {}", frame)?;
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for RuntimeError {}
+
+/// Why evaluating a piece of source code failed: it did not scan or parse,
+/// it raised an error nobody caught, or it asked to exit with a status code.
+#[derive(Debug, Clone, PartialEq)]
+pub enum EvalError {
+    Syntax(String),
+    Runtime(RuntimeError),
+    Exit(i32),
+}
+
+impl fmt::Display for EvalError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            EvalError::Syntax(message) => write!(f, "{}", message),
+            EvalError::Runtime(err) => write!(f, "{}", err),
+            EvalError::Exit(code) => write!(f, "Exited with status {}", code),
+        }
+    }
+}
+
+impl std::error::Error for EvalError {}
+
+impl From<RuntimeError> for EvalError {
+    fn from(err: RuntimeError) -> Self {
+        EvalError::Runtime(err)
+    }
+}
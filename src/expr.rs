@@ -1,25 +1,63 @@
-use std::clone;
-use std::fmt::format;
+use std::cell::RefCell;
+use std::fmt;
+use std::rc::Rc;
 
+use crate::callable::{Function, NativeFunction};
+use crate::iterator::NativeIterator;
+use crate::map::Key;
+use crate::module::Module;
+use crate::pattern::Pattern;
+use crate::stdlib::{iterator, list, map, regex, string, time};
 use crate::scanner;
 use crate::scanner::{Token, TokenType};
-use crate::environment::Environment;
+use crate::error::RuntimeError;
+use crate::interpreter::Interpreter;
+use crate::callable::Function as ScriptFunction;
+use crate::stmt::Stmt;
 
-#[derive(Clone, Debug, PartialEq)]
+#[derive(Clone, Debug)]
 pub enum LiteralValue {
-    Number(f32),
+    Number(f64),
     StringValue(String),
     True,
     False,
     Nil,
+    Error(Rc<RuntimeError>),
+    Function(Rc<Function>),
+    Native(Rc<NativeFunction>),
+    Module(Rc<Module>),
+    List(Rc<RefCell<Vec<LiteralValue>>>),
+    Map(Rc<RefCell<crate::map::Map>>),
+    Iterator(Rc<NativeIterator>),
+    Date(crate::date::Date),
+    Regex(Rc<Pattern>),
 }
 use LiteralValue::*;
 
-fn unwrap_as_f32(literal: Option<scanner::LiteralValue>) -> f32 {
+fn equal_contents<T>(
+    a: &Rc<RefCell<T>>,
+    b: &Rc<RefCell<T>>,
+    comparing: &mut Vec<(*const (), *const ())>,
+    compare: impl FnOnce(&T, &T, &mut Vec<(*const (), *const ())>) -> bool,
+) -> bool {
+    if Rc::ptr_eq(a, b) {
+        return true;
+    }
+    let pair = (Rc::as_ptr(a) as *const (), Rc::as_ptr(b) as *const ());
+    if comparing.contains(&pair) {
+        return true;
+    }
+    comparing.push(pair);
+    let equal = compare(&a.borrow(), &b.borrow(), comparing);
+    comparing.pop();
+    equal
+}
+
+fn unwrap_as_f64(literal: Option<scanner::LiteralValue>) -> f64 {
     match literal {
-        Some(scanner::LiteralValue::IntValue(x)) => x as f32,
-        Some(scanner::LiteralValue::FValue(x)) => x as f32,
-        _ => panic!("Could not unwrap as f32"),
+        Some(scanner::LiteralValue::IntValue(x)) => x as f64,
+        Some(scanner::LiteralValue::FValue(x)) => x,
+        _ => panic!("Could not unwrap as f64"),
     }
 }
 
@@ -42,12 +80,52 @@ pub enum Expr {
         operator: Token,
         right: Box<Expr>,
     },
+    Call {
+        callee: Box<Expr>,
+        paren: Token,
+        arguments: Vec<Expr>,
+    },
+    Get {
+        object: Box<Expr>,
+        name: Token,
+    },
+    Index {
+        object: Box<Expr>,
+        bracket: Token,
+        index: Box<Expr>,
+    },
+    Lambda {
+        keyword: Token,
+        params: Vec<Token>,
+        body: Rc<Vec<Stmt>>,
+    },
+    List {
+        bracket: Token,
+        elements: Vec<Expr>,
+    },
+    Map {
+        brace: Token,
+        entries: Vec<(Expr, Expr)>,
+    },
+    SetIndex {
+        object: Box<Expr>,
+        bracket: Token,
+        index: Box<Expr>,
+        value: Box<Expr>,
+    },
     Grouping {
+        paren: Token,
         expression: Box<Expr>,
     },
     Literal {
+        token: Token,
         value: LiteralValue,
     },
+    Logical {
+        left: Box<Expr>,
+        operator: Token,
+        right: Box<Expr>,
+    },
     Unary {
         operator: Token,
         right: Box<Expr>,
@@ -57,14 +135,114 @@ pub enum Expr {
     },
 }
 
+// Values print the way a script author wrote them: `3` rather than
+// `Number(3.0)`, and strings without quotes.
+impl fmt::Display for LiteralValue {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        self.write(f, &mut vec![])
+    }
+}
+
+// Lists and maps compare by their contents, so two lists that each contain
+// themselves are equal; comparing them stops where a pair repeats.
+impl PartialEq for LiteralValue {
+    fn eq(&self, other: &Self) -> bool {
+        self.equals(other, &mut vec![])
+    }
+}
+
 impl LiteralValue {
-    pub fn to_string(&self) -> String {
+    /// The value as it appears inside a printed list or map: like its
+    /// display form, but with strings quoted so `["a, b"]` and `["a", "b"]`
+    /// print differently.
+    pub fn repr(&self) -> String {
+        match self {
+            StringValue(s) => format!("{:?}", s),
+            other => other.to_string(),
+        }
+    }
+
+    // `open` holds the lists and maps being printed further up; one met
+    // again inside itself prints as `[...]` or `{...}`.
+    fn write(&self, f: &mut fmt::Formatter, open: &mut Vec<*const ()>) -> fmt::Result {
+        match self {
+            Number(x) => write!(f, "{}", x),
+            StringValue(s) => write!(f, "{}", s),
+            True => write!(f, "true"),
+            False => write!(f, "false"),
+            Nil => write!(f, "nil"),
+            Error(e) => write!(f, "{}: {}", e.kind, e.message),
+            Function(func) => write!(f, "<fn {}>", func.name.lexeme),
+            Native(func) => write!(f, "<native fn {}>", func.name),
+            Module(m) => write!(f, "<module {}>", m.name()),
+            Iterator(it) => write!(f, "<iterator {}>", it.name),
+            Date(date) => write!(f, "{}", date),
+            Regex(pattern) => write!(f, "<regex {}>", pattern.source()),
+            List(items) => {
+                let list = Rc::as_ptr(items) as *const ();
+                if open.contains(&list) {
+                    return write!(f, "[...]");
+                }
+                open.push(list);
+                write!(f, "[")?;
+                for (index, item) in items.borrow().iter().enumerate() {
+                    if index > 0 {
+                        write!(f, ", ")?;
+                    }
+                    item.write_repr(f, open)?;
+                }
+                open.pop();
+                write!(f, "]")
+            }
+            Map(map) => {
+                let pointer = Rc::as_ptr(map) as *const ();
+                if open.contains(&pointer) {
+                    return write!(f, "{{...}}");
+                }
+                open.push(pointer);
+                write!(f, "{{")?;
+                for (index, (key, value)) in map.borrow().iter().enumerate() {
+                    if index > 0 {
+                        write!(f, ", ")?;
+                    }
+                    write!(f, "{}: ", key.to_value().repr())?;
+                    value.write_repr(f, open)?;
+                }
+                open.pop();
+                write!(f, "}}")
+            }
+        }
+    }
+
+    fn write_repr(&self, f: &mut fmt::Formatter, open: &mut Vec<*const ()>) -> fmt::Result {
         match self {
-            Number(x) => x.to_string(),
-            StringValue(s) => s.clone(),
-            True => "true".to_string(),
-            False => "false".to_string(),
-            Nil => "nil".to_string(),
+            StringValue(s) => write!(f, "{:?}", s),
+            other => other.write(f, open),
+        }
+    }
+
+    // `comparing` holds the pairs of lists and maps being compared further
+    // up. Meeting a pair again adds nothing new, so it counts as equal there.
+    fn equals(&self, other: &Self, comparing: &mut Vec<(*const (), *const ())>) -> bool {
+        match (self, other) {
+            (Number(a), Number(b)) => a == b,
+            (StringValue(a), StringValue(b)) => a == b,
+            (True, True) | (False, False) | (Nil, Nil) => true,
+            (Error(a), Error(b)) => a == b,
+            (Function(a), Function(b)) => a == b,
+            (Native(a), Native(b)) => a == b,
+            (Module(a), Module(b)) => a == b,
+            (Iterator(a), Iterator(b)) => a == b,
+            (Date(a), Date(b)) => a == b,
+            (Regex(a), Regex(b)) => a == b,
+            (List(a), List(b)) => equal_contents(a, b, comparing, |a, b, comparing| {
+                a.len() == b.len() && a.iter().zip(b.iter()).all(|(x, y)| x.equals(y, comparing))
+            }),
+            (Map(a), Map(b)) => equal_contents(a, b, comparing, |a, b, comparing| {
+                a.len() == b.len()
+                    && a.iter().all(|(key, x)| b.get(key).is_some_and(|y| x.equals(y, comparing)))
+            }),
+            _ => false,
         }
     }
 
@@ -74,12 +252,20 @@ impl LiteralValue {
             StringValue(_) => "String",
             True | False => "Boolean",
             Nil => "nil",
+            Error(_) => "Error",
+            Function(_) | Native(_) => "Function",
+            Module(_) => "Module",
+            List(_) => "List",
+            Map(_) => "Map",
+            Iterator(_) => "Iterator",
+            Date(_) => "Date",
+            Regex(_) => "Regex",
         }
     }
 
     pub fn from_token(token: Token) -> Self {
         match token.token_type {
-            TokenType::NUMBER => Self::Number(unwrap_as_f32(token.literal)),
+            TokenType::NUMBER => Self::Number(unwrap_as_f64(token.literal)),
             TokenType::STRING => Self::StringValue(unwrap_as_string(token.literal)),
             TokenType::FALSE => Self::False,
             TokenType::TRUE => Self::True,
@@ -91,6 +277,14 @@ impl LiteralValue {
         }
     }
 
+    pub fn list(items: Vec<LiteralValue>) -> Self {
+        List(Rc::new(RefCell::new(items)))
+    }
+
+    pub fn map(map: crate::map::Map) -> Self {
+        Map(Rc::new(RefCell::new(map)))
+    }
+
     pub fn from_bool(b: bool) -> Self {
         if b { True } else { False }
     }
@@ -102,11 +296,15 @@ impl LiteralValue {
             True => false,
             False => true,
             Nil => true,
+            List(items) => items.borrow().is_empty(),
+            Map(map) => map.borrow().is_empty(),
+            Error(_) | Function(_) | Native(_) | Module(_) | Iterator(_) | Date(_) | Regex(_) => false,
         }
     }
 }
 
 impl Expr {
+    #[allow(clippy::inherent_to_string)]
     pub fn to_string(&self) -> String {
         match self {
             Expr::Assign { name, value } => format!("({name:?} : {})", value.to_string()),
@@ -122,51 +320,245 @@ impl Expr {
                     right.to_string()
                 )
             }
-            Expr::Grouping { expression } => format!("(group {})", expression.to_string()),
-            Expr::Literal { value } => value.to_string(),
+            Expr::Call {
+                callee,
+                paren: _,
+                arguments,
+            } => format!(
+                "(call {}{})",
+                callee.to_string(),
+                arguments.iter().map(|arg| format!(" {}", arg.to_string())).collect::<String>()
+            ),
+            Expr::Get { object, name } => format!("(. {} {})", object.to_string(), name.lexeme),
+            Expr::Index { object, bracket: _, index } => format!("([] {} {})", object.to_string(), index.to_string()),
+            Expr::Lambda { keyword: _, params, body } => format!(
+                "(fun ({}){})",
+                params.iter().map(|param| param.lexeme.clone()).collect::<Vec<String>>().join(" "),
+                body.iter().map(|stmt| format!(" {}", stmt.to_string())).collect::<String>()
+            ),
+            Expr::List { bracket: _, elements } => format!(
+                "(list{})",
+                elements.iter().map(|element| format!(" {}", element.to_string())).collect::<String>()
+            ),
+            Expr::Map { brace: _, entries } => format!(
+                "(map{})",
+                entries
+                    .iter()
+                    .map(|(key, value)| format!(" ({} {})", key.to_string(), value.to_string()))
+                    .collect::<String>()
+            ),
+            Expr::SetIndex {
+                object,
+                bracket: _,
+                index,
+                value,
+            } => format!("([]= {} {} {})", object.to_string(), index.to_string(), value.to_string()),
+            Expr::Grouping { paren: _, expression } => format!("(group {})", expression.to_string()),
+            Expr::Literal { token: _, value } => value.to_string(),
+            Expr::Logical {
+                left,
+                operator,
+                right,
+            } => format!("({} {} {})", operator.lexeme, left.to_string(), right.to_string()),
             Expr::Unary { operator, right } => {
                 format!("({} {})", operator.lexeme, right.to_string())
             }
             Expr::Variable { name } => format!("(var {})",name.lexeme),
-            _ => todo!()
         }
     }
 
-    pub fn evaluate(&self, environment: &mut Environment) -> Result<LiteralValue, String> {
+    pub fn evaluate(&self, interpreter: &mut Interpreter) -> Result<LiteralValue, RuntimeError> {
         match self {
             Expr::Assign { name, value } => {
                 // First, evaluate the right‐hand side
-                let new_value = value.evaluate(environment)?;
-                
-                let assign_success = environment.assign(&name.lexeme, new_value.clone());
-                
-                if(assign_success) {
+                let new_value = value.evaluate(interpreter)?;
+
+                let assign_success = interpreter.environment.borrow_mut().assign(&name.lexeme, new_value.clone());
+
+                if assign_success {
                     Ok(new_value)
 
                 } else {
-                    Err(format!("Variable {:#?} has not been declared", name.lexeme))
+                    Err(RuntimeError::name_error(
+                        format!("Variable '{}' has not been declared", name.lexeme),
+                        name.line_number,
+                    ))
                 }
             }
             Expr::Variable { name } => {
-                match environment.get(&name.lexeme.clone()) {
-                    Some(value) => Ok(value.clone()),
-                    None => Err(format!("Variable '{:#?}' has not declared", name.lexeme)),
+                let value = interpreter.environment.borrow().get(&name.lexeme);
+                match value {
+                    Some(value) => Ok(value),
+                    None => Err(RuntimeError::name_error(
+                        format!("Variable '{}' has not been declared", name.lexeme),
+                        name.line_number,
+                    )),
+                }
+            }
+            Expr::Literal { token: _, value } => Ok(value.clone()),
+
+            Expr::Grouping { paren: _, expression } => Ok(expression.evaluate(interpreter)?),
+
+            Expr::Call {
+                callee,
+                paren,
+                arguments,
+            } => {
+                let callee = callee.evaluate(interpreter)?;
+
+                let mut argument_values = Vec::with_capacity(arguments.len());
+                for argument in arguments {
+                    argument_values.push(argument.evaluate(interpreter)?);
+                }
+
+                interpreter.call(&callee, argument_values, paren.line_number)
+            }
+
+            Expr::Get { object, name } => {
+                let object = object.evaluate(interpreter)?;
+                let no_property = || {
+                    RuntimeError::type_error(
+                        format!("{} has no property '{}'", object.to_type(), name.lexeme),
+                        name.line_number,
+                    )
+                };
+
+                match (&object, name.lexeme.as_str()) {
+                    (Error(e), "kind") => Ok(StringValue(e.kind.clone())),
+                    (Error(e), "message") => Ok(StringValue(e.message.clone())),
+                    (Error(e), "line") => Ok(Number(e.line as f64)),
+                    (Error(e), "trace") => Ok(StringValue(e.trace.join("\n"))),
+                    (Module(m), name_str) => m.get(name_str).ok_or_else(|| {
+                        RuntimeError::name_error(
+                            format!("Module '{}' does not export '{}'", m.name(), name_str),
+                            name.line_number,
+                        )
+                    }),
+                    (StringValue(s), name_str) => string::method(s, name_str).ok_or_else(no_property),
+                    (List(items), name_str) => list::method(items, name_str).ok_or_else(no_property),
+                    (Map(entries), name_str) => map::method(entries, name_str).ok_or_else(no_property),
+                    (Iterator(it), name_str) => iterator::method(it, name_str).ok_or_else(no_property),
+                    (Date(date), name_str) => time::method(date, name_str).ok_or_else(no_property),
+                    (Regex(pattern), name_str) => regex::method(pattern, name_str).ok_or_else(no_property),
+                    _ => Err(no_property()),
+                }
+            }
+
+            Expr::Lambda { keyword, params, body } => {
+                let function = ScriptFunction {
+                    name: Token::new(TokenType::FUN, "<anonymous>".to_string(), None, keyword.line_number, keyword.offset),
+                    params: params.clone(),
+                    body: body.clone(),
+                    closure: interpreter.environment.clone(),
+                    file: interpreter.current_file().to_string(),
+                };
+                Ok(Function(Rc::new(function)))
+            }
+
+            Expr::List { bracket: _, elements } => {
+                let mut items = Vec::with_capacity(elements.len());
+                for element in elements {
+                    items.push(element.evaluate(interpreter)?);
+                }
+                Ok(LiteralValue::list(items))
+            }
+
+            Expr::Map { brace, entries } => {
+                let mut map = crate::map::Map::new();
+                for (key, value) in entries {
+                    let key = key.evaluate(interpreter)?;
+                    let key = Key::new(&key).map_err(|msg| RuntimeError::type_error(msg, brace.line_number))?;
+                    map.insert(key, value.evaluate(interpreter)?);
+                }
+                Ok(LiteralValue::map(map))
+            }
+
+            Expr::Index { object, bracket, index } => {
+                let object = object.evaluate(interpreter)?;
+                let index = index.evaluate(interpreter)?;
+
+                match &object {
+                    List(items) => {
+                        let items = items.borrow();
+                        let position = position(&object, &index, items.len(), bracket.line_number)?;
+                        Ok(items[position].clone())
+                    }
+                    StringValue(s) => {
+                        let length = s.chars().count();
+                        let position = position(&object, &index, length, bracket.line_number)?;
+                        Ok(StringValue(s.chars().nth(position).unwrap().to_string()))
+                    }
+                    Map(map) => {
+                        let key = Key::new(&index).map_err(|msg| RuntimeError::type_error(msg, bracket.line_number))?;
+                        map.borrow().get(&key).cloned().ok_or_else(|| {
+                            RuntimeError::new("KeyError", format!("Key {} not found", index.repr()), bracket.line_number)
+                        })
+                    }
+                    _ => Err(RuntimeError::type_error(
+                        format!("{} cannot be indexed", object.to_type()),
+                        bracket.line_number,
+                    )),
                 }
             }
-            Expr::Literal { value } => Ok(value.clone()),
 
-            Expr::Grouping { expression } => Ok(expression.evaluate(environment)?),
+            Expr::SetIndex {
+                object,
+                bracket,
+                index,
+                value,
+            } => {
+                let object = object.evaluate(interpreter)?;
+                let index = index.evaluate(interpreter)?;
+                let value = value.evaluate(interpreter)?;
+
+                match &object {
+                    List(items) => {
+                        let length = items.borrow().len();
+                        let position = position(&object, &index, length, bracket.line_number)?;
+                        items.borrow_mut()[position] = value.clone();
+                        Ok(value)
+                    }
+                    Map(map) => {
+                        let key = Key::new(&index).map_err(|msg| RuntimeError::type_error(msg, bracket.line_number))?;
+                        map.borrow_mut().insert(key, value.clone());
+                        Ok(value)
+                    }
+                    _ => Err(RuntimeError::type_error(
+                        format!("{} does not support item assignment", object.to_type()),
+                        bracket.line_number,
+                    )),
+                }
+            }
+
+            Expr::Logical {
+                left,
+                operator,
+                right,
+            } => {
+                let left = left.evaluate(interpreter)?;
+
+                // Short-circuit: the right operand only runs when the left one
+                // does not already decide the result.
+                match (operator.token_type, left.is_falsy()) {
+                    (TokenType::OR, false) | (TokenType::AND, true) => Ok(left),
+                    _ => right.evaluate(interpreter),
+                }
+            }
 
             Expr::Unary { operator, right } => {
-                let right = right.evaluate(environment)?;
+                let right = right.evaluate(interpreter)?;
 
                 match (right.clone(), operator.token_type) {
                     (Number(x), TokenType::MINUS) => Ok(Number(-x)),
-                    (_, TokenType::MINUS) => {
-                        Err(format!("Minus not implemented for {:?}", right.to_type()))
-                    }
+                    (_, TokenType::MINUS) => Err(RuntimeError::type_error(
+                        format!("Minus not implemented for {}", right.to_type()),
+                        operator.line_number,
+                    )),
                     (any, TokenType::BANG) => Ok(LiteralValue::from_bool(any.is_falsy())),
-                    (_, ttype) => Err(format!("{} is not a valid unary operator", ttype)),
+                    (_, ttype) => Err(RuntimeError::type_error(
+                        format!("{} is not a valid unary operator", ttype),
+                        operator.line_number,
+                    )),
                 }
             }
 
@@ -175,8 +567,8 @@ impl Expr {
                 operator,
                 right,
             } => {
-                let left = left.evaluate(environment)?;
-                let right = right.evaluate(environment)?;
+                let left = left.evaluate(interpreter)?;
+                let right = right.evaluate(interpreter)?;
 
                 match (&left, operator.token_type, &right) {
                     (Number(x), TokenType::PLUS, Number(y)) => Ok(Number(x + y)),
@@ -196,13 +588,21 @@ impl Expr {
                     }
 
                     (StringValue(_), op, Number(_)) | (Number(_), op, StringValue(_)) => {
-                        Err(format!("{} is not defined for string and number", op))
+                        Err(RuntimeError::type_error(
+                            format!("{} is not defined for string and number", op),
+                            operator.line_number,
+                        ))
                     }
 
                     (StringValue(s1), TokenType::PLUS, StringValue(s2)) => {
                         Ok(StringValue(format!("{}{}", s1, s2)))
                     }
 
+                    (Date(x), TokenType::GREATER, Date(y)) => Ok(LiteralValue::from_bool(x > y)),
+                    (Date(x), TokenType::GREATER_EQUAL, Date(y)) => Ok(LiteralValue::from_bool(x >= y)),
+                    (Date(x), TokenType::LESS, Date(y)) => Ok(LiteralValue::from_bool(x < y)),
+                    (Date(x), TokenType::LESS_EQUAL, Date(y)) => Ok(LiteralValue::from_bool(x <= y)),
+
                     (x, TokenType::BANG_EQUAL, y) => Ok(LiteralValue::from_bool(x != y)),
                     (x, TokenType::EQUAL_EQUAL, y) => Ok(LiteralValue::from_bool(x == y)),
 
@@ -219,23 +619,43 @@ impl Expr {
                         Ok(LiteralValue::from_bool(s1 <= s2))
                     }
 
-                    (x, ttype, y) => Err(format!(
-                        "{} is not implemented for operands {:?} and {:?}",
-                        ttype, x, y
+                    (x, ttype, y) => Err(RuntimeError::type_error(
+                        format!(
+                            "{} is not implemented for operands {} and {}",
+                            ttype,
+                            x.to_type(),
+                            y.to_type()
+                        ),
+                        operator.line_number,
                     )),
                 }
             }
-           // Expr::Variable { name } => Ok(expression.evaluate(environment)?),
-            Expr::Variable { name } => todo!(),
-            Expr::Assign { name, value } => todo!(),
        }
     }
 
+    #[allow(dead_code)]
     pub fn print(&self) {
         println!("{}", self.to_string());
     }
 }
 
+// Checks that `index` is an integer addressing one of `length` items of
+// `object`. Lists are indexed by item and strings by character.
+fn position(object: &LiteralValue, index: &LiteralValue, length: usize, line: u64) -> Result<usize, RuntimeError> {
+    match index {
+        Number(x) if x.fract() == 0.0 && *x >= 0.0 && (*x as usize) < length => Ok(*x as usize),
+        Number(x) if x.fract() == 0.0 => Err(RuntimeError::new(
+            "IndexError",
+            format!("{} index {} out of range for length {}", object.to_type(), x, length),
+            line,
+        )),
+        _ => Err(RuntimeError::type_error(
+            format!("{} index must be an integer, not {}", object.to_type(), index.to_type()),
+            line,
+        )),
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::{Expr, LiteralValue};
@@ -248,14 +668,18 @@ mod tests {
             lexeme: "-".to_string(),
             literal: None,
             line_number: 0,
+            offset: 0,
         };
 
         let onetwothree = Expr::Literal {
+            token: Token::new(TokenType::NUMBER, "123".to_string(), None, 0, 1),
             value: LiteralValue::Number(123.0),
         };
 
         let group = Expr::Grouping {
+            paren: Token::new(TokenType::LEFT_PAREN, "(".to_string(), None, 0, 7),
             expression: Box::new(Expr::Literal {
+                token: Token::new(TokenType::NUMBER, "45.67".to_string(), None, 0, 8),
                 value: LiteralValue::Number(45.67),
             }),
         };
@@ -265,6 +689,7 @@ mod tests {
             lexeme: "*".to_string(),
             literal: None,
             line_number: 0,
+            offset: 0,
         };
 
         let ast = Expr::Binary {
@@ -0,0 +1,335 @@
+//! The `fnky fmt` formatter. It reprints the lossless syntax tree rather
+//! than the parser's, so comments, `for` loops and the blank lines between
+//! statements survive; everything else gets a canonical layout.
+
+use crate::parser::Parser;
+use crate::scanner::{Scanner, TokenType};
+use crate::syntax::{self, NodeKind, SyntaxElement, SyntaxNode, SyntaxToken};
+use TokenType::*;
+
+const INDENT: &str = "    ";
+
+/// Formats a whole file: one statement per line, four spaces per block,
+/// opening braces on the line of their statement and single spaces around
+/// binary operators. Runs of blank lines shrink to one. Fails with the
+/// scanner's or parser's error if the source does not parse.
+pub fn format_source(source: &str) -> Result<String, String> {
+    Parser::new(Scanner::new(source).scan_tokens()?).parse()?;
+
+    let mut formatter = Formatter {
+        line: 1,
+        ..Formatter::default()
+    };
+    formatter.node(&syntax::parse(source));
+    if !formatter.out.is_empty() {
+        formatter.out.push('\n');
+    }
+    Ok(formatter.out)
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum Open {
+    Block,
+    // The body of an anonymous function, laid out like a block.
+    Function,
+    Map,
+    // An anonymous function's parameter list.
+    Params,
+    Paren,
+    Bracket,
+}
+
+// Whether the next token starts a new line.
+#[derive(Clone, Copy, PartialEq, Default)]
+enum Break {
+    #[default]
+    No,
+    // After a statement or a block's `{`. An `else`, or the `}` of an empty
+    // block, stays on the line.
+    Statement,
+    // After a block's `}`. `else`, `catch`, `finally` and closing
+    // punctuation stay on the line.
+    Block,
+    // After a line comment.
+    Always,
+    // After a line comment in the middle of a statement, as in
+    // `if (x) // note`. The rest of the statement is indented one level.
+    Continue,
+}
+
+#[derive(Default)]
+struct Formatter {
+    out: String,
+    // The brackets still open, with the indentation of their line.
+    open: Vec<(Open, usize)>,
+    indent: usize,
+    pending: Break,
+    // The last token written, comments aside, and the kind of bracket it
+    // opened or closed.
+    prev: Option<TokenType>,
+    prev_open: Option<Open>,
+    prev_unary: bool,
+    // The source line the last token (comments included) ended on, and the
+    // line the next token starts on.
+    last_line: u64,
+    line: u64,
+}
+
+// What a token does in the node it belongs to.
+#[derive(Clone, Copy, PartialEq)]
+enum Role {
+    Opens(NodeKind),
+    Closes,
+    // The `;` ending a statement, not one inside a `for` loop's header.
+    EndsStatement,
+    Other,
+}
+
+impl Formatter {
+    fn node(&mut self, node: &SyntaxNode) {
+        let last = node.children.len().saturating_sub(1);
+        for (index, child) in node.children.iter().enumerate() {
+            let token = match child {
+                SyntaxElement::Node(child) => {
+                    self.node(child);
+                    continue;
+                }
+                SyntaxElement::Token(token) => token,
+            };
+            // A bracket node starts with its opening bracket and, once
+            // closed, ends with the closing one.
+            let role = match (node.kind, token.kind) {
+                (_, WHITESPACE | COMMENT) => Role::Other,
+                (NodeKind::Statement, SEMICOLON) => Role::EndsStatement,
+                (NodeKind::Program | NodeKind::Statement, _) => Role::Other,
+                _ if index == 0 => Role::Opens(node.kind),
+                (_, RIGHT_PAREN | RIGHT_BRACKET | RIGHT_BRACE) if index == last => Role::Closes,
+                _ => Role::Other,
+            };
+            if token.kind == WHITESPACE {
+                self.line += token.text.matches('\n').count() as u64;
+            } else {
+                self.token(token, role);
+            }
+        }
+    }
+
+    fn token(&mut self, token: &SyntaxToken, role: Role) {
+        let kind = token.kind;
+        let start_line = self.line;
+        self.line += token.text.matches('\n').count() as u64;
+
+        if kind == COMMENT {
+            if !self.out.is_empty() && start_line == self.last_line {
+                self.out.push(' ');
+            } else {
+                let indent = self.indent_for(None);
+                self.new_line(start_line, indent, true);
+            }
+            self.out.push_str(token.text.trim_end());
+            self.last_line = self.line;
+            self.pending = match self.pending {
+                Break::No | Break::Continue if self.prev.is_some() => Break::Continue,
+                _ => Break::Always,
+            };
+            return;
+        }
+
+        let closing = match role {
+            Role::Closes => self.open.pop(),
+            _ => None,
+        };
+        let breaks = match self.pending {
+            Break::No => false,
+            Break::Always | Break::Continue => true,
+            Break::Statement => kind != ELSE && !(kind == RIGHT_BRACE && self.prev == Some(LEFT_BRACE)),
+            Break::Block => !matches!(
+                kind,
+                ELSE | CATCH | FINALLY | RIGHT_PAREN | RIGHT_BRACKET | COMMA | SEMICOLON | DOT
+            ),
+        };
+        if breaks {
+            let continues = self.pending == Break::Continue
+                && closing.is_none()
+                && kind != LEFT_BRACE
+                && matches!(self.open.last(), None | Some((Open::Block | Open::Function, _)));
+            let indent = self.indent_for(closing) + usize::from(continues);
+            self.new_line(start_line, indent, closing.is_none());
+        } else if self.space_before(kind, closing.map(|(open, _)| open)) {
+            self.out.push(' ');
+        }
+        self.out.push_str(&token.text);
+
+        let unary = matches!(kind, MINUS | BANG) && !self.prev_ends_operand();
+        let after_params = self.prev_open == Some(Open::Params);
+        self.pending = Break::No;
+        self.prev_open = closing.map(|(open, _)| open);
+        match role {
+            Role::Opens(node) => {
+                let open = match node {
+                    NodeKind::Block if after_params => Open::Function,
+                    NodeKind::Block => Open::Block,
+                    NodeKind::Map => Open::Map,
+                    NodeKind::Parens if self.prev == Some(FUN) => Open::Params,
+                    NodeKind::Parens => Open::Paren,
+                    _ => Open::Bracket,
+                };
+                if matches!(open, Open::Block | Open::Function) {
+                    self.pending = Break::Statement;
+                }
+                self.open.push((open, self.indent));
+                self.prev_open = Some(open);
+            }
+            Role::Closes if self.prev_open == Some(Open::Block) => self.pending = Break::Block,
+            Role::EndsStatement => self.pending = Break::Statement,
+            _ => {}
+        }
+        self.prev = Some(kind);
+        self.prev_unary = unary;
+        self.last_line = self.line;
+    }
+
+    // Closing brackets line up with the line that opened them; anything
+    // else is indented one level past the innermost open bracket.
+    fn indent_for(&self, closing: Option<(Open, usize)>) -> usize {
+        match (closing, self.open.last()) {
+            (Some((_, indent)), _) => indent,
+            (None, Some(&(_, indent))) => indent + 1,
+            (None, None) => 0,
+        }
+    }
+
+    // Blank lines in the source are kept, one at most, except at the start
+    // or end of a block.
+    fn new_line(&mut self, start_line: u64, indent: usize, keep_blank: bool) {
+        if !self.out.is_empty() {
+            self.out.push('\n');
+            if keep_blank && start_line > self.last_line + 1 && self.prev != Some(LEFT_BRACE) {
+                self.out.push('\n');
+            }
+        }
+        self.out.push_str(&INDENT.repeat(indent));
+        self.indent = indent;
+    }
+
+    fn space_before(&self, kind: TokenType, closing: Option<Open>) -> bool {
+        let Some(prev) = self.prev else {
+            return false;
+        };
+        if matches!(kind, COMMA | SEMICOLON | COLON | DOT | RIGHT_PAREN | RIGHT_BRACKET)
+            || matches!(prev, LEFT_PAREN | LEFT_BRACKET | DOT)
+            || self.prev_unary
+        {
+            return false;
+        }
+        if prev == LEFT_BRACE && (self.prev_open == Some(Open::Map) || kind == RIGHT_BRACE) {
+            return false;
+        }
+        match kind {
+            RIGHT_BRACE => closing != Some(Open::Map),
+            // Calls and indexing, but not `if (`, `fun (` or list literals.
+            LEFT_PAREN | LEFT_BRACKET => !self.prev_ends_operand(),
+            _ => true,
+        }
+    }
+
+    fn prev_ends_operand(&self) -> bool {
+        match self.prev {
+            Some(NUMBER | STRING | IDENTIFIER | TRUE | FALSE | NIL | THIS | SUPER | RIGHT_PAREN | RIGHT_BRACKET) => {
+                true
+            }
+            // An anonymous function's `}` too, so a call right after it
+            // stays on its line.
+            Some(RIGHT_BRACE) => matches!(self.prev_open, Some(Open::Map | Open::Function)),
+            _ => false,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn formats_layout_and_keeps_comments() {
+        let source = r#"// Greets people.
+var names=["a",  "b"];   // who
+fun greet(n){if(n=="a"){print "hi "+n;}else{print -1;}
+  for(var i=0;i<2;i=i+1) print names[i];
+
+
+  // a closure
+  var m={"k":fun (x){return !x;},2:[]};
+  try{ }catch(e){throw e;}finally{
+  }
+}
+greet(names[0]).len();
+"#;
+        let expected = r#"// Greets people.
+var names = ["a", "b"]; // who
+fun greet(n) {
+    if (n == "a") {
+        print "hi " + n;
+    } else {
+        print -1;
+    }
+    for (var i = 0; i < 2; i = i + 1) print names[i];
+
+    // a closure
+    var m = {"k": fun (x) {
+        return !x;
+    }, 2: []};
+    try {} catch (e) {
+        throw e;
+    } finally {}
+}
+greet(names[0]).len();
+"#;
+        let formatted = format_source(source).unwrap();
+        assert_eq!(formatted, expected);
+        assert_eq!(format_source(&formatted).unwrap(), formatted);
+    }
+
+    #[test]
+    fn formatting_keeps_the_tokens() {
+        let source = "var xs = [1, // one\n2]; x = 3 - -xs[0] * (2 / 1);\nif (x) print x; else x = {}[1];";
+        let formatted = format_source(source).unwrap();
+        let lexemes = |source: &str| -> Vec<String> {
+            Scanner::with_comments(source)
+                .scan_tokens()
+                .unwrap()
+                .into_iter()
+                .map(|token| token.lexeme)
+                .collect()
+        };
+        assert_eq!(lexemes(&formatted), lexemes(source));
+        assert_eq!(
+            formatted,
+            "var xs = [1, // one\n    2];\nx = 3 - -xs[0] * (2 / 1);\nif (x) print x; else x = {}[1];\n"
+        );
+        assert!(format_source("var x = ;").is_err());
+    }
+
+    #[test]
+    fn anonymous_functions_and_comments_inside_statements() {
+        let source = "print fun (a) { return a; }(1);
+var f = fun () {}.name;
+if (x) // c
+print 1;
+var y = 1 + // c
+2;
+";
+        let expected = "print fun (a) {
+    return a;
+}(1);
+var f = fun () {}.name;
+if (x) // c
+    print 1;
+var y = 1 + // c
+    2;
+";
+        let formatted = format_source(source).unwrap();
+        assert_eq!(formatted, expected);
+        assert_eq!(format_source(&formatted).unwrap(), formatted);
+    }
+}
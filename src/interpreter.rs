@@ -1,58 +1,907 @@
+use crate::callable::{Arity, Function, NativeFunction};
+use crate::convert::{IntoNative, IntoValue};
 use crate::expr::{Expr, LiteralValue};
+use crate::module::{self, Module, ModulePaths};
+use crate::parser::Parser;
+use crate::scanner::{self, Scanner, Token};
+use crate::sandbox::Sandbox;
+use crate::stdlib;
 use crate::stmt::Stmt;
 use crate::environment::Environment;
-use std::f32::consts::E;
+use crate::error::{EvalError, RuntimeError};
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::fs;
+use std::io::{self, BufRead, Write};
+use std::path::{Path, PathBuf};
 use std::rc::Rc;
 
+// Deep enough for ordinary recursion while still reporting runaway
+// recursion as an error. Each interpreted call costs a few kilobytes of
+// native stack, so hosts should run the interpreter on a thread with a
+// stack of at least `INTERPRETER_STACK_SIZE` bytes.
+const MAX_CALL_DEPTH: usize = 1000;
+pub const INTERPRETER_STACK_SIZE: usize = 64 * 1024 * 1024;
+
+// One entry of the interpreter's call stack. `line` is the line currently
+// executing in this frame: the call site while a callee runs, or the line
+// that raised an error.
+struct CallFrame {
+    function: String,
+    file: String,
+    line: u64,
+}
+
 pub struct Interpreter{
     //Global state
-    environment: Rc<Environment>,
+    globals: Rc<RefCell<Environment>>,
+    pub(crate) environment: Rc<RefCell<Environment>>,
+    frames: Vec<CallFrame>,
+    // Set by a `return` statement until the enclosing call picks it up.
+    returning: Option<LiteralValue>,
+    // Every module imported so far, keyed by canonical path, so each one
+    // runs only once.
+    modules: HashMap<PathBuf, Rc<Module>>,
+    // Files currently being executed, outermost first, for cycle detection.
+    loading: Vec<PathBuf>,
+    module_paths: ModulePaths,
+    // Where `print` writes; standard output unless the host redirects it.
+    output: Box<dyn Write>,
+    // Where `io.input` reads; standard input when not set.
+    input: Option<Box<dyn BufRead>>,
+    sandbox: Sandbox,
+    // What `os.args()` returns: the command-line arguments after the script.
+    args: Vec<String>,
+    // Set by `os.exit` while the error it raises unwinds to `eval`.
+    exiting: Option<i32>,
+    // Built-in globals such as `math`, defined afresh in the globals of the
+    // main script and of every module.
+    builtins: Vec<(String, LiteralValue)>,
 }
 
 impl Interpreter {
     pub fn new() -> Self {
+        Self::for_file("<stdin>")
+    }
+
+    /// Creates an interpreter whose top-level code is reported as coming
+    /// from `file` in stack traces.
+    pub fn for_file(file: &str) -> Self {
+        let builtins = stdlib::builtins();
+        let globals = Rc::new(RefCell::new(new_globals(&builtins)));
         Self {
-            environment: Rc::new(Environment::new()),
+            globals: globals.clone(),
+            environment: globals,
+            frames: vec![CallFrame {
+                function: "<script>".to_string(),
+                file: file.to_string(),
+                line: 0,
+            }],
+            returning: None,
+            modules: HashMap::new(),
+            loading: Path::new(file).canonicalize().into_iter().collect(),
+            module_paths: ModulePaths::default(),
+            output: Box::new(io::stdout()),
+            input: None,
+            sandbox: Sandbox::default(),
+            args: vec![],
+            exiting: None,
+            builtins,
         }
     }
 
-    // pub fn interpret_expr(&mut self, expr: Expr) -> Result<(), String> {
-    //     expr.evaluate(Rc::get_mut(&mut self.environment).expect("could not get mutable refernce to environment"))?;
-    // }
+    /// Adds a directory searched by `import` after the importing file's own
+    /// directory, e.g. an entry of `FNKY_PATH`.
+    pub fn add_search_path(&mut self, dir: PathBuf) {
+        self.module_paths.search_paths.push(dir);
+    }
+
+    /// Makes `import "name/..."` resolve inside the local directory `dir`.
+    pub fn add_package(&mut self, name: &str, dir: PathBuf) {
+        self.module_paths.packages.insert(name.to_string(), dir);
+    }
 
     pub fn interpret(&mut self, stmts: Vec<Stmt>) -> Result<(), String> {
+        self.execute_all(&stmts).map_err(|err| err.to_string())
+    }
+
+    /// Runs `source` and returns the value of its final expression.
+    ///
+    /// The source may be a bare expression (`1 + 2`) or a program; for a
+    /// program the result is the value of its last statement if that is an
+    /// expression statement, and nil otherwise. A script calling `os.exit`
+    /// stops with [`EvalError::Exit`].
+    pub fn eval(&mut self, source: &str) -> Result<LiteralValue, EvalError> {
+        self.eval_source(source).map_err(|err| match self.exiting.take() {
+            Some(code) => EvalError::Exit(code),
+            None => err,
+        })
+    }
+
+    fn eval_source(&mut self, source: &str) -> Result<LiteralValue, EvalError> {
+        let tokens = Scanner::new(source)
+            .scan_tokens()
+            .map_err(|err| EvalError::Syntax(err.trim_end().to_string()))?;
+
+        let mut parser = Parser::new(tokens.clone());
+        if let Ok(expression) = parser.expression()
+            && parser.is_finished()
+        {
+            return self.evaluate_top_level(&expression).map_err(EvalError::from);
+        }
+
+        let mut stmts = Parser::new(tokens).parse().map_err(EvalError::Syntax)?;
+        let last = match stmts.last() {
+            Some(Stmt::Expression { .. }) => stmts.pop(),
+            _ => None,
+        };
+        self.execute_all(&stmts)?;
+        match last {
+            Some(Stmt::Expression { expression }) => Ok(self.evaluate_top_level(&expression)?),
+            _ => Ok(LiteralValue::Nil),
+        }
+    }
+
+    fn evaluate_top_level(&mut self, expression: &Expr) -> Result<LiteralValue, RuntimeError> {
+        expression.evaluate(self).map_err(|err| self.traced(err))
+    }
+
+    /// Reads a global variable.
+    pub fn get_global(&self, name: &str) -> Option<LiteralValue> {
+        self.globals.borrow().get(name)
+    }
+
+    /// Defines or overwrites a global variable.
+    pub fn set_global<T: IntoValue>(&mut self, name: &str, value: T) {
+        self.globals.borrow_mut().define(name.to_string(), value.into_value());
+    }
+
+    /// Every global variable, built-in modules included, sorted by name.
+    pub fn globals(&self) -> Vec<(String, LiteralValue)> {
+        let mut globals: Vec<(String, LiteralValue)> = self
+            .globals
+            .borrow()
+            .iter()
+            .map(|(name, value)| (name.clone(), value.clone()))
+            .collect();
+        globals.sort_by(|a, b| a.0.cmp(&b.0));
+        globals
+    }
+
+    /// The global variables defined by scripts or the host, sorted by name.
+    /// Built-in modules are left out unless something replaced them.
+    pub fn user_globals(&self) -> Vec<(String, LiteralValue)> {
+        self.globals()
+            .into_iter()
+            .filter(|(name, value)| !self.builtins.iter().any(|builtin| builtin.0 == *name && builtin.1 == *value))
+            .collect()
+    }
+
+    // Whether `environment` is this interpreter's global scope, as it is
+    // for the closure of a function declared at the top level.
+    pub(crate) fn is_global_scope(&self, environment: &Rc<RefCell<Environment>>) -> bool {
+        Rc::ptr_eq(&self.globals, environment)
+    }
+
+    /// Calls the global function `name`, which may be declared in a script
+    /// or registered by the host.
+    pub fn call_global(&mut self, name: &str, arguments: Vec<LiteralValue>) -> Result<LiteralValue, RuntimeError> {
+        let callee = self
+            .get_global(name)
+            .ok_or_else(|| RuntimeError::name_error(format!("Variable '{}' has not been declared", name), 0))?;
+        self.call(&callee, arguments, 0).map_err(|err| self.traced(err))
+    }
+
+    /// Registers a native function working on raw runtime values. The
+    /// interpreter checks the argument count against `arity` before calling
+    /// `fun`; checking the argument types is up to `fun`.
+    pub fn define_native<F>(&mut self, name: &str, arity: Arity, fun: F)
+    where
+        F: Fn(&mut Interpreter, Vec<LiteralValue>) -> Result<LiteralValue, RuntimeError> + 'static,
+    {
+        let native = NativeFunction {
+            name: name.to_string(),
+            arity,
+            fun: Rc::new(fun),
+        };
+        self.set_global(name, LiteralValue::Native(Rc::new(native)));
+    }
+
+    /// Registers a Rust closure as a native function. Its arity and argument
+    /// types come from its signature, so scripts calling it with the wrong
+    /// number or types of arguments get a catchable TypeError:
+    ///
+    /// ```
+    /// let mut interpreter = fnky::Interpreter::new();
+    /// interpreter.register_fn("hypot", |a: f64, b: f64| (a * a + b * b).sqrt());
+    /// assert_eq!(interpreter.eval("hypot(3, 4)").unwrap(), fnky::LiteralValue::Number(5.0));
+    /// ```
+    pub fn register_fn<Args, F: IntoNative<Args>>(&mut self, name: &str, fun: F) {
+        let native = NativeFunction {
+            name: name.to_string(),
+            arity: Arity::Exact(F::arity()),
+            fun: fun.into_native(name),
+        };
+        self.set_global(name, LiteralValue::Native(Rc::new(native)));
+    }
+
+    /// Sends the output of `print` to `output` instead of standard output.
+    /// Pass an [`OutputBuffer`](crate::OutputBuffer) to capture it.
+    pub fn set_output(&mut self, output: impl Write + 'static) {
+        self.output = Box::new(output);
+    }
+
+    /// Makes `io.input` read from `input` instead of standard input.
+    pub fn set_input(&mut self, input: impl BufRead + 'static) {
+        self.input = Some(Box::new(input));
+    }
+
+    /// Restricts what the standard library may do on behalf of scripts.
+    /// Interpreters start out unrestricted.
+    pub fn set_sandbox(&mut self, sandbox: Sandbox) {
+        self.sandbox = sandbox;
+    }
+
+    pub fn sandbox(&self) -> &Sandbox {
+        &self.sandbox
+    }
+
+    /// Sets the command-line arguments scripts see through `os.args()`.
+    pub fn set_args(&mut self, args: Vec<String>) {
+        self.args = args;
+    }
+
+    pub(crate) fn args(&self) -> &[String] {
+        &self.args
+    }
+
+    // Starts unwinding the script for `os.exit`. The returned error passes
+    // through `catch` clauses untouched and `eval` turns it into
+    // `EvalError::Exit`.
+    pub(crate) fn exit(&mut self, code: i32) -> RuntimeError {
+        self.exiting = Some(code);
+        RuntimeError::new("Exit", format!("exit({})", code), 0)
+    }
+
+    // Reads a line of input without its line ending, or None at the end of
+    // the input.
+    pub(crate) fn read_input_line(&mut self) -> io::Result<Option<String>> {
+        let mut line = String::new();
+        let read = match &mut self.input {
+            Some(input) => input.read_line(&mut line)?,
+            None => io::stdin().read_line(&mut line)?,
+        };
+        if read == 0 {
+            return Ok(None);
+        }
+        if line.ends_with('\n') {
+            line.pop();
+            if line.ends_with('\r') {
+                line.pop();
+            }
+        }
+        Ok(Some(line))
+    }
+
+    pub(crate) fn write_output(&mut self, text: &str) -> io::Result<()> {
+        self.output.write_all(text.as_bytes())?;
+        self.output.flush()
+    }
+
+    fn execute_all(&mut self, stmts: &[Stmt]) -> Result<(), RuntimeError> {
         for stmt in stmts {
-            match stmt {
-                Stmt::Expression { expression } => {
-                    expression.evaluate(Rc::get_mut(&mut self.environment).expect("could not get mutable refernce to environment"))?;
-                }
-                Stmt::Print { expression } => {
-                    let value = expression.evaluate(Rc::get_mut(&mut self.environment).expect("could not get mutable refernce to environment"))?;
-                    println!("{value:?}");
-                }
-                Stmt::Var {
-                    expression,
-                    name,
-                    initialiser,
-                } => {
-                    let value = initialiser.evaluate(Rc::get_mut(&mut self.environment).expect("could not get mutable refernce to environment"))?;
-
-                    Rc::get_mut(&mut self.environment).expect("could not get mutable ref to env").define(name.lexeme, value);
-                }
-                Stmt::Block {
-                    statements
-                } => {
-                    let mut new_environment = Environment::new();
-                    new_environment.enclosing = Some(self.environment.clone());                    
-                    let old_environment = self.environment.clone();
-                    self.environment = Rc::new(new_environment);
-                    let block_result = self.interpret(statements);
-                    self.environment = old_environment;
-                    
-                    block_result?;
-                }
-            };
+            self.execute(stmt).map_err(|err| self.traced(err))?;
         }
         Ok(())
     }
-}
\ No newline at end of file
+
+    fn execute(&mut self, stmt: &Stmt) -> Result<(), RuntimeError> {
+        match stmt {
+            Stmt::Expression { expression } => {
+                expression.evaluate(self)?;
+            }
+            Stmt::Print { keyword, expression } => {
+                let value = expression.evaluate(self)?;
+                writeln!(self.output, "{}", value).map_err(|err| {
+                    RuntimeError::new("IOError", format!("Could not write output: {}", err), keyword.line_number)
+                })?;
+            }
+            Stmt::Var {
+                keyword: _,
+                name,
+                initialiser,
+            } => {
+                let value = initialiser.evaluate(self)?;
+
+                self.environment.borrow_mut().define(name.lexeme.clone(), value);
+            }
+            Stmt::Block {
+                brace: _,
+                statements,
+            } => {
+                let new_environment = Environment::new_enclosed(self.environment.clone());
+                self.execute_block(statements, new_environment)?;
+            }
+            Stmt::Function {
+                keyword: _,
+                name,
+                params,
+                body,
+            } => {
+                let function = Function {
+                    name: name.clone(),
+                    params: params.clone(),
+                    body: body.clone(),
+                    closure: self.environment.clone(),
+                    file: self.current_file().to_string(),
+                };
+                self.environment
+                    .borrow_mut()
+                    .define(name.lexeme.clone(), LiteralValue::Function(Rc::new(function)));
+            }
+            Stmt::If {
+                keyword: _,
+                condition,
+                then_branch,
+                else_branch,
+            } => {
+                if !condition.evaluate(self)?.is_falsy() {
+                    self.execute(then_branch)?;
+                } else if let Some(else_branch) = else_branch {
+                    self.execute(else_branch)?;
+                }
+            }
+            Stmt::While { keyword: _, condition, body } => {
+                while self.returning.is_none() && !condition.evaluate(self)?.is_falsy() {
+                    self.execute(body)?;
+                }
+            }
+            Stmt::Return { keyword: _, value } => {
+                let value = match value {
+                    Some(value) => value.evaluate(self)?,
+                    None => LiteralValue::Nil,
+                };
+                self.returning = Some(value);
+            }
+            Stmt::Throw { keyword, value } => {
+                let value = value.evaluate(self)?;
+                return Err(match value {
+                    LiteralValue::Error(err) => err.as_ref().clone(),
+                    other => RuntimeError::new("Error", other.to_string(), keyword.line_number),
+                });
+            }
+            Stmt::Import {
+                keyword,
+                path,
+                alias,
+            } => {
+                let module = self.import_module(path, keyword.line_number)?;
+                self.environment
+                    .borrow_mut()
+                    .define(alias.lexeme.clone(), LiteralValue::Module(module));
+            }
+            Stmt::ImportFrom {
+                keyword,
+                path,
+                names,
+            } => {
+                let module = self.import_module(path, keyword.line_number)?;
+                for name in names {
+                    let value = module.get(&name.lexeme).ok_or_else(|| {
+                        RuntimeError::new(
+                            "ImportError",
+                            format!("Module '{}' does not export '{}'", module.name(), name.lexeme),
+                            name.line_number,
+                        )
+                    })?;
+                    self.environment.borrow_mut().define(name.lexeme.clone(), value);
+                }
+            }
+            Stmt::Export { keyword: _, declaration } => self.execute(declaration)?,
+            Stmt::Try {
+                keyword: _,
+                body,
+                catch_clause,
+                finally_body,
+            } => {
+                let mut result = self.execute_block(body, Environment::new_enclosed(self.environment.clone()));
+
+                if let (Err(err), Some((name, statements))) = (&result, catch_clause)
+                    && self.exiting.is_none()
+                {
+                    let mut catch_environment = Environment::new_enclosed(self.environment.clone());
+                    catch_environment.define(
+                        name.lexeme.clone(),
+                        LiteralValue::Error(Rc::new(self.traced(err.clone()))),
+                    );
+                    result = self.execute_block(statements, catch_environment);
+                }
+
+                // An error raised by the finally block replaces whatever the
+                // try/catch part produced, otherwise the earlier outcome stands.
+                // A pending return is parked so the finally block runs in full.
+                if let Some(statements) = finally_body {
+                    let returning = self.returning.take();
+                    self.execute_block(statements, Environment::new_enclosed(self.environment.clone()))?;
+                    if self.returning.is_none() {
+                        self.returning = returning;
+                    }
+                }
+
+                result?;
+            }
+            Stmt::Error { token, message } => {
+                return Err(RuntimeError::new("SyntaxError", message.clone(), token.line_number));
+            }
+        };
+        Ok(())
+    }
+
+    fn execute_block(&mut self, statements: &[Stmt], environment: Environment) -> Result<(), RuntimeError> {
+        let old_environment = self.environment.clone();
+        self.environment = Rc::new(RefCell::new(environment));
+        let mut block_result = Ok(());
+        for stmt in statements {
+            block_result = self.execute(stmt);
+            if block_result.is_err() || self.returning.is_some() {
+                break;
+            }
+        }
+        self.environment = old_environment;
+
+        block_result
+    }
+
+    /// Calls `callee` with already evaluated arguments. `line` is the line of
+    /// the call site, used for arity errors and stack traces.
+    pub fn call(&mut self, callee: &LiteralValue, arguments: Vec<LiteralValue>, line: u64) -> Result<LiteralValue, RuntimeError> {
+        match callee {
+            LiteralValue::Function(function) => {
+                if arguments.len() != function.arity() {
+                    return Err(RuntimeError::type_error(
+                        format!(
+                            "{} expected {} arguments but got {}",
+                            function.name.lexeme,
+                            function.arity(),
+                            arguments.len()
+                        ),
+                        line,
+                    ));
+                }
+                self.call_function(function, arguments, line)
+            }
+            LiteralValue::Native(native) => {
+                if !native.arity.accepts(arguments.len()) {
+                    return Err(RuntimeError::type_error(
+                        format!(
+                            "{} expected {} arguments but got {}",
+                            native.name,
+                            native.arity,
+                            arguments.len()
+                        ),
+                        line,
+                    ));
+                }
+                // Natives don't know where they were called from, so errors
+                // they raise are attributed to the call site. The same goes
+                // for functions they call back into.
+                self.current_frame().line = line;
+                (native.fun)(self, arguments).map_err(|mut err| {
+                    if err.line == 0 {
+                        err.line = line;
+                    }
+                    err
+                })
+            }
+            other => Err(RuntimeError::type_error(
+                format!("Can only call functions, not {}", other.to_type()),
+                line,
+            )),
+        }
+    }
+
+    fn call_function(&mut self, function: &Function, arguments: Vec<LiteralValue>, line: u64) -> Result<LiteralValue, RuntimeError> {
+        if self.frames.len() >= MAX_CALL_DEPTH {
+            return Err(RuntimeError::new(
+                "RecursionError",
+                format!("Maximum call depth of {MAX_CALL_DEPTH} exceeded"),
+                line,
+            ));
+        }
+
+        // Line 0 means a native is calling back; the frame already points at
+        // the native's call site.
+        if line != 0 {
+            self.current_frame().line = line;
+        }
+        self.frames.push(CallFrame {
+            function: function.name.lexeme.clone(),
+            file: function.file.clone(),
+            line: function.name.line_number,
+        });
+
+        let mut environment = Environment::new_enclosed(function.closure.clone());
+        for (param, argument) in function.params.iter().zip(arguments) {
+            environment.define(param.lexeme.clone(), argument);
+        }
+
+        let result = self
+            .execute_block(&function.body, environment)
+            .map_err(|err| self.traced(err));
+        let value = self.returning.take().unwrap_or(LiteralValue::Nil);
+        self.frames.pop();
+
+        result.map(|_| value)
+    }
+
+    // Loads, runs and caches the module named by an import statement. The
+    // module's top-level code runs in a fresh global environment and shows
+    // up as a `<module>` frame in stack traces.
+    fn import_module(&mut self, path: &Token, line: u64) -> Result<Rc<Module>, RuntimeError> {
+        let import_error = |message: String| RuntimeError::new("ImportError", message, line);
+
+        let requested = match &path.literal {
+            Some(scanner::LiteralValue::StringValue(requested)) => requested.clone(),
+            _ => return Err(import_error(format!("Invalid module path {}", path.lexeme))),
+        };
+        let resolved = self
+            .module_paths
+            .resolve(self.current_file(), &requested)
+            .map_err(import_error)?;
+
+        if let Some(module) = self.modules.get(&resolved) {
+            return Ok(module.clone());
+        }
+        if let Some(start) = self.loading.iter().position(|loading| loading == &resolved) {
+            let cycle = self.loading[start..]
+                .iter()
+                .chain(std::iter::once(&resolved))
+                .map(|path| path.display().to_string())
+                .collect::<Vec<String>>()
+                .join(" -> ");
+            return Err(import_error(format!("Import cycle detected: {}", cycle)));
+        }
+
+        let source = fs::read_to_string(&resolved)
+            .map_err(|err| import_error(format!("Cannot read module '{}': {}", requested, err)))?;
+        let tokens = Scanner::new(&source)
+            .scan_tokens()
+            .map_err(|err| import_error(format!("In module '{}':\n{}", requested, err.trim_end())))?;
+        let stmts = Parser::new(tokens)
+            .parse()
+            .map_err(|err| import_error(format!("In module '{}':\n{}", requested, err)))?;
+
+        let globals = Rc::new(RefCell::new(new_globals(&self.builtins)));
+        let previous_environment = std::mem::replace(&mut self.environment, globals.clone());
+        self.loading.push(resolved.clone());
+        self.current_frame().line = line;
+        self.frames.push(CallFrame {
+            function: "<module>".to_string(),
+            file: resolved.display().to_string(),
+            line: 0,
+        });
+
+        let result = self.execute_all(&stmts);
+
+        self.frames.pop();
+        self.loading.pop();
+        self.environment = previous_environment;
+        result?;
+
+        let module = Rc::new(Module {
+            path: resolved.clone(),
+            globals,
+            exports: module::exported_names(&stmts),
+        });
+        self.modules.insert(resolved, module.clone());
+        Ok(module)
+    }
+
+    fn current_frame(&mut self) -> &mut CallFrame {
+        self.frames.last_mut().expect("the script frame is never popped")
+    }
+
+    pub(crate) fn current_file(&self) -> &str {
+        &self.frames.last().expect("the script frame is never popped").file
+    }
+
+    // Fills in the stack trace of an error that has not been traced yet.
+    // Errors are traced before they leave the frame that raised them, so
+    // the call stack still describes where they came from.
+    fn traced(&mut self, mut err: RuntimeError) -> RuntimeError {
+        if err.trace.is_empty() {
+            self.current_frame().line = err.line;
+            err.trace = self
+                .frames
+                .iter()
+                .rev()
+                .map(|frame| format!("at {} ({}:{})", frame.function, frame.file, frame.line))
+                .collect();
+        }
+        err
+    }
+}
+
+fn new_globals(builtins: &[(String, LiteralValue)]) -> Environment {
+    let mut globals = Environment::new();
+    for (name, value) in builtins {
+        globals.define(name.clone(), value.clone());
+    }
+    globals
+}
+
+impl Default for Interpreter {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::Parser;
+    use crate::output::OutputBuffer;
+    use crate::scanner::Scanner;
+
+    fn run(interpreter: &mut Interpreter, source: &str) -> Result<(), String> {
+        let tokens = Scanner::new(source).scan_tokens()?;
+        let stmts = Parser::new(tokens).parse()?;
+        interpreter.interpret(stmts)
+    }
+
+    fn global(interpreter: &Interpreter, name: &str) -> Option<LiteralValue> {
+        interpreter.environment.borrow().get(name)
+    }
+
+    #[test]
+    fn block_assigns_enclosing_variable() {
+        let mut interpreter = Interpreter::new();
+        run(&mut interpreter, "var a = 1; { var b = 2; a = a + b; }").unwrap();
+        assert_eq!(global(&interpreter, "a"), Some(LiteralValue::Number(3.0)));
+    }
+
+    #[test]
+    fn catch_binds_thrown_error() {
+        let mut interpreter = Interpreter::new();
+        let source = r#"
+            var kind = nil;
+            var message = nil;
+            try {
+                throw "boom";
+            } catch (e) {
+                kind = e.kind;
+                message = e.message;
+            }
+        "#;
+        run(&mut interpreter, source).unwrap();
+        assert_eq!(global(&interpreter, "kind"), Some(LiteralValue::StringValue("Error".to_string())));
+        assert_eq!(global(&interpreter, "message"), Some(LiteralValue::StringValue("boom".to_string())));
+    }
+
+    #[test]
+    fn runtime_errors_are_catchable() {
+        let mut interpreter = Interpreter::new();
+        let source = r#"
+            var undefined_kind = nil;
+            var type_kind = nil;
+            try { print missing; } catch (e) { undefined_kind = e.kind; }
+            try { print 1 + "a"; } catch (e) { type_kind = e.kind; }
+        "#;
+        run(&mut interpreter, source).unwrap();
+        assert_eq!(global(&interpreter, "undefined_kind"), Some(LiteralValue::StringValue("NameError".to_string())));
+        assert_eq!(global(&interpreter, "type_kind"), Some(LiteralValue::StringValue("TypeError".to_string())));
+    }
+
+    #[test]
+    fn finally_runs_on_both_paths() {
+        let mut interpreter = Interpreter::new();
+        let source = r#"
+            var count = 0;
+            try { count = count + 1; } finally { count = count + 10; }
+            try { throw "x"; } catch (e) { count = count + 100; } finally { count = count + 1000; }
+        "#;
+        run(&mut interpreter, source).unwrap();
+        assert_eq!(global(&interpreter, "count"), Some(LiteralValue::Number(1111.0)));
+    }
+
+    #[test]
+    fn uncaught_error_propagates_after_finally() {
+        let mut interpreter = Interpreter::new();
+        let result = run(&mut interpreter, r#"var done = false; try { throw "late"; } finally { done = true; }"#);
+        assert!(result.unwrap_err().starts_with("Error: late"));
+        assert_eq!(global(&interpreter, "done"), Some(LiteralValue::True));
+    }
+
+    #[test]
+    fn rethrow_keeps_original_error() {
+        let mut interpreter = Interpreter::new();
+        let source = r#"
+            try {
+                try { print nope; } catch (e) { throw e; }
+            } catch (outer) {
+                var kind = outer.kind;
+                throw outer;
+            }
+        "#;
+        let err = run(&mut interpreter, source).unwrap_err();
+        assert!(err.starts_with("NameError: Variable 'nope' has not been declared"));
+    }
+
+    #[test]
+    fn functions_return_values_and_close_over_scope() {
+        let mut interpreter = Interpreter::new();
+        let source = r#"
+            fun make_counter() {
+                var count = 0;
+                fun counter() {
+                    count = count + 1;
+                    return count;
+                }
+                return counter;
+            }
+            var counter = make_counter();
+            counter();
+            var result = counter();
+
+            fun fib(n) {
+                if (n < 2) return n;
+                return fib(n - 1) + fib(n - 2);
+            }
+            var f = fib(10);
+
+            var total = 0;
+            for (var i = 0; i < 5; i = i + 1) {
+                if (i == 3 or false) total = total + 100;
+                else total = total + i;
+            }
+        "#;
+        run(&mut interpreter, source).unwrap();
+        assert_eq!(global(&interpreter, "result"), Some(LiteralValue::Number(2.0)));
+        assert_eq!(global(&interpreter, "f"), Some(LiteralValue::Number(55.0)));
+        assert_eq!(global(&interpreter, "total"), Some(LiteralValue::Number(107.0)));
+    }
+
+    #[test]
+    fn return_inside_try_still_runs_finally() {
+        let mut interpreter = Interpreter::new();
+        let source = r#"
+            var cleaned = false;
+            fun f() {
+                try { return 1; } finally { cleaned = true; }
+                return 2;
+            }
+            var result = f();
+        "#;
+        run(&mut interpreter, source).unwrap();
+        assert_eq!(global(&interpreter, "result"), Some(LiteralValue::Number(1.0)));
+        assert_eq!(global(&interpreter, "cleaned"), Some(LiteralValue::True));
+    }
+
+    #[test]
+    fn uncaught_error_lists_every_frame() {
+        let mut interpreter = Interpreter::for_file("math.fnky");
+        let source = "fun add(a, b) {\n  return a + b;\n}\nfun twice(x) {\n  return add(x, nil);\n}\n\ntwice(1);\n";
+        let err = run(&mut interpreter, source).unwrap_err();
+        assert_eq!(
+            err,
+            "TypeError: PLUS is not implemented for operands Number and nil\n    \
+             at add (math.fnky:2)\n    at twice (math.fnky:5)\n    at <script> (math.fnky:8)"
+        );
+    }
+
+    #[test]
+    fn caught_error_carries_trace() {
+        let mut interpreter = Interpreter::for_file("main.fnky");
+        let source = "fun fail() {\n  throw \"nope\";\n}\nvar trace = nil;\ntry {\n  fail();\n} catch (e) {\n  trace = e.trace;\n}\n";
+        run(&mut interpreter, source).unwrap();
+        assert_eq!(
+            global(&interpreter, "trace"),
+            Some(LiteralValue::StringValue("at fail (main.fnky:2)\nat <script> (main.fnky:6)".to_string()))
+        );
+    }
+
+    #[test]
+    fn runaway_recursion_is_an_error() {
+        let err = std::thread::Builder::new()
+            .stack_size(INTERPRETER_STACK_SIZE)
+            .spawn(|| {
+                let mut interpreter = Interpreter::new();
+                run(&mut interpreter, "fun f(n) { return f(n + 1); } f(0);").unwrap_err()
+            })
+            .unwrap()
+            .join()
+            .unwrap();
+        assert!(err.starts_with("RecursionError"));
+    }
+
+    #[test]
+    fn eval_returns_final_expression() {
+        let mut interpreter = Interpreter::new();
+        assert_eq!(interpreter.eval("1 + 2").unwrap(), LiteralValue::Number(3.0));
+        assert_eq!(interpreter.eval("var a = 4; a * 2;").unwrap(), LiteralValue::Number(8.0));
+        assert_eq!(interpreter.eval("var b = 1;").unwrap(), LiteralValue::Nil);
+        assert!(matches!(interpreter.eval("var = ;"), Err(EvalError::Syntax(_))));
+        assert!(matches!(interpreter.eval("missing"), Err(EvalError::Runtime(err)) if err.kind == "NameError"));
+    }
+
+    #[test]
+    fn print_writes_display_format_to_output() {
+        let output = OutputBuffer::new();
+        let mut interpreter = Interpreter::new();
+        interpreter.set_output(output.clone());
+        let source = r#"
+            print 3;
+            print 0.5;
+            print "plain text";
+            print nil;
+            print 1 < 2;
+            fun f() {}
+            print f;
+        "#;
+        run(&mut interpreter, source).unwrap();
+        assert_eq!(output.contents(), "3\n0.5\nplain text\nnil\ntrue\n<fn f>\n");
+    }
+
+    #[test]
+    fn host_reads_and_writes_globals() {
+        let mut interpreter = Interpreter::new();
+        interpreter.set_global("limit", 10);
+        interpreter.eval("var doubled = limit * 2;").unwrap();
+        assert_eq!(interpreter.get_global("doubled"), Some(LiteralValue::Number(20.0)));
+        assert_eq!(interpreter.get_global("nothing"), None);
+    }
+
+    #[test]
+    fn registered_functions_check_arity_and_types() {
+        let mut interpreter = Interpreter::new();
+        interpreter.register_fn("repeat", |s: String, n: usize| s.repeat(n));
+        assert_eq!(
+            interpreter.eval(r#"repeat("ab", 3)"#).unwrap(),
+            LiteralValue::StringValue("ababab".to_string())
+        );
+
+        let source = r#"
+            var arity = nil;
+            var types = nil;
+            try { repeat("ab"); } catch (e) { arity = e.message; }
+            try { repeat("ab", 1.5); } catch (e) { types = e.kind + ": " + e.message; }
+        "#;
+        interpreter.eval(source).unwrap();
+        assert_eq!(
+            interpreter.get_global("arity"),
+            Some(LiteralValue::StringValue("repeat expected 2 arguments but got 1".to_string()))
+        );
+        assert_eq!(
+            interpreter.get_global("types"),
+            Some(LiteralValue::StringValue("TypeError: repeat() argument 2 must be integer, not Number".to_string()))
+        );
+    }
+
+    #[test]
+    fn native_errors_point_at_call_site() {
+        let mut interpreter = Interpreter::for_file("host.fnky");
+        interpreter.register_fn("fail", |message: String| -> Result<(), String> { Err(message) });
+        let err = interpreter.eval("fun f() {\n  fail(\"no\");\n}\nf();").unwrap_err();
+        assert_eq!(err.to_string(), "Error: no\n    at f (host.fnky:2)\n    at <script> (host.fnky:4)");
+    }
+
+    #[test]
+    fn natives_can_call_back_into_scripts() {
+        let mut interpreter = Interpreter::new();
+        interpreter.define_native("apply_all", Arity::AtLeast(1), |interpreter, arguments| {
+            let mut total = 0.0;
+            for argument in &arguments[1..] {
+                if let LiteralValue::Number(x) = interpreter.call(&arguments[0], vec![argument.clone()], 0)? {
+                    total += x;
+                }
+            }
+            Ok(LiteralValue::Number(total))
+        });
+        interpreter.eval("fun square(x) { return x * x; }").unwrap();
+        assert_eq!(interpreter.eval("apply_all(square, 1, 2, 3)").unwrap(), LiteralValue::Number(14.0));
+        assert_eq!(
+            interpreter.call_global("square", vec![LiteralValue::Number(5.0)]).unwrap(),
+            LiteralValue::Number(25.0)
+        );
+    }
+}
@@ -0,0 +1,40 @@
+use std::cell::RefCell;
+use std::fmt;
+
+use crate::error::RuntimeError;
+use crate::expr::LiteralValue;
+
+type Source = Box<dyn Iterator<Item = Result<LiteralValue, RuntimeError>>>;
+
+/// A lazy sequence produced by the host, such as the lines of a file read
+/// with `io.read_lines`. Items are computed one at a time as the script
+/// asks for them, and producing one may fail.
+pub struct NativeIterator {
+    pub name: String,
+    source: RefCell<Source>,
+}
+
+impl NativeIterator {
+    pub fn new(name: &str, source: impl Iterator<Item = Result<LiteralValue, RuntimeError>> + 'static) -> Self {
+        Self {
+            name: name.to_string(),
+            source: RefCell::new(Box::new(source)),
+        }
+    }
+
+    pub fn next(&self) -> Option<Result<LiteralValue, RuntimeError>> {
+        self.source.borrow_mut().next()
+    }
+}
+
+impl PartialEq for NativeIterator {
+    fn eq(&self, other: &Self) -> bool {
+        std::ptr::eq(self, other)
+    }
+}
+
+impl fmt::Debug for NativeIterator {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "<iterator {}>", self.name)
+    }
+}
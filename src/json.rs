@@ -0,0 +1,451 @@
+use std::rc::Rc;
+
+use crate::error::RuntimeError;
+use crate::expr::LiteralValue;
+use crate::map::{Key, Map};
+
+// Nesting deeper than this is rejected instead of overflowing the stack.
+const MAX_DEPTH: usize = 512;
+
+// Wider indents are narrowed to this, as JavaScript's JSON.stringify does.
+const MAX_INDENT: usize = 10;
+
+/// Parses a JSON document. Objects become maps (keeping the order of their
+/// keys), arrays become lists and `null` becomes nil. Errors give the line
+/// and column of the offending character.
+pub fn parse(source: &str) -> Result<LiteralValue, String> {
+    let mut parser = JsonParser {
+        chars: source.chars().collect(),
+        current: 0,
+        depth: 0,
+    };
+    parser.skip_whitespace();
+    let value = parser.value()?;
+    parser.skip_whitespace();
+    if parser.current < parser.chars.len() {
+        return Err(parser.error("unexpected trailing characters"));
+    }
+    Ok(value)
+}
+
+/// Converts a value to JSON. With an indent, objects and arrays are spread
+/// over several lines indented by that many spaces per level, at most 10;
+/// without one the output is compact. Map keys that are not strings are
+/// converted to their JSON text. Functions, modules, errors and iterators
+/// cannot be converted, nor can NaN, infinities or lists and maps that
+/// contain themselves.
+pub fn stringify(value: &LiteralValue, indent: Option<usize>) -> Result<String, RuntimeError> {
+    let mut writer = JsonWriter {
+        out: String::new(),
+        indent: indent.map(|indent| indent.min(MAX_INDENT)),
+        open: vec![],
+    };
+    writer.value(value, 0)?;
+    Ok(writer.out)
+}
+
+struct JsonParser {
+    chars: Vec<char>,
+    current: usize,
+    depth: usize,
+}
+
+impl JsonParser {
+    fn error(&self, message: &str) -> String {
+        let before = &self.chars[..self.current.min(self.chars.len())];
+        let line = before.iter().filter(|&&ch| ch == '\n').count() + 1;
+        let column = before.iter().rev().take_while(|&&ch| ch != '\n').count() + 1;
+        format!("Invalid JSON at line {}, column {}: {}", line, column, message)
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.chars.get(self.current).copied()
+    }
+
+    fn skip_whitespace(&mut self) {
+        while matches!(self.peek(), Some(' ' | '\t' | '\n' | '\r')) {
+            self.current += 1;
+        }
+    }
+
+    fn expect(&mut self, expected: char) -> Result<(), String> {
+        if self.peek() == Some(expected) {
+            self.current += 1;
+            Ok(())
+        } else {
+            Err(self.error(&format!("expected '{}'", expected)))
+        }
+    }
+
+    fn value(&mut self) -> Result<LiteralValue, String> {
+        match self.peek() {
+            Some('{') => self.nested(Self::object),
+            Some('[') => self.nested(Self::array),
+            Some('"') => self.string().map(LiteralValue::StringValue),
+            Some('-' | '0'..='9') => self.number(),
+            Some('t') => self.literal("true", LiteralValue::True),
+            Some('f') => self.literal("false", LiteralValue::False),
+            Some('n') => self.literal("null", LiteralValue::Nil),
+            Some(_) => Err(self.error("expected a value")),
+            None => Err(self.error("unexpected end of input")),
+        }
+    }
+
+    fn nested(&mut self, parse: fn(&mut Self) -> Result<LiteralValue, String>) -> Result<LiteralValue, String> {
+        if self.depth >= MAX_DEPTH {
+            return Err(self.error("nesting is too deep"));
+        }
+        self.depth += 1;
+        let value = parse(self);
+        self.depth -= 1;
+        value
+    }
+
+    fn object(&mut self) -> Result<LiteralValue, String> {
+        self.expect('{')?;
+        let mut map = Map::new();
+        self.skip_whitespace();
+        if self.peek() == Some('}') {
+            self.current += 1;
+            return Ok(LiteralValue::map(map));
+        }
+        loop {
+            self.skip_whitespace();
+            if self.peek() != Some('"') {
+                return Err(self.error("expected a string key"));
+            }
+            let key = self.string()?;
+            self.skip_whitespace();
+            self.expect(':')?;
+            self.skip_whitespace();
+            let value = self.value()?;
+            map.insert(Key::String(key), value);
+            self.skip_whitespace();
+            match self.peek() {
+                Some(',') => self.current += 1,
+                Some('}') => {
+                    self.current += 1;
+                    return Ok(LiteralValue::map(map));
+                }
+                _ => return Err(self.error("expected ',' or '}'")),
+            }
+        }
+    }
+
+    fn array(&mut self) -> Result<LiteralValue, String> {
+        self.expect('[')?;
+        let mut items = vec![];
+        self.skip_whitespace();
+        if self.peek() == Some(']') {
+            self.current += 1;
+            return Ok(LiteralValue::list(items));
+        }
+        loop {
+            self.skip_whitespace();
+            items.push(self.value()?);
+            self.skip_whitespace();
+            match self.peek() {
+                Some(',') => self.current += 1,
+                Some(']') => {
+                    self.current += 1;
+                    return Ok(LiteralValue::list(items));
+                }
+                _ => return Err(self.error("expected ',' or ']'")),
+            }
+        }
+    }
+
+    fn string(&mut self) -> Result<String, String> {
+        self.expect('"')?;
+        let mut result = String::new();
+        loop {
+            match self.peek() {
+                None => return Err(self.error("unterminated string")),
+                Some('"') => {
+                    self.current += 1;
+                    return Ok(result);
+                }
+                Some('\\') => {
+                    self.current += 1;
+                    let escaped = match self.peek() {
+                        Some('"') => '"',
+                        Some('\\') => '\\',
+                        Some('/') => '/',
+                        Some('b') => '\u{8}',
+                        Some('f') => '\u{c}',
+                        Some('n') => '\n',
+                        Some('r') => '\r',
+                        Some('t') => '\t',
+                        Some('u') => {
+                            self.current += 1;
+                            result.push(self.unicode_escape()?);
+                            continue;
+                        }
+                        _ => return Err(self.error("invalid escape sequence")),
+                    };
+                    self.current += 1;
+                    result.push(escaped);
+                }
+                Some(ch) if (ch as u32) < 0x20 => return Err(self.error("control character in string")),
+                Some(ch) => {
+                    self.current += 1;
+                    result.push(ch);
+                }
+            }
+        }
+    }
+
+    // The part of a `\uXXXX` escape after the `u`, combining surrogate
+    // pairs into one character.
+    fn unicode_escape(&mut self) -> Result<char, String> {
+        let first = self.hex4()?;
+        let code = if (0xD800..0xDC00).contains(&first) {
+            if self.chars.get(self.current..self.current + 2) != Some(&['\\', 'u']) {
+                return Err(self.error("unpaired surrogate in \\u escape"));
+            }
+            self.current += 2;
+            let second = self.hex4()?;
+            if !(0xDC00..0xE000).contains(&second) {
+                return Err(self.error("unpaired surrogate in \\u escape"));
+            }
+            0x10000 + ((first - 0xD800) << 10) + (second - 0xDC00)
+        } else {
+            first
+        };
+        char::from_u32(code).ok_or_else(|| self.error("invalid \\u escape"))
+    }
+
+    fn hex4(&mut self) -> Result<u32, String> {
+        let digits: String = self.chars.iter().skip(self.current).take(4).collect();
+        if digits.len() != 4 || !digits.chars().all(|ch| ch.is_ascii_hexdigit()) {
+            return Err(self.error("expected four hex digits in \\u escape"));
+        }
+        self.current += 4;
+        Ok(u32::from_str_radix(&digits, 16).expect("checked to be hex digits"))
+    }
+
+    fn number(&mut self) -> Result<LiteralValue, String> {
+        let start = self.current;
+        let digits = |parser: &mut Self| {
+            let before = parser.current;
+            while matches!(parser.peek(), Some('0'..='9')) {
+                parser.current += 1;
+            }
+            parser.current > before
+        };
+
+        if self.peek() == Some('-') {
+            self.current += 1;
+        }
+        if self.peek() == Some('0') {
+            self.current += 1;
+        } else if !digits(self) {
+            return Err(self.error("expected a digit"));
+        }
+        if self.peek() == Some('.') {
+            self.current += 1;
+            if !digits(self) {
+                return Err(self.error("expected a digit after '.'"));
+            }
+        }
+        if matches!(self.peek(), Some('e' | 'E')) {
+            self.current += 1;
+            if matches!(self.peek(), Some('+' | '-')) {
+                self.current += 1;
+            }
+            if !digits(self) {
+                return Err(self.error("expected a digit in exponent"));
+            }
+        }
+
+        let text: String = self.chars[start..self.current].iter().collect();
+        text.parse::<f64>()
+            .map(LiteralValue::Number)
+            .map_err(|_| self.error("invalid number"))
+    }
+
+    fn literal(&mut self, word: &str, value: LiteralValue) -> Result<LiteralValue, String> {
+        let matches = word
+            .chars()
+            .enumerate()
+            .all(|(offset, ch)| self.chars.get(self.current + offset) == Some(&ch));
+        if matches {
+            self.current += word.chars().count();
+            Ok(value)
+        } else {
+            Err(self.error("expected a value"))
+        }
+    }
+}
+
+struct JsonWriter {
+    out: String,
+    indent: Option<usize>,
+    // Lists and maps currently being written, to detect cycles.
+    open: Vec<*const ()>,
+}
+
+impl JsonWriter {
+    fn value(&mut self, value: &LiteralValue, level: usize) -> Result<(), RuntimeError> {
+        match value {
+            LiteralValue::Nil => self.out.push_str("null"),
+            LiteralValue::True => self.out.push_str("true"),
+            LiteralValue::False => self.out.push_str("false"),
+            LiteralValue::Number(x) if x.is_finite() => self.out.push_str(&x.to_string()),
+            LiteralValue::Number(x) => {
+                return Err(RuntimeError::new(
+                    "ValueError",
+                    format!("{} cannot be converted to JSON", x),
+                    0,
+                ))
+            }
+            LiteralValue::StringValue(s) => self.string(s),
+            LiteralValue::List(items) => {
+                self.enter(Rc::as_ptr(items) as *const ())?;
+                let items = items.borrow();
+                self.out.push('[');
+                for (index, item) in items.iter().enumerate() {
+                    self.separator(index, level + 1);
+                    self.value(item, level + 1)?;
+                }
+                self.close(!items.is_empty(), level, ']');
+            }
+            LiteralValue::Map(map) => {
+                self.enter(Rc::as_ptr(map) as *const ())?;
+                let map = map.borrow();
+                self.out.push('{');
+                for (index, (key, item)) in map.iter().enumerate() {
+                    self.separator(index, level + 1);
+                    let key = match key {
+                        Key::String(s) => s.clone(),
+                        other => stringify(&other.to_value(), None)?,
+                    };
+                    self.string(&key);
+                    self.out.push(':');
+                    if self.indent.is_some() {
+                        self.out.push(' ');
+                    }
+                    self.value(item, level + 1)?;
+                }
+                self.close(!map.is_empty(), level, '}');
+            }
+            other => {
+                return Err(RuntimeError::type_error(
+                    format!("{} cannot be converted to JSON", other.to_type()),
+                    0,
+                ))
+            }
+        }
+        Ok(())
+    }
+
+    fn enter(&mut self, container: *const ()) -> Result<(), RuntimeError> {
+        if self.open.contains(&container) {
+            return Err(RuntimeError::new(
+                "ValueError",
+                "Cannot convert a value that contains itself to JSON".to_string(),
+                0,
+            ));
+        }
+        self.open.push(container);
+        Ok(())
+    }
+
+    // Writes what goes before the item at `index` of a list or map.
+    fn separator(&mut self, index: usize, level: usize) {
+        if index > 0 {
+            self.out.push(',');
+        }
+        self.newline(level);
+    }
+
+    fn close(&mut self, has_items: bool, level: usize, bracket: char) {
+        self.open.pop();
+        if has_items {
+            self.newline(level);
+        }
+        self.out.push(bracket);
+    }
+
+    fn newline(&mut self, level: usize) {
+        if let Some(indent) = self.indent {
+            self.out.push('\n');
+            self.out.push_str(&" ".repeat(indent.saturating_mul(level)));
+        }
+    }
+
+    fn string(&mut self, s: &str) {
+        self.out.push('"');
+        for ch in s.chars() {
+            match ch {
+                '"' => self.out.push_str("\\\""),
+                '\\' => self.out.push_str("\\\\"),
+                '\n' => self.out.push_str("\\n"),
+                '\r' => self.out.push_str("\\r"),
+                '\t' => self.out.push_str("\\t"),
+                '\u{8}' => self.out.push_str("\\b"),
+                '\u{c}' => self.out.push_str("\\f"),
+                ch if (ch as u32) < 0x20 => self.out.push_str(&format!("\\u{:04x}", ch as u32)),
+                ch => self.out.push(ch),
+            }
+        }
+        self.out.push('"');
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_maps_json_onto_values() {
+        let value = parse(r#" {"name": "fnky", "tags": ["a", "b"], "n": -1.5e2, "ok": true, "none": null, "nested": {}} "#)
+            .unwrap();
+        assert_eq!(
+            value.to_string(),
+            r#"{"name": "fnky", "tags": ["a", "b"], "n": -150, "ok": true, "none": nil, "nested": {}}"#
+        );
+        assert_eq!(
+            parse(r#""tab\tquote\" é 🎉""#).unwrap(),
+            LiteralValue::StringValue("tab\tquote\" é 🎉".to_string())
+        );
+    }
+
+    #[test]
+    fn parse_errors_point_at_the_problem() {
+        assert_eq!(parse("[1, 2,]").unwrap_err(), "Invalid JSON at line 1, column 7: expected a value");
+        assert_eq!(parse("{\n  \"a\" 1}").unwrap_err(), "Invalid JSON at line 2, column 7: expected ':'");
+        assert_eq!(parse("01").unwrap_err(), "Invalid JSON at line 1, column 2: unexpected trailing characters");
+        assert!(parse("\"abc").unwrap_err().contains("unterminated string"));
+        assert!(parse(&"[".repeat(MAX_DEPTH + 1)).unwrap_err().contains("nesting is too deep"));
+    }
+
+    #[test]
+    fn stringify_round_trips() {
+        let source = r#"{"a":[1,2.5,"x\n\"y\""],"b":{"c":null,"d":false},"e":[],"f":{}}"#;
+        let value = parse(source).unwrap();
+        assert_eq!(stringify(&value, None).unwrap(), source);
+        assert_eq!(
+            stringify(&value, Some(2)).unwrap(),
+            "{\n  \"a\": [\n    1,\n    2.5,\n    \"x\\n\\\"y\\\"\"\n  ],\n  \"b\": {\n    \"c\": null,\n    \"d\": false\n  },\n  \"e\": [],\n  \"f\": {}\n}"
+        );
+        let list = LiteralValue::list(vec![LiteralValue::Nil]);
+        assert_eq!(stringify(&list, Some(usize::MAX)).unwrap(), format!("[\n{}null\n]", " ".repeat(10)));
+    }
+
+    #[test]
+    fn stringify_rejects_unrepresentable_values() {
+        let list = LiteralValue::list(vec![]);
+        if let LiteralValue::List(items) = &list {
+            items.borrow_mut().push(list.clone());
+        }
+        assert_eq!(stringify(&list, None).unwrap_err().kind, "ValueError");
+        // The same list twice, side by side, is not a cycle.
+        let inner = LiteralValue::list(vec![LiteralValue::Nil]);
+        assert_eq!(
+            stringify(&LiteralValue::list(vec![inner.clone(), inner]), None).unwrap(),
+            "[[null],[null]]"
+        );
+        assert_eq!(stringify(&LiteralValue::Number(f64::NAN), None).unwrap_err().kind, "ValueError");
+    }
+}
@@ -0,0 +1,59 @@
+//! Fnky is a small, dynamically typed scripting language with a tree-walking
+//! interpreter. Besides the `fnky` command line tool, the interpreter can be
+//! embedded in Rust programs:
+//!
+//! ```
+//! use fnky::{Interpreter, LiteralValue};
+//!
+//! let mut interpreter = Interpreter::new();
+//! interpreter.set_global("base", 40);
+//! interpreter.register_fn("greet", |name: String| format!("hello, {}", name));
+//!
+//! assert_eq!(interpreter.eval("base + 2").unwrap(), LiteralValue::Number(42.0));
+//! assert_eq!(
+//!     interpreter.eval(r#"greet("fnky")"#).unwrap(),
+//!     LiteralValue::StringValue("hello, fnky".to_string())
+//! );
+//! ```
+//!
+//! Interpreted calls recurse on the native stack; run deeply recursive
+//! scripts on a thread with at least [`INTERPRETER_STACK_SIZE`] bytes of
+//! stack.
+
+pub mod ast;
+pub mod callable;
+pub mod convert;
+pub mod date;
+pub mod environment;
+pub mod error;
+pub mod expr;
+pub mod format;
+pub mod interpreter;
+pub mod iterator;
+pub mod json;
+pub mod lint;
+pub mod lsp;
+pub mod map;
+pub mod module;
+pub mod output;
+pub mod parser;
+pub mod pattern;
+pub mod printer;
+pub mod project;
+pub mod repl;
+pub mod sandbox;
+pub mod scanner;
+pub mod session;
+pub mod stdlib;
+pub mod stmt;
+pub mod syntax;
+#[cfg(test)]
+mod testing;
+
+pub use callable::Arity;
+pub use convert::{FromValue, IntoValue};
+pub use error::{EvalError, RuntimeError};
+pub use expr::LiteralValue;
+pub use interpreter::{Interpreter, INTERPRETER_STACK_SIZE};
+pub use output::OutputBuffer;
+pub use sandbox::{Access, Sandbox};
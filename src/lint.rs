@@ -0,0 +1,606 @@
+//! The `fnky lint` checks. They run over the parsed statements, so they see
+//! `for` loops as the `while` loops they stand for; comments only matter for
+//! `// fnky:allow(rule)` suppressions.
+
+use std::fmt;
+
+use crate::expr::{Expr, LiteralValue};
+use crate::parser::Parser;
+use crate::scanner::{Scanner, Token, TokenType};
+use crate::stmt::Stmt;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Rule {
+    UnusedVariable,
+    ShadowedBinding,
+    UnreachableCode,
+    SelfComparison,
+    AssignmentInCondition,
+    PrintInLibrary,
+    ConstantCondition,
+}
+
+impl Rule {
+    pub const ALL: [Rule; 7] = [
+        Rule::UnusedVariable,
+        Rule::ShadowedBinding,
+        Rule::UnreachableCode,
+        Rule::SelfComparison,
+        Rule::AssignmentInCondition,
+        Rule::PrintInLibrary,
+        Rule::ConstantCondition,
+    ];
+
+    /// The name used in `fnky.toml` and in `fnky:allow(...)` comments.
+    pub fn id(self) -> &'static str {
+        match self {
+            Rule::UnusedVariable => "unused-variable",
+            Rule::ShadowedBinding => "shadowed-binding",
+            Rule::UnreachableCode => "unreachable-code",
+            Rule::SelfComparison => "self-comparison",
+            Rule::AssignmentInCondition => "assignment-in-condition",
+            Rule::PrintInLibrary => "print-in-library",
+            Rule::ConstantCondition => "constant-condition",
+        }
+    }
+
+    pub fn from_id(id: &str) -> Option<Rule> {
+        Rule::ALL.into_iter().find(|rule| rule.id() == id)
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Severity {
+    Warning,
+    Error,
+}
+
+impl fmt::Display for Severity {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Severity::Warning => write!(f, "warning"),
+            Severity::Error => write!(f, "error"),
+        }
+    }
+}
+
+/// Which rules run and how severe their findings are. Every rule is a
+/// warning unless configured otherwise, e.g. from the `[lint]` section of
+/// `fnky.toml`:
+///
+/// ```toml
+/// [lint]
+/// unused-variable = "error"
+/// shadowed-binding = "off"
+/// ```
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct LintConfig {
+    overrides: Vec<(Rule, Option<Severity>)>,
+}
+
+impl LintConfig {
+    /// Sets the severity of a rule; `None` turns it off.
+    pub fn set(&mut self, rule: Rule, severity: Option<Severity>) {
+        self.overrides.retain(|(other, _)| *other != rule);
+        self.overrides.push((rule, severity));
+    }
+
+    /// Like `set`, from a rule id and `"off"`, `"warning"` or `"error"`.
+    pub fn set_level(&mut self, id: &str, level: &str) -> Result<(), String> {
+        let rule = Rule::from_id(id).ok_or_else(|| format!("Unknown lint rule '{}'", id))?;
+        let severity = match level {
+            "off" => None,
+            "warning" => Some(Severity::Warning),
+            "error" => Some(Severity::Error),
+            _ => {
+                return Err(format!(
+                    "Unknown lint level '{}' for '{}': expected \"off\", \"warning\" or \"error\"",
+                    level, id
+                ))
+            }
+        };
+        self.set(rule, severity);
+        Ok(())
+    }
+
+    pub fn severity(&self, rule: Rule) -> Option<Severity> {
+        self.overrides
+            .iter()
+            .find(|(other, _)| *other == rule)
+            .map_or(Some(Severity::Warning), |(_, severity)| *severity)
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Diagnostic {
+    pub rule: Rule,
+    pub severity: Severity,
+    pub line: u64,
+    pub message: String,
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}[{}]: {} at line {}", self.severity, self.rule.id(), self.message, self.line)
+    }
+}
+
+/// Parses and lints a file, dropping the findings suppressed by a
+/// `// fnky:allow(rule, ...)` comment on the same line or the line before.
+/// Fails with the scanner's or parser's error if the source does not parse.
+pub fn lint_source(source: &str, config: &LintConfig) -> Result<Vec<Diagnostic>, String> {
+    let stmts = Parser::new(Scanner::new(source).scan_tokens()?).parse()?;
+    let allowed: Vec<(u64, Rule)> = Scanner::with_comments(source)
+        .scan_tokens()?
+        .iter()
+        .filter(|token| token.token_type == TokenType::COMMENT)
+        .flat_map(|comment| allowed_rules(&comment.lexeme).into_iter().map(|rule| (comment.line_number, rule)))
+        .collect();
+
+    let mut diagnostics = lint(&stmts, config);
+    diagnostics.retain(|diagnostic| {
+        !allowed
+            .iter()
+            .any(|&(line, rule)| rule == diagnostic.rule && (line == diagnostic.line || line + 1 == diagnostic.line))
+    });
+    Ok(diagnostics)
+}
+
+/// Lints a parsed program. A program with a top-level `export` is treated
+/// as a library module. Findings come back in line order.
+pub fn lint(stmts: &[Stmt], config: &LintConfig) -> Vec<Diagnostic> {
+    let mut linter = Linter {
+        config,
+        scopes: vec![vec![]],
+        library: stmts.iter().any(|stmt| matches!(stmt, Stmt::Export { .. })),
+        diagnostics: vec![],
+    };
+    linter.stmts(stmts);
+    linter.diagnostics.sort_by_key(|diagnostic| diagnostic.line);
+    linter.diagnostics
+}
+
+fn allowed_rules(comment: &str) -> Vec<Rule> {
+    let Some((_, rest)) = comment.split_once("fnky:allow(") else {
+        return vec![];
+    };
+    let ids = rest.split_once(')').map_or(rest, |(ids, _)| ids);
+    ids.split(',').filter_map(|id| Rule::from_id(id.trim())).collect()
+}
+
+struct Binding {
+    name: String,
+    kind: &'static str,
+    line: u64,
+    used: bool,
+    // Parameters, `catch` names and globals may go unused.
+    report_unused: bool,
+}
+
+struct Linter<'a> {
+    config: &'a LintConfig,
+    // The innermost scope is last; the first holds the globals.
+    scopes: Vec<Vec<Binding>>,
+    library: bool,
+    diagnostics: Vec<Diagnostic>,
+}
+
+impl Linter<'_> {
+    fn report(&mut self, rule: Rule, line: u64, message: String) {
+        if let Some(severity) = self.config.severity(rule) {
+            self.diagnostics.push(Diagnostic {
+                rule,
+                severity,
+                line,
+                message,
+            });
+        }
+    }
+
+    // Functions can be called from code above their declaration, so their
+    // names are declared before the statements are walked.
+    fn stmts(&mut self, stmts: &[Stmt]) {
+        for stmt in stmts {
+            let declaration = match stmt {
+                Stmt::Export { keyword: _, declaration } => declaration,
+                other => other,
+            };
+            if let Stmt::Function { name, .. } = declaration {
+                self.declare(name, "Function", true);
+            }
+        }
+
+        // Only the first unreachable statement is reported.
+        let mut terminated = false;
+        let mut reported = false;
+        for stmt in stmts {
+            if terminated && !reported {
+                self.report(Rule::UnreachableCode, stmt_line(stmt), "Unreachable code".to_string());
+                reported = true;
+            }
+            self.stmt(stmt);
+            terminated = terminated || terminates(stmt);
+        }
+    }
+
+    fn stmt(&mut self, stmt: &Stmt) {
+        match stmt {
+            Stmt::Expression { expression } => self.expr(expression),
+            Stmt::Print { keyword, expression } => {
+                if self.library {
+                    self.report(
+                        Rule::PrintInLibrary,
+                        keyword.line_number,
+                        "'print' in a module that exports names".to_string(),
+                    );
+                }
+                self.expr(expression);
+            }
+            Stmt::Var { keyword: _, name, initialiser } => {
+                self.expr(initialiser);
+                self.declare(name, "Variable", true);
+            }
+            Stmt::Block { brace: _, statements } => {
+                self.scopes.push(vec![]);
+                self.stmts(statements);
+                self.end_scope();
+            }
+            Stmt::Function {
+                keyword: _,
+                name: _,
+                params,
+                body,
+            } => self.function(params, body),
+            Stmt::If {
+                keyword,
+                condition,
+                then_branch,
+                else_branch,
+            } => {
+                self.condition(keyword, condition);
+                self.expr(condition);
+                self.stmt(then_branch);
+                if let Some(else_branch) = else_branch {
+                    self.stmt(else_branch);
+                }
+            }
+            Stmt::While { keyword, condition, body } => {
+                // `while (true)` and `for (;;)` are the usual infinite loops.
+                if !matches!(condition, Expr::Literal { value: LiteralValue::True, .. }) {
+                    self.condition(keyword, condition);
+                }
+                self.expr(condition);
+                self.stmt(body);
+            }
+            Stmt::Return { keyword: _, value } => {
+                if let Some(value) = value {
+                    self.expr(value);
+                }
+            }
+            Stmt::Throw { keyword: _, value } => self.expr(value),
+            Stmt::Import { keyword: _, path: _, alias } => self.declare(alias, "Import", false),
+            Stmt::ImportFrom { keyword: _, path: _, names } => {
+                for name in names {
+                    self.declare(name, "Import", false);
+                }
+            }
+            Stmt::Export { keyword: _, declaration } => self.stmt(declaration),
+            Stmt::Error { .. } => {}
+            Stmt::Try {
+                keyword: _,
+                body,
+                catch_clause,
+                finally_body,
+            } => {
+                self.scopes.push(vec![]);
+                self.stmts(body);
+                self.end_scope();
+                if let Some((name, statements)) = catch_clause {
+                    self.scopes.push(vec![]);
+                    self.declare(name, "Variable", false);
+                    self.stmts(statements);
+                    self.end_scope();
+                }
+                if let Some(statements) = finally_body {
+                    self.scopes.push(vec![]);
+                    self.stmts(statements);
+                    self.end_scope();
+                }
+            }
+        }
+    }
+
+    fn function(&mut self, params: &[Token], body: &[Stmt]) {
+        self.scopes.push(vec![]);
+        for param in params {
+            self.declare(param, "Parameter", false);
+        }
+        self.stmts(body);
+        self.end_scope();
+    }
+
+    fn condition(&mut self, keyword: &Token, condition: &Expr) {
+        match ungroup(condition) {
+            Expr::Assign { .. } | Expr::SetIndex { .. } => self.report(
+                Rule::AssignmentInCondition,
+                keyword.line_number,
+                format!("Assignment used as the '{}' condition; did you mean '=='?", keyword.lexeme),
+            ),
+            Expr::Literal { token: _, value } => self.report(
+                Rule::ConstantCondition,
+                keyword.line_number,
+                format!(
+                    "The '{}' condition is always {}",
+                    keyword.lexeme,
+                    if value.is_falsy() { "false" } else { "true" }
+                ),
+            ),
+            _ => {}
+        }
+    }
+
+    fn expr(&mut self, expr: &Expr) {
+        match expr {
+            Expr::Assign { name: _, value } => self.expr(value),
+            Expr::Binary { left, operator, right } => {
+                use TokenType::*;
+                if matches!(
+                    operator.token_type,
+                    EQUAL_EQUAL | BANG_EQUAL | LESS | LESS_EQUAL | GREATER | GREATER_EQUAL
+                ) && same_place(left, right)
+                {
+                    self.report(
+                        Rule::SelfComparison,
+                        operator.line_number,
+                        format!("'{}' compares a value with itself", operator.lexeme),
+                    );
+                }
+                self.expr(left);
+                self.expr(right);
+            }
+            Expr::Logical { left, operator: _, right } => {
+                self.expr(left);
+                self.expr(right);
+            }
+            Expr::Call { callee, paren: _, arguments } => {
+                self.expr(callee);
+                for argument in arguments {
+                    self.expr(argument);
+                }
+            }
+            Expr::Get { object, name: _ } => self.expr(object),
+            Expr::Index { object, bracket: _, index } => {
+                self.expr(object);
+                self.expr(index);
+            }
+            Expr::SetIndex {
+                object,
+                bracket: _,
+                index,
+                value,
+            } => {
+                self.expr(object);
+                self.expr(index);
+                self.expr(value);
+            }
+            Expr::Lambda { keyword: _, params, body } => self.function(params, body),
+            Expr::List { bracket: _, elements } => {
+                for element in elements {
+                    self.expr(element);
+                }
+            }
+            Expr::Map { brace: _, entries } => {
+                for (key, value) in entries {
+                    self.expr(key);
+                    self.expr(value);
+                }
+            }
+            Expr::Grouping { paren: _, expression } => self.expr(expression),
+            Expr::Literal { .. } => {}
+            Expr::Unary { operator: _, right } => self.expr(right),
+            Expr::Variable { name } => {
+                // Assigning to a variable does not count as using it.
+                let binding = self
+                    .scopes
+                    .iter_mut()
+                    .rev()
+                    .find_map(|scope| scope.iter_mut().rev().find(|binding| binding.name == name.lexeme));
+                if let Some(binding) = binding {
+                    binding.used = true;
+                }
+            }
+        }
+    }
+
+    fn declare(&mut self, name: &Token, kind: &'static str, report_unused: bool) {
+        let (current, enclosing) = self.scopes.split_last_mut().expect("the global scope is never popped");
+        let shadowed = enclosing
+            .iter()
+            .rev()
+            .find_map(|scope| scope.iter().find(|binding| binding.name == name.lexeme))
+            .map(|binding| binding.line);
+        current.push(Binding {
+            name: name.lexeme.clone(),
+            kind,
+            line: name.line_number,
+            used: false,
+            report_unused: report_unused && !enclosing.is_empty(),
+        });
+        if let Some(line) = shadowed {
+            self.report(
+                Rule::ShadowedBinding,
+                name.line_number,
+                format!("'{}' shadows the binding declared at line {}", name.lexeme, line),
+            );
+        }
+    }
+
+    fn end_scope(&mut self) {
+        let scope = self.scopes.pop().expect("scopes are balanced");
+        for binding in scope {
+            if binding.report_unused && !binding.used && !binding.name.starts_with('_') {
+                self.report(
+                    Rule::UnusedVariable,
+                    binding.line,
+                    format!("{} '{}' is never used", binding.kind, binding.name),
+                );
+            }
+        }
+    }
+}
+
+fn ungroup(expr: &Expr) -> &Expr {
+    match expr {
+        Expr::Grouping { paren: _, expression } => ungroup(expression),
+        other => other,
+    }
+}
+
+// `x == x` and `a.b < a.b`: the same variable or property on both sides.
+fn same_place(left: &Expr, right: &Expr) -> bool {
+    match (ungroup(left), ungroup(right)) {
+        (Expr::Variable { name: left }, Expr::Variable { name: right }) => left.lexeme == right.lexeme,
+        (
+            Expr::Get {
+                object: left_object,
+                name: left,
+            },
+            Expr::Get {
+                object: right_object,
+                name: right,
+            },
+        ) => left.lexeme == right.lexeme && same_place(left_object, right_object),
+        _ => false,
+    }
+}
+
+fn terminates(stmt: &Stmt) -> bool {
+    match stmt {
+        Stmt::Return { .. } | Stmt::Throw { .. } => true,
+        Stmt::Block { brace: _, statements } => statements.iter().any(terminates),
+        Stmt::If {
+            then_branch,
+            else_branch: Some(else_branch),
+            ..
+        } => terminates(then_branch) && terminates(else_branch),
+        _ => false,
+    }
+}
+
+fn stmt_line(stmt: &Stmt) -> u64 {
+    match stmt {
+        Stmt::Expression { expression } => expr_line(expression),
+        Stmt::Print { keyword, .. }
+        | Stmt::If { keyword, .. }
+        | Stmt::While { keyword, .. }
+        | Stmt::Return { keyword, .. }
+        | Stmt::Throw { keyword, .. }
+        | Stmt::Import { keyword, .. }
+        | Stmt::ImportFrom { keyword, .. }
+        | Stmt::Try { keyword, .. }
+        | Stmt::Block { brace: keyword, .. }
+        | Stmt::Error { token: keyword, .. } => keyword.line_number,
+        Stmt::Var { name, .. } | Stmt::Function { name, .. } => name.line_number,
+        Stmt::Export { keyword: _, declaration } => stmt_line(declaration),
+    }
+}
+
+fn expr_line(expr: &Expr) -> u64 {
+    match expr {
+        Expr::Assign { name, .. } | Expr::Get { name, .. } | Expr::Variable { name } => name.line_number,
+        Expr::Binary { left, .. } | Expr::Logical { left, .. } => expr_line(left),
+        Expr::Call { callee, .. } => expr_line(callee),
+        Expr::Index { object, .. } | Expr::SetIndex { object, .. } => expr_line(object),
+        Expr::Lambda { keyword, .. } => keyword.line_number,
+        Expr::List { bracket, .. } => bracket.line_number,
+        Expr::Map { brace, .. } => brace.line_number,
+        Expr::Unary { operator, .. } => operator.line_number,
+        Expr::Grouping { paren, .. } => paren.line_number,
+        Expr::Literal { token, .. } => token.line_number,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn findings(source: &str) -> Vec<(Rule, u64)> {
+        lint_source(source, &LintConfig::default())
+            .unwrap()
+            .into_iter()
+            .map(|diagnostic| (diagnostic.rule, diagnostic.line))
+            .collect()
+    }
+
+    #[test]
+    fn reports_each_rule() {
+        let source = r#"var total = 0;
+fun add(x) {
+    var unused = 1;
+    var total = x;
+    if (total = 2) print total;
+    while (false) print x == x;
+    return total;
+    print "done";
+}
+for (var i = 0; i < 3; i = i + 1) add(i);
+while (true) {}
+"#;
+        assert_eq!(
+            findings(source),
+            vec![
+                (Rule::UnusedVariable, 3),
+                (Rule::ShadowedBinding, 4),
+                (Rule::AssignmentInCondition, 5),
+                (Rule::ConstantCondition, 6),
+                (Rule::SelfComparison, 6),
+                (Rule::UnreachableCode, 8),
+            ]
+        );
+
+        let library = "export fun f() { print 1; return helper(); }\nfun helper() { return 2; }\nprint f();";
+        assert_eq!(
+            findings(library),
+            vec![(Rule::PrintInLibrary, 1), (Rule::PrintInLibrary, 3)]
+        );
+    }
+
+    #[test]
+    fn hoisted_functions_and_closures_count_as_uses() {
+        let source = r#"fun outer() {
+    fun even(n) { if (n == 0) return true; return odd(n - 1); }
+    fun odd(n) { if (n == 0) return false; return even(n - 1); }
+    var count = 0;
+    var _ignored = 1;
+    return fun () { return even(count); };
+}
+"#;
+        assert_eq!(findings(source), vec![]);
+    }
+
+    #[test]
+    fn allow_comments_and_config() {
+        let source = r#"var x = 0;
+fun f() {
+    var a = 1; // fnky:allow(unused-variable)
+    // fnky:allow(shadowed-binding, unused-variable)
+    var x = 2;
+    var b = 3;
+    return x == x;
+}
+"#;
+        assert_eq!(findings(source), vec![(Rule::UnusedVariable, 6), (Rule::SelfComparison, 7)]);
+
+        let mut config = LintConfig::default();
+        config.set_level("unused-variable", "error").unwrap();
+        config.set_level("self-comparison", "off").unwrap();
+        let diagnostics = lint_source(source, &config).unwrap();
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(
+            diagnostics[0].to_string(),
+            "error[unused-variable]: Variable 'b' is never used at line 6"
+        );
+        assert!(config.set_level("no-such-rule", "off").is_err());
+        assert!(config.set_level("unused-variable", "loud").is_err());
+    }
+}
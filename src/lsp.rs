@@ -0,0 +1,958 @@
+//! `fnky lsp`: a Language Server Protocol server over standard input and
+//! output. It keeps the text of each open document and answers from its
+//! tokens, parse and lint findings; no script is ever run.
+//!
+//! Names are resolved on the token stream rather than the syntax tree so
+//! that positions are exact: a declaration is visible from where it appears
+//! (from the start of its block, for functions) to the end of its block.
+
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::io::{self, BufRead, Read, Write};
+use std::ops::Range;
+use std::path::Path;
+use std::rc::Rc;
+
+use crate::expr::LiteralValue;
+use crate::interpreter::Interpreter;
+use crate::json;
+use crate::lint::{self, LintConfig, Severity};
+use crate::map::{Key, Map};
+use crate::parser::Parser;
+use crate::project::Manifest;
+use crate::repl;
+use crate::scanner::{self, Scanner, Token, TokenType};
+use crate::syntax;
+use TokenType::*;
+
+const PARSE_ERROR: f64 = -32700.0;
+const INVALID_PARAMS: f64 = -32602.0;
+const METHOD_NOT_FOUND: f64 = -32601.0;
+
+// Larger messages are skipped rather than read into memory.
+const MAX_CONTENT_LENGTH: u64 = 64 << 20;
+
+/// Reads one message framed by a `Content-Length` header, or `None` at the
+/// end of the input. A message that can't be read, because of a bad header,
+/// a body that is too long or one that isn't UTF-8, gives the inner error;
+/// the input is left at the next message. The outer error is for a failure
+/// of the input itself.
+pub fn read_message(input: &mut impl BufRead) -> Result<Option<Result<String, String>>, String> {
+    let mut length = Err("Missing Content-Length header".to_string());
+    loop {
+        let mut header = String::new();
+        if input.read_line(&mut header).map_err(|err| err.to_string())? == 0 {
+            return Ok(None);
+        }
+        let header = header.trim_end();
+        if header.is_empty() {
+            break;
+        }
+        if let Some((name, value)) = header.split_once(':')
+            && name.eq_ignore_ascii_case("Content-Length")
+        {
+            let value = value.trim();
+            length = value.parse::<u64>().map_err(|_| format!("Invalid Content-Length '{}'", value));
+        }
+    }
+
+    let length = match length {
+        Ok(length) => length,
+        Err(err) => return Ok(Some(Err(err))),
+    };
+    let mut body = input.take(length);
+    if length > MAX_CONTENT_LENGTH {
+        io::copy(&mut body, &mut io::sink()).map_err(|err| err.to_string())?;
+        return Ok(Some(Err(format!("Content-Length {} is over the limit of {} bytes", length, MAX_CONTENT_LENGTH))));
+    }
+    let mut bytes = vec![];
+    body.read_to_end(&mut bytes).map_err(|err| err.to_string())?;
+    if (bytes.len() as u64) < length {
+        return Err("Unexpected end of input in a message body".to_string());
+    }
+    Ok(Some(String::from_utf8(bytes).map_err(|err| err.to_string())))
+}
+
+pub fn write_message(output: &mut impl Write, body: &str) -> Result<(), String> {
+    write!(output, "Content-Length: {}\r\n\r\n{}", body.len(), body)
+        .and_then(|_| output.flush())
+        .map_err(|err| err.to_string())
+}
+
+/// Serves messages until the client sends `exit` or closes the input. The
+/// exit status is 0 only if `shutdown` came first, as the protocol asks.
+pub fn run(mut input: impl BufRead, mut output: impl Write) -> Result<i32, String> {
+    let mut server = Server::new();
+    while let Some(body) = read_message(&mut input)? {
+        let replies = match body.and_then(|body| json::parse(&body)) {
+            Ok(message) => server.handle(&message),
+            Err(err) => vec![response(LiteralValue::Nil, Err((PARSE_ERROR, err)))],
+        };
+        for reply in replies {
+            let body = json::stringify(&reply, None).map_err(|err| err.message)?;
+            write_message(&mut output, &body)?;
+        }
+        if server.exited {
+            return Ok(if server.shut_down { 0 } else { 1 });
+        }
+    }
+    Ok(1)
+}
+
+pub struct Server {
+    documents: HashMap<String, String>,
+    // The built-in modules and functions every script can use.
+    globals: Vec<(String, LiteralValue)>,
+    shut_down: bool,
+    exited: bool,
+}
+
+impl Default for Server {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Server {
+    pub fn new() -> Self {
+        Self {
+            documents: HashMap::new(),
+            globals: Interpreter::new().globals(),
+            shut_down: false,
+            exited: false,
+        }
+    }
+
+    /// Handles one message and returns the messages to send back: the
+    /// response to a request, or the diagnostics a change of document
+    /// produced.
+    pub fn handle(&mut self, message: &LiteralValue) -> Vec<LiteralValue> {
+        let method = get(message, "method").map(|method| method.to_string()).unwrap_or_default();
+        let params = get(message, "params").unwrap_or(LiteralValue::Nil);
+        let uri = get_path(&params, &["textDocument", "uri"]).map(|uri| uri.to_string());
+
+        let result = match (method.as_str(), uri) {
+            ("initialize", _) => Ok(capabilities()),
+            ("shutdown", _) => {
+                self.shut_down = true;
+                Ok(LiteralValue::Nil)
+            }
+            ("exit", _) => {
+                self.exited = true;
+                return vec![];
+            }
+            ("textDocument/didOpen", Some(uri)) => {
+                let text = get_path(&params, &["textDocument", "text"]).unwrap_or(LiteralValue::Nil);
+                self.documents.insert(uri.clone(), text.to_string());
+                return vec![self.publish_diagnostics(&uri)];
+            }
+            ("textDocument/didChange", Some(uri)) => {
+                // Only full-text synchronisation is offered, so the last
+                // change holds the whole document.
+                let changes = get(&params, "contentChanges").map(list).unwrap_or_default();
+                if let Some(text) = changes.last().and_then(|change| get(change, "text")) {
+                    self.documents.insert(uri.clone(), text.to_string());
+                }
+                return vec![self.publish_diagnostics(&uri)];
+            }
+            ("textDocument/didClose", Some(uri)) => {
+                self.documents.remove(&uri);
+                return vec![notification(
+                    "textDocument/publishDiagnostics",
+                    object(vec![("uri", string(&uri)), ("diagnostics", array(vec![]))]),
+                )];
+            }
+            ("textDocument/hover", Some(uri)) => self.with_position(&uri, &params, Server::hover),
+            ("textDocument/definition", Some(uri)) => self.with_position(&uri, &params, |_, document, offset| {
+                document.definition(offset).map_or(LiteralValue::Nil, |declaration| {
+                    let name = declaration.name_span(document);
+                    object(vec![("uri", string(&uri)), ("range", document.lines.range(name))])
+                })
+            }),
+            ("textDocument/documentSymbol", Some(uri)) => match self.documents.get(&uri) {
+                Some(text) => Ok(Document::new(text).map_or(array(vec![]), |document| document.symbols(None))),
+                None => Err((INVALID_PARAMS, format!("Unknown document {}", uri))),
+            },
+            ("textDocument/completion", Some(uri)) => self.with_position(&uri, &params, Server::completion),
+            _ => Err((METHOD_NOT_FOUND, format!("Unknown method '{}'", method))),
+        };
+
+        match get(message, "id") {
+            Some(id) => vec![response(id, result)],
+            None => vec![],
+        }
+    }
+
+    fn with_position(
+        &self,
+        uri: &str,
+        params: &LiteralValue,
+        answer: impl Fn(&Server, &Document, usize) -> LiteralValue,
+    ) -> Result<LiteralValue, (f64, String)> {
+        let text = self
+            .documents
+            .get(uri)
+            .ok_or_else(|| (INVALID_PARAMS, format!("Unknown document {}", uri)))?;
+        let line = get_path(params, &["position", "line"]).and_then(number);
+        let character = get_path(params, &["position", "character"]).and_then(number);
+        let (Some(line), Some(character)) = (line, character) else {
+            return Err((INVALID_PARAMS, "Expected a position".to_string()));
+        };
+        let offset = Lines::new(text).offset(line, character);
+        Ok(match Document::new(text) {
+            Some(document) => answer(self, &document, offset),
+            None => LiteralValue::Nil,
+        })
+    }
+
+    fn publish_diagnostics(&self, uri: &str) -> LiteralValue {
+        let text = self.documents.get(uri).map_or("", String::as_str);
+        let lines = Lines::new(text);
+        let diagnostic = |line: u64, severity: f64, code: Option<&str>, message: &str| {
+            let mut entries = vec![
+                ("range", lines.line_range(line)),
+                ("severity", LiteralValue::Number(severity)),
+                ("source", string("fnky")),
+                ("message", string(message)),
+            ];
+            if let Some(code) = code {
+                entries.push(("code", string(code)));
+            }
+            object(entries)
+        };
+        let errors = |errors: String| {
+            errors
+                .lines()
+                .filter(|error| !error.trim().is_empty())
+                .map(|error| diagnostic(error_line(error), 1.0, None, error))
+                .collect()
+        };
+
+        let diagnostics = match Scanner::new(text).scan_tokens() {
+            Err(scan_errors) => errors(scan_errors),
+            Ok(tokens) => match Parser::new(tokens).parse() {
+                Err(parse_errors) => errors(parse_errors),
+                Ok(_) => lint::lint_source(text, &lint_config(uri))
+                    .unwrap_or_default()
+                    .iter()
+                    .map(|finding| {
+                        let severity = if finding.severity == Severity::Error { 1.0 } else { 2.0 };
+                        diagnostic(finding.line, severity, Some(finding.rule.id()), &finding.message)
+                    })
+                    .collect(),
+            },
+        };
+        notification(
+            "textDocument/publishDiagnostics",
+            object(vec![("uri", string(uri)), ("diagnostics", array(diagnostics))]),
+        )
+    }
+
+    fn hover(&self, document: &Document, offset: usize) -> LiteralValue {
+        let Some(index) = document.identifier_at(offset) else {
+            return LiteralValue::Nil;
+        };
+        let text = match document.definition(offset) {
+            Some(declaration) => match declaration.value_type {
+                Some(value_type) => format!("```fnky\n{}\n```\n{}", declaration.detail, value_type),
+                None => format!("```fnky\n{}\n```", declaration.detail),
+            },
+            None => {
+                let path = document.dotted_path(index);
+                let Some(value) = repl::resolve(&path, &self.globals) else {
+                    return LiteralValue::Nil;
+                };
+                match value {
+                    LiteralValue::Module(_) | LiteralValue::Function(_) | LiteralValue::Native(_) => {
+                        format!("```fnky\n{}: {}\n```", path, value)
+                    }
+                    value => format!("```fnky\n{} = {}\n```\n{}", path, value.repr(), value.to_type()),
+                }
+            }
+        };
+        object(vec![
+            ("contents", object(vec![("kind", string("markdown")), ("value", string(&text))])),
+            ("range", document.lines.range(document.span(index))),
+        ])
+    }
+
+    fn completion(&self, document: &Document, offset: usize) -> LiteralValue {
+        let line_start = document.lines.starts[document.lines.line_of(offset)];
+        let line_end = document.text[line_start..].find('\n').map_or(document.text.len(), |end| line_start + end);
+        let line = &document.text[line_start..line_end];
+        let (start, names) = repl::complete(line, offset - line_start, &self.globals);
+        let prefix = &line[start..offset - line_start];
+        let after_dot = line[..start].ends_with('.');
+
+        let keywords = scanner::get_keywords_hashmap();
+        let mut items: Vec<(String, f64)> = names
+            .into_iter()
+            .map(|name| {
+                let kind = if after_dot {
+                    METHOD
+                } else if keywords.contains_key(name.as_str()) {
+                    KEYWORD
+                } else {
+                    match self.globals.iter().find(|(global, _)| *global == name) {
+                        Some((_, LiteralValue::Module(_))) => MODULE,
+                        Some((_, LiteralValue::Function(_) | LiteralValue::Native(_))) => FUNCTION,
+                        _ => VARIABLE,
+                    }
+                };
+                (name, kind)
+            })
+            .collect();
+        if !after_dot {
+            for declaration in &document.declarations {
+                if declaration.visible.contains(&offset) && declaration.name.starts_with(prefix) {
+                    items.push((declaration.name.clone(), declaration.kind.completion_kind()));
+                }
+            }
+        }
+        items.sort_by(|a, b| a.0.cmp(&b.0));
+        items.dedup_by(|a, b| a.0 == b.0);
+
+        array(
+            items
+                .into_iter()
+                .map(|(label, kind)| object(vec![("label", string(&label)), ("kind", LiteralValue::Number(kind))]))
+                .collect(),
+        )
+    }
+}
+
+// Completion item kinds.
+const METHOD: f64 = 2.0;
+const FUNCTION: f64 = 3.0;
+const VARIABLE: f64 = 6.0;
+const MODULE: f64 = 9.0;
+const KEYWORD: f64 = 14.0;
+
+fn capabilities() -> LiteralValue {
+    object(vec![
+        (
+            "capabilities",
+            object(vec![
+                // Full-text synchronisation.
+                ("textDocumentSync", LiteralValue::Number(1.0)),
+                ("hoverProvider", LiteralValue::True),
+                ("definitionProvider", LiteralValue::True),
+                ("documentSymbolProvider", LiteralValue::True),
+                ("completionProvider", object(vec![("triggerCharacters", array(vec![string(".")]))])),
+            ]),
+        ),
+        ("serverInfo", object(vec![("name", string("fnky"))])),
+    ])
+}
+
+// The `[lint]` section of the fnky.toml above a `file://` document, if any.
+fn lint_config(uri: &str) -> LintConfig {
+    uri.strip_prefix("file://")
+        .and_then(|path| Manifest::find(Path::new(path).parent()?))
+        .and_then(|manifest| Manifest::load(&manifest).ok())
+        .map(|manifest| manifest.lint)
+        .unwrap_or_default()
+}
+
+// Scanner and parser errors end with "at line N".
+fn error_line(error: &str) -> u64 {
+    error
+        .rsplit_once("line ")
+        .and_then(|(_, rest)| {
+            let digits: String = rest.chars().take_while(char::is_ascii_digit).collect();
+            digits.parse().ok()
+        })
+        .unwrap_or(1)
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum Kind {
+    Function,
+    Variable,
+    Parameter,
+    Import,
+}
+
+impl Kind {
+    fn symbol_kind(self) -> f64 {
+        match self {
+            Kind::Function => 12.0,
+            Kind::Variable | Kind::Parameter => 13.0,
+            Kind::Import => 2.0,
+        }
+    }
+
+    fn completion_kind(self) -> f64 {
+        match self {
+            Kind::Function => FUNCTION,
+            Kind::Variable | Kind::Parameter => VARIABLE,
+            Kind::Import => MODULE,
+        }
+    }
+}
+
+struct Declaration {
+    name: String,
+    kind: Kind,
+    // The index of the name's token.
+    token: usize,
+    // The bytes in which the name refers to this declaration.
+    visible: Range<usize>,
+    // The `{` of the block the declaration is in, and of a function's body.
+    block: Option<usize>,
+    body: Option<usize>,
+    // The source shown on hover, like `fun add(a, b)` or `var x = 1`.
+    detail: String,
+    value_type: Option<&'static str>,
+}
+
+impl Declaration {
+    fn name_span(&self, document: &Document) -> Range<usize> {
+        document.span(self.token)
+    }
+}
+
+struct Document<'a> {
+    text: &'a str,
+    tokens: Vec<Token>,
+    // The index of the bracket that closes each opening bracket.
+    closing: HashMap<usize, usize>,
+    declarations: Vec<Declaration>,
+    lines: Lines,
+}
+
+impl<'a> Document<'a> {
+    // None if the text does not scan.
+    fn new(text: &'a str) -> Option<Self> {
+        let tokens = Scanner::new(text).scan_tokens().ok()?;
+        let mut document = Document {
+            text,
+            tokens,
+            closing: HashMap::new(),
+            declarations: vec![],
+            lines: Lines::new(text),
+        };
+
+        let index_at: HashMap<usize, usize> =
+            document.tokens.iter().enumerate().map(|(index, token)| (token.offset, index)).collect();
+        for (opening, closing) in syntax::parse(text).bracket_pairs() {
+            if let (Some(&opening), Some(&closing)) = (index_at.get(&opening), index_at.get(&closing)) {
+                document.closing.insert(opening, closing);
+            }
+        }
+        document.declare_all();
+        Some(document)
+    }
+
+    fn kind(&self, index: usize) -> Option<TokenType> {
+        self.tokens.get(index).map(|token| token.token_type)
+    }
+
+    fn span(&self, index: usize) -> Range<usize> {
+        self.tokens[index].offset..self.tokens[index].offset + self.tokens[index].lexeme.len()
+    }
+
+    // The byte just past the bracket that closes the one at `index`, or the
+    // end of the text if it is never closed.
+    fn end_of(&self, index: usize) -> usize {
+        self.closing
+            .get(&index)
+            .map_or(self.text.len(), |&closing| self.tokens[closing].offset + 1)
+    }
+
+    // The index of the `;` that ends the statement starting at `index`,
+    // skipping over brackets.
+    fn statement_end(&self, mut index: usize) -> usize {
+        while index < self.tokens.len() - 1 && self.tokens[index].token_type != SEMICOLON {
+            index = match self.closing.get(&index) {
+                Some(&closing) => closing + 1,
+                None => index + 1,
+            };
+        }
+        index
+    }
+
+    fn source(&self, from: usize, to: usize) -> String {
+        let text = &self.text[self.tokens[from].offset..self.tokens[to].offset];
+        text.split_whitespace().collect::<Vec<&str>>().join(" ")
+    }
+
+    fn declare_all(&mut self) {
+        let mut blocks: Vec<usize> = vec![];
+        for index in 0..self.tokens.len() {
+            let block = blocks.last().copied();
+            let block_start = block.map_or(0, |block| self.tokens[block].offset);
+            let block_end = block.map_or(self.text.len(), |block| self.end_of(block));
+            match self.tokens[index].token_type {
+                LEFT_BRACE => blocks.push(index),
+                RIGHT_BRACE => {
+                    blocks.pop();
+                }
+                VAR if self.kind(index + 1) == Some(IDENTIFIER) => {
+                    let name = index + 1;
+                    let end = self.statement_end(name);
+                    // `for (var i ...)` is visible in the loop only.
+                    let in_for = index >= 2 && self.kind(index - 1) == Some(LEFT_PAREN) && self.kind(index - 2) == Some(FOR);
+                    let header_end = if in_for { self.closing.get(&(index - 1)).copied() } else { None };
+                    let visible_end = match header_end {
+                        Some(header_end) => match self.kind(header_end + 1) {
+                            Some(LEFT_BRACE) => self.end_of(header_end + 1),
+                            _ => self.tokens[self.statement_end(header_end + 1)].offset + 1,
+                        },
+                        _ => block_end,
+                    };
+                    let value_type = if self.kind(name + 1) == Some(EQUAL) {
+                        self.value_type(name + 2, end)
+                    } else {
+                        Some("nil")
+                    };
+                    self.declarations.push(Declaration {
+                        name: self.tokens[name].lexeme.clone(),
+                        kind: Kind::Variable,
+                        token: name,
+                        visible: self.tokens[name].offset..visible_end,
+                        block,
+                        body: None,
+                        detail: self.source(index, end),
+                        value_type,
+                    });
+                }
+                FUN => self.declare_function(index, block, block_start..block_end),
+                CATCH if self.kind(index + 1) == Some(LEFT_PAREN) && self.kind(index + 2) == Some(IDENTIFIER) => {
+                    let name = index + 2;
+                    let body = index + 4;
+                    if self.kind(body) == Some(LEFT_BRACE) {
+                        self.declarations.push(Declaration {
+                            name: self.tokens[name].lexeme.clone(),
+                            kind: Kind::Parameter,
+                            token: name,
+                            visible: self.tokens[name].offset..self.end_of(body),
+                            block: Some(body),
+                            body: None,
+                            detail: format!("(error) {}", self.tokens[name].lexeme),
+                            value_type: Some("Error"),
+                        });
+                    }
+                }
+                IMPORT => {
+                    let statement_end = self.statement_end(index);
+                    let from_import = index >= 2 && self.tokens[index - 2].lexeme == "from";
+                    let names: Vec<usize> = if from_import {
+                        (index + 1..statement_end).filter(|&i| self.kind(i) == Some(IDENTIFIER)).collect()
+                    } else if self.kind(index + 3) == Some(IDENTIFIER) && self.tokens[index + 2].lexeme == "as" {
+                        vec![index + 3]
+                    } else {
+                        vec![]
+                    };
+                    let start = if from_import { index - 2 } else { index };
+                    for name in names {
+                        self.declarations.push(Declaration {
+                            name: self.tokens[name].lexeme.clone(),
+                            kind: Kind::Import,
+                            token: name,
+                            visible: block_start..block_end,
+                            block,
+                            body: None,
+                            detail: self.source(start, statement_end),
+                            value_type: None,
+                        });
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+
+    // `fun name(params) {` declares the name in its block (all of it, so
+    // calls may come first) and the parameters in the body; `fun (params) {`
+    // only the parameters.
+    fn declare_function(&mut self, index: usize, block: Option<usize>, block_span: Range<usize>) {
+        let name = (self.kind(index + 1) == Some(IDENTIFIER)).then_some(index + 1);
+        let paren = name.map_or(index + 1, |name| name + 1);
+        if self.kind(paren) != Some(LEFT_PAREN) {
+            return;
+        }
+        let Some(&paren_end) = self.closing.get(&paren) else {
+            return;
+        };
+        let body = (self.kind(paren_end + 1) == Some(LEFT_BRACE)).then_some(paren_end + 1);
+        let body_end = body.map_or(self.text.len(), |body| self.end_of(body));
+
+        let params: Vec<usize> = (paren + 1..paren_end).filter(|&i| self.kind(i) == Some(IDENTIFIER)).collect();
+        let names: Vec<&str> = params.iter().map(|&param| self.tokens[param].lexeme.as_str()).collect();
+        let signature = match name {
+            Some(name) => format!("fun {}({})", self.tokens[name].lexeme, names.join(", ")),
+            None => format!("fun ({})", names.join(", ")),
+        };
+
+        if let Some(name) = name {
+            self.declarations.push(Declaration {
+                name: self.tokens[name].lexeme.clone(),
+                kind: Kind::Function,
+                token: name,
+                visible: block_span,
+                block,
+                body,
+                detail: signature.clone(),
+                value_type: None,
+            });
+        }
+        for param in params {
+            self.declarations.push(Declaration {
+                name: self.tokens[param].lexeme.clone(),
+                kind: Kind::Parameter,
+                token: param,
+                visible: self.tokens[param].offset..body_end,
+                block: body,
+                body: None,
+                detail: format!("(parameter) {} of {}", self.tokens[param].lexeme, signature),
+                value_type: None,
+            });
+        }
+    }
+
+    // The type of an initialiser that is a single literal, list, map or
+    // function expression.
+    fn value_type(&self, from: usize, to: usize) -> Option<&'static str> {
+        let last = match self.kind(from)? {
+            FUN => self.closing.get(&(from + 1)).and_then(|&paren_end| self.closing.get(&(paren_end + 1)).copied()),
+            LEFT_BRACKET | LEFT_BRACE => self.closing.get(&from).copied(),
+            _ => Some(from),
+        }?;
+        if last + 1 != to {
+            return None;
+        }
+        Some(match self.kind(from)? {
+            NUMBER => "Number",
+            STRING => "String",
+            TRUE | FALSE => "Boolean",
+            NIL => "nil",
+            LEFT_BRACKET => "List",
+            LEFT_BRACE => "Map",
+            FUN => "Function",
+            _ => return None,
+        })
+    }
+
+    // The identifier the cursor is on or just after.
+    fn identifier_at(&self, offset: usize) -> Option<usize> {
+        (0..self.tokens.len())
+            .find(|&index| self.kind(index) == Some(IDENTIFIER) && (self.span(index).start..=self.span(index).end).contains(&offset))
+    }
+
+    // The declaration the identifier at `offset` refers to: of those in
+    // sight, the one whose scope starts last. Properties after a `.` are
+    // not variables and resolve to nothing.
+    fn definition(&self, offset: usize) -> Option<&Declaration> {
+        let index = self.identifier_at(offset)?;
+        if let Some(declaration) = self.declarations.iter().find(|declaration| declaration.token == index) {
+            return Some(declaration);
+        }
+        if index > 0 && self.kind(index - 1) == Some(DOT) {
+            return None;
+        }
+        let start = self.tokens[index].offset;
+        self.declarations
+            .iter()
+            .filter(|declaration| declaration.name == self.tokens[index].lexeme && declaration.visible.contains(&start))
+            .max_by_key(|declaration| declaration.visible.start)
+    }
+
+    // `math.sqrt` for the `sqrt` in `math.sqrt(2)`.
+    fn dotted_path(&self, index: usize) -> String {
+        let mut first = index;
+        while first >= 2 && self.kind(first - 1) == Some(DOT) && self.kind(first - 2) == Some(IDENTIFIER) {
+            first -= 2;
+        }
+        (first..=index).step_by(2).map(|i| self.tokens[i].lexeme.as_str()).collect::<Vec<&str>>().join(".")
+    }
+
+    // The functions, variables and imports declared directly in `block`
+    // (the top level for None), each function with its own declarations
+    // as children.
+    fn symbols(&self, block: Option<usize>) -> LiteralValue {
+        let symbols = self
+            .declarations
+            .iter()
+            .filter(|declaration| declaration.block == block && declaration.kind != Kind::Parameter)
+            .map(|declaration| {
+                let name = declaration.name_span(self);
+                let range = match declaration.body {
+                    Some(body) => name.start..self.end_of(body),
+                    None => name.clone(),
+                };
+                let mut entries = vec![
+                    ("name", string(&declaration.name)),
+                    ("detail", string(&declaration.detail)),
+                    ("kind", LiteralValue::Number(declaration.kind.symbol_kind())),
+                    ("range", self.lines.range(range)),
+                    ("selectionRange", self.lines.range(name)),
+                ];
+                if let Some(body) = declaration.body {
+                    entries.push(("children", self.symbols(Some(body))));
+                }
+                object(entries)
+            })
+            .collect();
+        array(symbols)
+    }
+}
+
+// Converts between byte offsets and LSP positions, whose characters are
+// counted in UTF-16 code units.
+struct Lines {
+    text: String,
+    starts: Vec<usize>,
+}
+
+impl Lines {
+    fn new(text: &str) -> Self {
+        let starts = std::iter::once(0)
+            .chain(text.match_indices('\n').map(|(index, _)| index + 1))
+            .collect();
+        Self {
+            text: text.to_string(),
+            starts,
+        }
+    }
+
+    fn line_of(&self, offset: usize) -> usize {
+        self.starts.partition_point(|&start| start <= offset) - 1
+    }
+
+    fn line_end(&self, line: usize) -> usize {
+        self.starts.get(line + 1).map_or(self.text.len(), |next| next - 1)
+    }
+
+    fn offset(&self, line: usize, character: usize) -> usize {
+        let Some(&start) = self.starts.get(line) else {
+            return self.text.len();
+        };
+        let mut units = 0;
+        for (index, ch) in self.text[start..self.line_end(line)].char_indices() {
+            if units >= character {
+                return start + index;
+            }
+            units += ch.len_utf16();
+        }
+        self.line_end(line)
+    }
+
+    fn position(&self, offset: usize) -> LiteralValue {
+        let line = self.line_of(offset);
+        let character: usize = self.text[self.starts[line]..offset].chars().map(char::len_utf16).sum();
+        object(vec![
+            ("line", LiteralValue::Number(line as f64)),
+            ("character", LiteralValue::Number(character as f64)),
+        ])
+    }
+
+    fn range(&self, span: Range<usize>) -> LiteralValue {
+        object(vec![("start", self.position(span.start)), ("end", self.position(span.end))])
+    }
+
+    // The whole of a line, counted from 1 as in error messages.
+    fn line_range(&self, line_number: u64) -> LiteralValue {
+        let line = (line_number.max(1) as usize - 1).min(self.starts.len() - 1);
+        self.range(self.starts[line]..self.line_end(line))
+    }
+}
+
+fn object(entries: Vec<(&str, LiteralValue)>) -> LiteralValue {
+    let mut map = Map::new();
+    for (key, value) in entries {
+        map.insert(Key::String(key.to_string()), value);
+    }
+    LiteralValue::Map(Rc::new(RefCell::new(map)))
+}
+
+fn array(values: Vec<LiteralValue>) -> LiteralValue {
+    LiteralValue::List(Rc::new(RefCell::new(values)))
+}
+
+fn string(text: &str) -> LiteralValue {
+    LiteralValue::StringValue(text.to_string())
+}
+
+fn get(value: &LiteralValue, key: &str) -> Option<LiteralValue> {
+    match value {
+        LiteralValue::Map(map) => map.borrow().get(&Key::String(key.to_string())).cloned(),
+        _ => None,
+    }
+}
+
+fn get_path(value: &LiteralValue, keys: &[&str]) -> Option<LiteralValue> {
+    keys.iter().try_fold(value.clone(), |value, key| get(&value, key))
+}
+
+fn list(value: LiteralValue) -> Vec<LiteralValue> {
+    match value {
+        LiteralValue::List(values) => values.borrow().clone(),
+        _ => vec![],
+    }
+}
+
+fn number(value: LiteralValue) -> Option<usize> {
+    match value {
+        LiteralValue::Number(x) if x >= 0.0 => Some(x as usize),
+        _ => None,
+    }
+}
+
+fn response(id: LiteralValue, result: Result<LiteralValue, (f64, String)>) -> LiteralValue {
+    let outcome = match result {
+        Ok(result) => ("result", result),
+        Err((code, message)) => (
+            "error",
+            object(vec![("code", LiteralValue::Number(code)), ("message", string(&message))]),
+        ),
+    };
+    object(vec![("jsonrpc", string("2.0")), ("id", id), outcome])
+}
+
+fn notification(method: &str, params: LiteralValue) -> LiteralValue {
+    object(vec![("jsonrpc", string("2.0")), ("method", string(method)), ("params", params)])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+
+    // Plays a client: frames the messages, runs the server over them and
+    // returns the exit status and the messages it sent back.
+    fn session(messages: &[&str]) -> (i32, Vec<LiteralValue>) {
+        let mut input = vec![];
+        for message in messages {
+            write_message(&mut input, message).unwrap();
+        }
+        serve(input)
+    }
+
+    fn serve(input: Vec<u8>) -> (i32, Vec<LiteralValue>) {
+        let mut output = vec![];
+        let status = run(Cursor::new(input), &mut output).unwrap();
+
+        let mut output = Cursor::new(output);
+        let mut replies = vec![];
+        while let Some(body) = read_message(&mut output).unwrap() {
+            replies.push(json::parse(&body.unwrap()).unwrap());
+        }
+        (status, replies)
+    }
+
+    fn request(id: u32, method: &str, params: &str) -> String {
+        format!(r#"{{"jsonrpc": "2.0", "id": {}, "method": "{}", "params": {}}}"#, id, method, params)
+    }
+
+    fn at(id: u32, method: &str, line: u32, character: u32) -> String {
+        let params = format!(
+            r#"{{"textDocument": {{"uri": "mem:a.fnky"}}, "position": {{"line": {}, "character": {}}}}}"#,
+            line, character
+        );
+        request(id, method, &params)
+    }
+
+    fn json(value: &Option<LiteralValue>) -> String {
+        json::stringify(value.as_ref().unwrap(), None).unwrap()
+    }
+
+    const SOURCE: &str = r#"var total = 0;
+fun add(x, y) {
+    var sum = x + y;
+    return sum;
+}
+total = add(total, math.PI);
+"#;
+
+    #[test]
+    fn scripted_session() {
+        let open = format!(
+            r#"{{"jsonrpc": "2.0", "method": "textDocument/didOpen", "params": {{"textDocument": {{"uri": "mem:a.fnky", "languageId": "fnky", "version": 1, "text": {}}}}}}}"#,
+            json::stringify(&string(SOURCE), None).unwrap()
+        );
+        let (status, replies) = session(&[
+            &request(1, "initialize", "{}"),
+            r#"{"jsonrpc": "2.0", "method": "initialized", "params": {}}"#,
+            &open,
+            &at(2, "textDocument/hover", 5, 10),
+            &at(3, "textDocument/definition", 3, 12),
+            &at(4, "textDocument/hover", 5, 25),
+            &request(5, "textDocument/documentSymbol", r#"{"textDocument": {"uri": "mem:a.fnky"}}"#),
+            &at(6, "textDocument/completion", 5, 3),
+            &request(7, "textDocument/formatting", "{}"),
+            &request(8, "shutdown", "null"),
+            r#"{"jsonrpc": "2.0", "method": "exit"}"#,
+        ]);
+        assert_eq!(status, 0);
+        assert_eq!(replies.len(), 9);
+
+        let capabilities = get_path(&replies[0], &["result", "capabilities", "hoverProvider"]);
+        assert_eq!(capabilities, Some(LiteralValue::True));
+        assert_eq!(json(&get_path(&replies[1], &["params", "diagnostics"])), "[]");
+
+        let hover = get_path(&replies[2], &["result", "contents", "value"]).unwrap();
+        assert_eq!(hover.to_string(), "```fnky\nfun add(x, y)\n```");
+        assert_eq!(
+            json(&get_path(&replies[3], &["result", "range"])),
+            r#"{"start":{"line":2,"character":8},"end":{"line":2,"character":11}}"#
+        );
+        let hover = get_path(&replies[4], &["result", "contents", "value"]).unwrap();
+        assert!(hover.to_string().starts_with("```fnky\nmath.PI = 3.14159"));
+
+        let symbols = list(get(&replies[5], "result").unwrap());
+        let names: Vec<String> = symbols.iter().map(|symbol| get(symbol, "name").unwrap().to_string()).collect();
+        assert_eq!(names, ["total", "add"]);
+        assert_eq!(json(&get_path(&symbols[1], &["children"])).matches("\"name\":\"sum\"").count(), 1);
+
+        let labels: Vec<String> = list(get(&replies[6], "result").unwrap())
+            .iter()
+            .map(|item| get(item, "label").unwrap().to_string())
+            .collect();
+        assert_eq!(labels, ["total"]);
+
+        assert_eq!(get_path(&replies[7], &["error", "code"]), Some(LiteralValue::Number(METHOD_NOT_FOUND)));
+        assert_eq!(get(&replies[8], "result"), Some(LiteralValue::Nil));
+    }
+
+    #[test]
+    fn unreadable_messages_get_parse_errors() {
+        let mut input = b"Content-Length: many\r\n\r\nContent-Length: 2\r\n\r\n\xff\xfe".to_vec();
+        write_message(&mut input, &request(1, "shutdown", "null")).unwrap();
+        input.extend_from_slice(b"Content-Length: 99999999999\r\n\r\n{}");
+        let (status, replies) = serve(input);
+        assert_eq!(status, 1);
+        let codes: Vec<Option<LiteralValue>> = replies.iter().map(|reply| get_path(reply, &["error", "code"])).collect();
+        let parse_error = Some(LiteralValue::Number(PARSE_ERROR));
+        assert_eq!(codes, [parse_error.clone(), parse_error.clone(), None, parse_error]);
+        assert_eq!(get(&replies[2], "id"), Some(LiteralValue::Number(1.0)));
+    }
+
+    #[test]
+    fn diagnostics_follow_changes() {
+        let mut server = Server::new();
+        let open = json::parse(r#"{"method": "textDocument/didOpen", "params": {"textDocument": {"uri": "mem:b.fnky", "text": "var x = 1;\nprint (x;"}}}"#).unwrap();
+        let diagnostics = list(get_path(&server.handle(&open)[0], &["params", "diagnostics"]).unwrap());
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(
+            json(&get_path(&diagnostics[0], &["range"])),
+            r#"{"start":{"line":1,"character":0},"end":{"line":1,"character":9}}"#
+        );
+
+        let change = json::parse(r#"{"method": "textDocument/didChange", "params": {"textDocument": {"uri": "mem:b.fnky"}, "contentChanges": [{"text": "fun f() {\n    var unused = 1;\n}"}]}}"#).unwrap();
+        let diagnostics = list(get_path(&server.handle(&change)[0], &["params", "diagnostics"]).unwrap());
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(get(&diagnostics[0], "code"), Some(string("unused-variable")));
+        assert_eq!(get(&diagnostics[0], "severity"), Some(LiteralValue::Number(2.0)));
+        assert_eq!(get_path(&diagnostics[0], &["range", "start", "line"]), Some(LiteralValue::Number(1.0)));
+    }
+
+    #[test]
+    fn positions_count_utf16_units() {
+        let lines = Lines::new("var s = \"😀\"; s\nx");
+        assert_eq!(lines.offset(0, 14), 16);
+        assert_eq!(json(&Some(lines.position(16))), r#"{"line":0,"character":14}"#);
+        assert_eq!(lines.offset(1, 0), 18);
+        assert_eq!(lines.offset(5, 0), 19);
+    }
+}
@@ -1,84 +1,220 @@
-mod expr;
-mod interpreter;
-mod parser;
-mod scanner;
-mod stmt;
-mod environment;
-use crate::environment::Environment;
-use crate::interpreter::Interpreter;
-use crate::scanner::*;
-use crate::stmt::Stmt::*;
-use parser::Parser;
+use fnky::ast;
+use fnky::format::format_source;
+use fnky::lint::{lint_source, LintConfig, Severity};
+use fnky::parser::Parser;
+use fnky::project::{Manifest, MANIFEST_FILE};
+use fnky::repl::Repl;
+use fnky::scanner::Scanner;
+use fnky::{EvalError, Interpreter, INTERPRETER_STACK_SIZE};
 use std::env;
 use std::fs;
-use std::io::{self, BufRead, Write};
+use std::io::{self, Write};
 use std::process::exit;
+use std::thread;
 
 // static interpreter: Interpreter = Interpreter::new();
 
-fn run_file(path: &str) -> Result<(), String> {
-    let mut interpreter: Interpreter = Interpreter::new();
+fn run_file(path: &str, script_args: &[String]) -> Result<(), String> {
+    let mut interpreter: Interpreter = Interpreter::for_file(path);
+    interpreter.set_args(script_args.to_vec());
+    add_fnky_path(&mut interpreter);
     match fs::read_to_string(path) {
-        Err(msg) => return Err(msg.to_string()),
-        Ok(contents) => return run(&mut interpreter, &contents),
+        Err(msg) => Err(msg.to_string()),
+        Ok(contents) => run(&mut interpreter, &contents),
     }
 }
 
-fn run(interpreter: &mut Interpreter, _contents: &str) -> Result<(), String> {
-    let mut scanner = Scanner::new(_contents); // Now `scanner` is mutable
-    let tokens = scanner.scan_tokens().unwrap(); // Now it can be borrowed mutably
+// `fnky run`: runs the entry point of the project whose fnky.toml is in the
+// current directory or one of its parents.
+fn run_project(script_args: &[String]) -> Result<(), String> {
+    let cwd = env::current_dir().map_err(|err| err.to_string())?;
+    let manifest_path = Manifest::find(&cwd).ok_or_else(|| {
+        format!("Could not find {} in {} or any parent directory", MANIFEST_FILE, cwd.display())
+    })?;
+    let manifest = Manifest::load(&manifest_path)?;
+    let entry_path = manifest
+        .entry
+        .ok_or_else(|| format!("{}: Missing 'entry' in [package]", manifest_path.display()))?;
 
-    let mut parser = Parser::new(tokens);
-    // let expr = parser.parse()?;
-    // let result = expr.evaluate()?;
-    let stmts = parser.parse()?;
+    let entry = entry_path.to_string_lossy().to_string();
+    let mut interpreter = Interpreter::for_file(&entry);
+    interpreter.set_args(script_args.to_vec());
+    for (name, dir) in manifest.dependencies {
+        interpreter.add_package(&name, dir);
+    }
+    add_fnky_path(&mut interpreter);
 
-    interpreter.interpret(stmts)?;
-    return Ok(());
+    match fs::read_to_string(&entry_path) {
+        Err(msg) => Err(format!("Could not read entry point {}: {}", entry, msg)),
+        Ok(contents) => run(&mut interpreter, &contents),
+    }
 }
 
-fn run_prompt() -> Result<(), String> {
-    let mut interpreter: Interpreter = Interpreter::new();
-    loop {
-        println!("> ");
-        let mut buffer = String::new(); //make a new empty string which will later store the input from user
-        match io::stdout().flush() {
-            Ok(_) => (),
-            Err(_) => return Err("Could not reach flush stdout".to_string()),
+// `fnky fmt [--check] FILE...`: rewrites each file in the canonical layout.
+// With --check nothing is written; the files that would change are listed
+// and the exit status says whether there were any.
+fn format_files(args: &[String]) -> Result<bool, String> {
+    let check = args.iter().any(|arg| arg == "--check");
+    let paths: Vec<&String> = args.iter().filter(|arg| *arg != "--check").collect();
+    if paths.is_empty() {
+        return Err("Usage: fnky fmt [--check] FILE...".to_string());
+    }
+
+    let mut formatted = true;
+    for path in paths {
+        let source = fs::read_to_string(path).map_err(|err| format!("Could not read {}: {}", path, err))?;
+        let output = format_source(&source).map_err(|err| format!("{}:\n{}", path, err))?;
+        if output == source {
+            continue;
         }
-        //means to print > as soon  as possible
-
-        let stdin = io::stdin();
-        let mut handle = stdin.lock();
-        match handle.read_line(&mut buffer) {
-            //stores t:he input string in the buffer
-            Ok(n) => {
-                if n <= 1 {
-                    //n is the number of bytes here
-                    // dbg!(n);
-                    return Ok(());
-                }
-            }
-            Err(_) => return Err("Couldn't read line".to_string()),
+        if check {
+            println!("{} is not formatted", path);
+            formatted = false;
+        } else {
+            fs::write(path, output).map_err(|err| format!("Could not write {}: {}", path, err))?;
         }
+    }
+    Ok(formatted)
+}
+
+// `fnky lint FILE...`: prints every finding, configured by the [lint]
+// section of the fnky.toml nearest each file if there is one. Returns false
+// when a file has findings of error severity.
+fn lint_files(paths: &[String]) -> Result<bool, String> {
+    if paths.is_empty() {
+        return Err("Usage: fnky lint FILE...".to_string());
+    }
+    let cwd = env::current_dir().map_err(|err| err.to_string())?;
 
-        println!("ECHO: {}", buffer);
-        match run(&mut interpreter, &buffer) {
-            //sedning a immutable refernece to the run function which will execute the text passed in the input terminal
-            Ok(_) => (),
-            Err(msg) => println!("ERROR:\n {}", msg),
+    let mut passed = true;
+    for path in paths {
+        let config = match cwd.join(path).parent().and_then(Manifest::find) {
+            Some(manifest_path) => Manifest::load(&manifest_path)?.lint,
+            None => LintConfig::default(),
+        };
+        let source = fs::read_to_string(path).map_err(|err| format!("Could not read {}: {}", path, err))?;
+        let diagnostics = lint_source(&source, &config).map_err(|err| format!("{}:\n{}", path, err))?;
+        for diagnostic in diagnostics {
+            println!("{}: {}", path, diagnostic);
+            passed &= diagnostic.severity != Severity::Error;
         }
     }
+    Ok(passed)
+}
+
+// `fnky --dump-ast=json|sexpr FILE`: prints the syntax tree of a file, with
+// the span of every node, for other tools to read.
+fn dump_ast(format: &str, args: &[String]) -> Result<String, String> {
+    let [path] = args else {
+        return Err("Usage: fnky --dump-ast=json|sexpr FILE".to_string());
+    };
+    let source = fs::read_to_string(path).map_err(|err| format!("Could not read {}: {}", path, err))?;
+    let stmts = Parser::new(Scanner::new(&source).scan_tokens()?)
+        .parse()
+        .map_err(|err| format!("{}:\n{}", path, err))?;
+    match format {
+        "json" => Ok(ast::to_json(&stmts) + "\n"),
+        "sexpr" => Ok(ast::to_sexpr(&stmts)),
+        other => Err(format!("Unknown AST format '{}'; expected json or sexpr", other)),
+    }
+}
+
+// FNKY_PATH lists extra directories searched by `import`, separated like PATH.
+fn add_fnky_path(interpreter: &mut Interpreter) {
+    if let Some(paths) = env::var_os("FNKY_PATH") {
+        for dir in env::split_paths(&paths).filter(|dir| !dir.as_os_str().is_empty()) {
+            interpreter.add_search_path(dir);
+        }
+    }
+}
+
+fn run(interpreter: &mut Interpreter, contents: &str) -> Result<(), String> {
+    match interpreter.eval(contents) {
+        Ok(_) => Ok(()),
+        // os.exit(code) ends the whole process, the REPL included.
+        Err(EvalError::Exit(code)) => {
+            let _ = io::stdout().flush();
+            exit(code)
+        }
+        Err(err) => Err(err.to_string()),
+    }
+}
+
+fn run_prompt() -> Result<(), String> {
+    let mut repl = Repl::new(|| {
+        let mut interpreter = Interpreter::new();
+        add_fnky_path(&mut interpreter);
+        interpreter
+    });
+    let status = repl.run()?;
+    exit(status)
 }
 
 fn main() {
+    // Interpreted calls recurse on the native stack, so run on a thread with
+    // more room than the main thread gets by default.
+    let cli = thread::Builder::new()
+        .stack_size(INTERPRETER_STACK_SIZE)
+        .spawn(run_cli)
+        .expect("could not start interpreter thread");
+    if cli.join().is_err() {
+        exit(101);
+    }
+}
+
+fn run_cli() {
     let args: Vec<String> = env::args().collect();
 
-    if args.len() > 2 {
-        println!("Usage: fnky [script]");
-        exit(64);
-    } else if args.len() == 2 {
-        match run_file(&args[1]) {
+    // Arguments after the script name (or after `run`) are passed on to the
+    // script as `os.args()`.
+    if args.len() >= 2 && args[1] == "fmt" {
+        match format_files(&args[2..]) {
+            Ok(formatted) => exit(if formatted { 0 } else { 1 }),
+            Err(msg) => {
+                println!("ERROR:\n{}", msg);
+                exit(1);
+            }
+        }
+    } else if args.len() >= 2 && args[1] == "lint" {
+        match lint_files(&args[2..]) {
+            Ok(passed) => exit(if passed { 0 } else { 1 }),
+            Err(msg) => {
+                println!("ERROR:\n{}", msg);
+                exit(1);
+            }
+        }
+    } else if args.len() >= 2
+        && let Some(format) = args[1].strip_prefix("--dump-ast=")
+    {
+        match dump_ast(format, &args[2..]) {
+            Ok(tree) => {
+                print!("{}", tree);
+                exit(0)
+            }
+            Err(msg) => {
+                println!("ERROR:\n{}", msg);
+                exit(1);
+            }
+        }
+    } else if args.len() >= 2 && args[1] == "lsp" {
+        // Standard output carries the protocol, so errors go to stderr.
+        match fnky::lsp::run(io::stdin().lock(), io::stdout()) {
+            Ok(status) => exit(status),
+            Err(msg) => {
+                eprintln!("ERROR:\n{}", msg);
+                exit(1);
+            }
+        }
+    } else if args.len() >= 2 && args[1] == "run" {
+        match run_project(&args[2..]) {
+            Ok(_) => exit(0),
+            Err(msg) => {
+                println!("ERROR:\n{}", msg);
+                exit(1);
+            }
+        }
+    } else if args.len() >= 2 {
+        match run_file(&args[1], &args[2..]) {
             //the args[0] will be the location of executable of this code but args[1] will store the file location whijch will be read by the interpreter and executes it
             Ok(_) => exit(0),
             Err(msg) => {
@@ -0,0 +1,130 @@
+use std::collections::HashMap;
+
+use crate::expr::LiteralValue;
+
+/// A value that can be used as a map key: nil, a boolean, a number or a
+/// string. Lists, maps and functions cannot be keys.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum Key {
+    Nil,
+    Bool(bool),
+    // The bits of the number, with -0 stored as 0 so both find the same
+    // entry.
+    Number(u64),
+    String(String),
+}
+
+impl Key {
+    pub fn new(value: &LiteralValue) -> Result<Self, String> {
+        match value {
+            LiteralValue::Nil => Ok(Key::Nil),
+            LiteralValue::True => Ok(Key::Bool(true)),
+            LiteralValue::False => Ok(Key::Bool(false)),
+            LiteralValue::Number(x) if *x == 0.0 => Ok(Key::Number(0f64.to_bits())),
+            LiteralValue::Number(x) => Ok(Key::Number(x.to_bits())),
+            LiteralValue::StringValue(s) => Ok(Key::String(s.clone())),
+            other => Err(format!("{} cannot be used as a map key", other.to_type())),
+        }
+    }
+
+    pub fn to_value(&self) -> LiteralValue {
+        match self {
+            Key::Nil => LiteralValue::Nil,
+            Key::Bool(b) => LiteralValue::from_bool(*b),
+            Key::Number(bits) => LiteralValue::Number(f64::from_bits(*bits)),
+            Key::String(s) => LiteralValue::StringValue(s.clone()),
+        }
+    }
+}
+
+/// The contents of a map value. Entries keep their insertion order, which
+/// is the order `keys()`, printing and iteration use.
+#[derive(Debug, Clone, Default)]
+pub struct Map {
+    entries: Vec<(Key, LiteralValue)>,
+    positions: HashMap<Key, usize>,
+}
+
+impl Map {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    pub fn get(&self, key: &Key) -> Option<&LiteralValue> {
+        self.positions.get(key).map(|&position| &self.entries[position].1)
+    }
+
+    /// Sets `key` to `value`. A key that is already present keeps its place
+    /// in the order.
+    pub fn insert(&mut self, key: Key, value: LiteralValue) {
+        match self.positions.get(&key) {
+            Some(&position) => self.entries[position].1 = value,
+            None => {
+                self.positions.insert(key.clone(), self.entries.len());
+                self.entries.push((key, value));
+            }
+        }
+    }
+
+    pub fn remove(&mut self, key: &Key) -> Option<LiteralValue> {
+        let position = self.positions.remove(key)?;
+        let (_, value) = self.entries.remove(position);
+        for (key, _) in &self.entries[position..] {
+            *self.positions.get_mut(key).expect("every entry is indexed") -= 1;
+        }
+        Some(value)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&Key, &LiteralValue)> {
+        self.entries.iter().map(|(key, value)| (key, value))
+    }
+}
+
+// Two maps are equal when they hold the same entries, in any order.
+impl PartialEq for Map {
+    fn eq(&self, other: &Self) -> bool {
+        self.len() == other.len() && self.iter().all(|(key, value)| other.get(key) == Some(value))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn key(s: &str) -> Key {
+        Key::String(s.to_string())
+    }
+
+    #[test]
+    fn keeps_insertion_order_across_removal() {
+        let mut map = Map::new();
+        for (index, name) in ["a", "b", "c"].iter().enumerate() {
+            map.insert(key(name), LiteralValue::Number(index as f64));
+        }
+        map.insert(key("a"), LiteralValue::Nil);
+        assert_eq!(map.remove(&key("b")), Some(LiteralValue::Number(1.0)));
+        map.insert(key("d"), LiteralValue::True);
+
+        let keys: Vec<&Key> = map.iter().map(|(key, _)| key).collect();
+        assert_eq!(keys, vec![&key("a"), &key("c"), &key("d")]);
+        assert_eq!(map.get(&key("c")), Some(&LiteralValue::Number(2.0)));
+        assert_eq!(map.get(&key("b")), None);
+    }
+
+    #[test]
+    fn only_simple_values_are_keys() {
+        assert_eq!(Key::new(&LiteralValue::Number(-0.0)), Key::new(&LiteralValue::Number(0.0)));
+        assert_eq!(
+            Key::new(&LiteralValue::list(vec![])),
+            Err("List cannot be used as a map key".to_string())
+        );
+    }
+}
@@ -0,0 +1,259 @@
+use std::cell::RefCell;
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+
+use crate::environment::Environment;
+use crate::expr::LiteralValue;
+use crate::stmt::Stmt;
+
+/// A script loaded through `import`. It keeps its own global environment
+/// alive, but only the names it exports can be read from outside.
+pub struct Module {
+    pub path: PathBuf,
+    pub globals: Rc<RefCell<Environment>>,
+    pub exports: HashSet<String>,
+}
+
+impl Module {
+    pub fn name(&self) -> String {
+        self.path
+            .file_stem()
+            .map(|stem| stem.to_string_lossy().to_string())
+            .unwrap_or_else(|| self.path.display().to_string())
+    }
+
+    /// Looks up an exported name. Exports are read live, so a module that
+    /// reassigns one of its exported variables is seen by its importers.
+    pub fn get(&self, name: &str) -> Option<LiteralValue> {
+        if self.exports.contains(name) {
+            self.globals.borrow().get(name)
+        } else {
+            None
+        }
+    }
+}
+
+impl PartialEq for Module {
+    fn eq(&self, other: &Self) -> bool {
+        std::ptr::eq(self, other)
+    }
+}
+
+impl fmt::Debug for Module {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "<module {}>", self.name())
+    }
+}
+
+/// Where `import` looks for modules besides the importing file's directory:
+/// named local packages (from a project manifest) and a list of search
+/// directories (from `FNKY_PATH`). Everything is resolved on the local
+/// filesystem.
+#[derive(Default)]
+pub struct ModulePaths {
+    pub packages: HashMap<String, PathBuf>,
+    pub search_paths: Vec<PathBuf>,
+}
+
+impl ModulePaths {
+    /// Resolves the path written in an import statement.
+    ///
+    /// Paths starting with `./` or `../` and absolute paths only resolve
+    /// against the importing file. Other paths are tried, in order,
+    /// relative to the importing file, inside the package named by their
+    /// first component, and in each search directory. A missing `.fnky`
+    /// extension is filled in. Code that does not come from a file (like
+    /// the REPL) resolves against the working directory.
+    pub fn resolve(&self, importer: &str, requested: &str) -> Result<PathBuf, String> {
+        let base = if importer.starts_with('<') {
+            PathBuf::from(".")
+        } else {
+            Path::new(importer)
+                .parent()
+                .map(Path::to_path_buf)
+                .unwrap_or_else(|| PathBuf::from("."))
+        };
+
+        let requested_path = Path::new(requested);
+        let mut candidates = vec![base.join(requested_path)];
+
+        let explicitly_relative = requested.starts_with("./") || requested.starts_with("../");
+        if !explicitly_relative && !requested_path.is_absolute() {
+            let mut components = requested_path.components();
+            if let Some(package) = components.next().and_then(|first| self.packages.get(&*first.as_os_str().to_string_lossy())) {
+                candidates.push(package.join(components.as_path()));
+            }
+            for dir in &self.search_paths {
+                candidates.push(dir.join(requested_path));
+            }
+        }
+
+        for candidate in candidates {
+            let with_extension = candidate.with_extension(EXTENSION);
+            let variants = if candidate.extension().is_none() {
+                vec![candidate, with_extension]
+            } else {
+                vec![candidate]
+            };
+            for variant in variants {
+                if variant.is_file() {
+                    return variant
+                        .canonicalize()
+                        .map_err(|err| format!("Cannot open module '{}': {}", requested, err));
+                }
+            }
+        }
+
+        Err(format!("Cannot find module '{}'", requested))
+    }
+}
+
+const EXTENSION: &str = "fnky";
+
+/// Names declared with `export` at the top level of a module.
+pub fn exported_names(stmts: &[Stmt]) -> HashSet<String> {
+    stmts
+        .iter()
+        .filter_map(|stmt| match stmt {
+            Stmt::Export { keyword: _, declaration } => match declaration.as_ref() {
+                Stmt::Var { name, .. } | Stmt::Function { name, .. } => Some(name.lexeme.clone()),
+                _ => None,
+            },
+            _ => None,
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::interpreter::Interpreter;
+    use crate::parser::Parser;
+    use crate::scanner::Scanner;
+    use crate::testing::TempDir;
+    use std::fs;
+
+    // Writes `files` into a fresh directory and runs the first one as the
+    // main script.
+    fn run_project(name: &str, files: &[(&str, &str)]) -> (Interpreter, Result<(), String>) {
+        let root = TempDir::new(&format!("module-{}", name));
+        for (path, source) in files {
+            let path = root.join(path);
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(path, source).unwrap();
+        }
+
+        let main = root.join(files[0].0);
+        let mut interpreter = Interpreter::for_file(main.to_str().unwrap());
+        let tokens = Scanner::new(files[0].1).scan_tokens().unwrap();
+        let stmts = Parser::new(tokens).parse().unwrap();
+        let result = interpreter.interpret(stmts);
+        (interpreter, result)
+    }
+
+    fn global(interpreter: &Interpreter, name: &str) -> Option<LiteralValue> {
+        interpreter.environment.borrow().get(name)
+    }
+
+    #[test]
+    fn import_exposes_only_exported_names() {
+        let (interpreter, result) = run_project(
+            "exports",
+            &[
+                (
+                    "main.fnky",
+                    r#"
+                    import "lib/math.fnky" as math;
+                    var sum = math.add(2, 3);
+                    var hidden = nil;
+                    try { math.helper; } catch (e) { hidden = e.message; }
+                    "#,
+                ),
+                (
+                    "lib/math.fnky",
+                    "fun helper(x) { return x; }\nexport fun add(a, b) { return helper(a) + b; }\n",
+                ),
+            ],
+        );
+        result.unwrap();
+        assert_eq!(global(&interpreter, "sum"), Some(LiteralValue::Number(5.0)));
+        assert_eq!(
+            global(&interpreter, "hidden"),
+            Some(LiteralValue::StringValue("Module 'math' does not export 'helper'".to_string()))
+        );
+    }
+
+    #[test]
+    fn from_import_resolves_relative_to_importer_and_runs_once() {
+        let (interpreter, result) = run_project(
+            "from",
+            &[
+                (
+                    "main.fnky",
+                    r#"
+                    from "pkg/a.fnky" import greeting, b;
+                    import "pkg/b.fnky" as direct;
+                    var same = b == direct;
+                    "#,
+                ),
+                (
+                    "pkg/a.fnky",
+                    "import \"b.fnky\" as inner;\nexport var b = inner;\nexport var greeting = inner.name + \"!\";\n",
+                ),
+                ("pkg/b.fnky", "export var name = \"hi\";\n"),
+            ],
+        );
+        result.unwrap();
+        assert_eq!(global(&interpreter, "greeting"), Some(LiteralValue::StringValue("hi!".to_string())));
+        assert_eq!(global(&interpreter, "same"), Some(LiteralValue::True));
+    }
+
+    #[test]
+    fn import_cycles_are_reported() {
+        let (_, result) = run_project(
+            "cycle",
+            &[
+                ("main.fnky", "import \"a.fnky\" as a;\n"),
+                ("a.fnky", "import \"b.fnky\" as b;\n"),
+                ("b.fnky", "import \"a.fnky\" as a;\n"),
+            ],
+        );
+        let err = result.unwrap_err();
+        assert!(err.starts_with("ImportError: Import cycle detected: "));
+        let first_line = err.lines().next().unwrap();
+        assert!(first_line.contains("a.fnky -> ") && first_line.contains("b.fnky -> ") && first_line.ends_with("a.fnky"));
+    }
+
+    #[test]
+    fn search_paths_and_packages_are_tried_after_the_importer() {
+        let root = TempDir::new("module-search");
+        for dir in ["app", "std", "vendor/strings"] {
+            fs::create_dir_all(root.join(dir)).unwrap();
+        }
+        fs::write(root.join("std/list.fnky"), "").unwrap();
+        fs::write(root.join("vendor/strings/pad.fnky"), "").unwrap();
+        fs::write(root.join("app/list.fnky"), "").unwrap();
+
+        let mut paths = ModulePaths::default();
+        paths.search_paths.push(root.join("std"));
+        paths.packages.insert("strings".to_string(), root.join("vendor/strings"));
+
+        let importer = root.join("app/main.fnky");
+        let importer = importer.to_str().unwrap();
+        let canonical = |path: &str| root.join(path).canonicalize().unwrap();
+
+        assert_eq!(paths.resolve(importer, "list").unwrap(), canonical("app/list.fnky"));
+        assert_eq!(paths.resolve(importer, "strings/pad").unwrap(), canonical("vendor/strings/pad.fnky"));
+        fs::remove_file(root.join("app/list.fnky")).unwrap();
+        assert_eq!(paths.resolve(importer, "list.fnky").unwrap(), canonical("std/list.fnky"));
+        assert!(paths.resolve(importer, "./list").is_err());
+    }
+
+    #[test]
+    fn missing_module_is_an_import_error() {
+        let (_, result) = run_project("missing", &[("main.fnky", "import \"nope.fnky\" as nope;\n")]);
+        assert!(result.unwrap_err().starts_with("ImportError: Cannot find module 'nope.fnky'"));
+    }
+}
@@ -0,0 +1,53 @@
+use std::cell::RefCell;
+use std::io::{self, Write};
+use std::rc::Rc;
+
+/// An output sink that collects everything written to it in memory. Clones
+/// share the same buffer, so a host can hand one clone to
+/// [`Interpreter::set_output`](crate::Interpreter::set_output) and read the
+/// script's output through another.
+#[derive(Clone, Default)]
+pub struct OutputBuffer {
+    buffer: Rc<RefCell<Vec<u8>>>,
+}
+
+impl OutputBuffer {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Everything written so far, decoded as UTF-8.
+    pub fn contents(&self) -> String {
+        String::from_utf8_lossy(&self.buffer.borrow()).to_string()
+    }
+
+    pub fn clear(&self) {
+        self.buffer.borrow_mut().clear();
+    }
+}
+
+impl Write for OutputBuffer {
+    fn write(&mut self, bytes: &[u8]) -> io::Result<usize> {
+        self.buffer.borrow_mut().extend_from_slice(bytes);
+        Ok(bytes.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn clones_share_the_buffer() {
+        let buffer = OutputBuffer::new();
+        let mut writer = buffer.clone();
+        write!(writer, "héllo {}", 1).unwrap();
+        assert_eq!(buffer.contents(), "héllo 1");
+        buffer.clear();
+        assert_eq!(writer.contents(), "");
+    }
+}
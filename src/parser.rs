@@ -1,17 +1,20 @@
-use std::char::EscapeUnicode;
+use std::rc::Rc;
 
 use crate::expr::{Expr, Expr::*};
-use crate::expr::{LiteralValue, LiteralValue::*};
-use crate::scanner::*;
+use crate::expr::LiteralValue;
 use crate::scanner::{Token, TokenType, TokenType::*};
 use crate::stmt::Stmt;
-use crate::stmt::Stmt::*;
 
 pub struct Parser {
     tokens: Vec<Token>,
     current: usize,
+    function_depth: usize,
+    block_depth: usize,
+    errors: Vec<String>,
 }
 
+const MAX_ARGUMENTS: usize = 255;
+
 //the modification donen and reverted backk from sublime text
 macro_rules! match_tokens {
     ($self:expr, $($token:ident),+) => {
@@ -25,57 +28,206 @@ macro_rules! match_tokens {
 
 impl Parser {
     pub fn new(tokens: Vec<Token>) -> Self {
-        Self { tokens, current: 0 }
+        Self {
+            tokens,
+            current: 0,
+            function_depth: 0,
+            block_depth: 0,
+            errors: vec![],
+        }
     }
 
     pub fn parse(&mut self) -> Result<Vec<Stmt>, String> {
-        let mut stmts: Vec<Stmt> = Vec::new();
-        let mut errs: Vec<String> = Vec::new();
+        let (stmts, errors) = self.parse_partial();
+        if errors.is_empty() {
+            Ok(stmts)
+        } else {
+            Err(errors.join("\n"))
+        }
+    }
 
+    /// Parses as much of the program as it can. A statement that does not
+    /// parse becomes a `Stmt::Error` and parsing carries on after it, inside
+    /// blocks and function bodies too, so one mistake does not hide the
+    /// code that follows. Returns the statements and every error message.
+    ///
+    /// `parse` is built on this. It is public for programs embedding Fnky
+    /// that want the tree around the errors; the CLI and the LSP server only
+    /// report the messages.
+    pub fn parse_partial(&mut self) -> (Vec<Stmt>, Vec<String>) {
+        let mut stmts = vec![];
         while !self.is_at_end() {
-            let stmt = self.declaration();
-            match stmt {
-                Ok(s) => stmts.push(s),
-                Err(msg) => errs.push(msg),
-            }
+            stmts.push(self.declaration_or_error());
         }
+        (stmts, std::mem::take(&mut self.errors))
+    }
 
-        if errs.len() == 0 {
-            Ok(stmts)
-        } else {
-            Err(errs.join("\n"))
+    // On an error, records it and skips to the next statement, which also
+    // makes sure the parser never stalls on the same token.
+    fn declaration_or_error(&mut self) -> Stmt {
+        match self.declaration() {
+            Ok(stmt) => stmt,
+            Err(message) => {
+                let token = self.peek().clone();
+                self.synchronize();
+                self.errors.push(message.clone());
+                Stmt::Error { token, message }
+            }
         }
     }
 
     fn declaration(&mut self) -> Result<Stmt, String> {
         if self.match_token(&VAR) {
-            match self.var_declaration() {
-                Ok(stmt) => Ok(stmt),
-                Err(msg) => {
-                    self.synchronize();
-                    Err(msg)
-                }
-            }
+            self.var_declaration()
+        } else if self.check_function_declaration() {
+            self.advance();
+            self.function("function")
+        } else if self.match_token(&EXPORT) {
+            self.export_declaration()
+        } else if self.match_token(&IMPORT) {
+            self.import_statement()
+        } else if self.check_import_from() {
+            self.import_from_statement()
         } else {
             self.statement()
         }
     }
 
+    fn export_declaration(&mut self) -> Result<Stmt, String> {
+        let keyword = self.previous();
+        if self.block_depth > 0 {
+            return Err(format!(
+                "'export' is only allowed at the top level of a module at line {}",
+                keyword.line_number
+            ));
+        }
+
+        let declaration = if self.match_token(&VAR) {
+            self.var_declaration()?
+        } else if self.match_token(&FUN) {
+            self.function("function")?
+        } else {
+            return Err(format!(
+                "Expected 'var' or 'fun' after 'export' at line {}",
+                keyword.line_number
+            ));
+        };
+
+        Ok(Stmt::Export {
+            keyword,
+            declaration: Box::new(declaration),
+        })
+    }
+
+    // import "path/to/module.fnky" as name;
+    fn import_statement(&mut self) -> Result<Stmt, String> {
+        let keyword = self.previous();
+        let path = self.consume(STRING, "Expected module path after 'import'")?;
+        if !self.match_contextual("as") {
+            return Err(format!("Expected 'as' after module path at line {}", path.line_number));
+        }
+        let alias = self.consume(IDENTIFIER, "Expected module name after 'as'")?;
+        self.consume(SEMICOLON, "Expected ';' after import")?;
+
+        Ok(Stmt::Import {
+            keyword,
+            path,
+            alias,
+        })
+    }
+
+    // `from` is not reserved, so it only starts an import when a module
+    // path follows it: from "path/to/module.fnky" import a, b;
+    fn check_import_from(&self) -> bool {
+        let next_is_path = matches!(self.tokens.get(self.current + 1), Some(token) if token.token_type == STRING);
+        self.peek().token_type == IDENTIFIER && self.peek().lexeme == "from" && next_is_path
+    }
+
+    fn import_from_statement(&mut self) -> Result<Stmt, String> {
+        self.advance();
+        let keyword = self.previous();
+        let path = self.consume(STRING, "Expected module path after 'from'")?;
+        self.consume(IMPORT, "Expected 'import' after module path")?;
+
+        let mut names = vec![self.consume(IDENTIFIER, "Expected name to import")?];
+        while self.match_token(&COMMA) {
+            names.push(self.consume(IDENTIFIER, "Expected name to import")?);
+        }
+        self.consume(SEMICOLON, "Expected ';' after import")?;
+
+        Ok(Stmt::ImportFrom {
+            keyword,
+            path,
+            names,
+        })
+    }
+
+    // `fun` followed by a name declares a function; `fun (` starts an
+    // anonymous function expression.
+    fn check_function_declaration(&self) -> bool {
+        let next_is_name = matches!(self.tokens.get(self.current + 1), Some(token) if token.token_type == IDENTIFIER);
+        self.peek().token_type == FUN && next_is_name
+    }
+
+    fn function(&mut self, kind: &str) -> Result<Stmt, String> {
+        let keyword = self.previous();
+        let name = self.consume(IDENTIFIER, &format!("Expected {kind} name"))?;
+        self.consume(LEFT_PAREN, &format!("Expected '(' after {kind} name"))?;
+        let (params, body) = self.function_rest(kind)?;
+
+        Ok(Stmt::Function {
+            keyword,
+            name,
+            params,
+            body,
+        })
+    }
+
+    // Parses the parameters and body of a function, after its '('.
+    fn function_rest(&mut self, kind: &str) -> Result<(Vec<Token>, Rc<Vec<Stmt>>), String> {
+
+        let mut params = vec![];
+        if !self.check(RIGHT_PAREN) {
+            loop {
+                if params.len() >= MAX_ARGUMENTS {
+                    return Err(format!(
+                        "Can't have more than {MAX_ARGUMENTS} parameters at line {}",
+                        self.peek().line_number
+                    ));
+                }
+                params.push(self.consume(IDENTIFIER, "Expected parameter name")?);
+                if !self.match_token(&COMMA) {
+                    break;
+                }
+            }
+        }
+        self.consume(RIGHT_PAREN, "Expected ')' after parameters")?;
+        self.consume(LEFT_BRACE, &format!("Expected '{{' before {kind} body"))?;
+
+        self.function_depth += 1;
+        let body = self.block();
+        self.function_depth -= 1;
+
+        Ok((params, Rc::new(body?)))
+    }
+
     fn var_declaration(&mut self) -> Result<Stmt, String> {
+        let keyword = self.previous();
         let token = self.consume(IDENTIFIER, "Expected bvariable name")?;
 
-        let initialser;
-
-        if self.match_token(&EQUAL) {
-            initialser = self.expression()?;
+        let initialser = if self.match_token(&EQUAL) {
+            self.expression()?
         } else {
-            initialser = Literal {value: LiteralValue::Nil };
-        }
+            Literal {
+                token: token.clone(),
+                value: LiteralValue::Nil,
+            }
+        };
 
-        self.consume(SEMICOLON, "Expected ':' after variable decalaration")?;
+        self.consume(SEMICOLON, "Expected ';' after variable decalaration")?;
 
         Ok(Stmt:: Var {
-            expression: self.expression()?,
+            keyword,
             name: token,
             initialiser: initialser,
         })
@@ -86,26 +238,201 @@ impl Parser {
             self.print_statement()
         } else if self.match_token(&LEFT_BRACE)  {
             self.block_statement()
+        } else if self.match_token(&IF) {
+            self.if_statement()
+        } else if self.match_token(&WHILE) {
+            self.while_statement()
+        } else if self.match_token(&FOR) {
+            self.for_statement()
+        } else if self.match_token(&RETURN) {
+            self.return_statement()
+        } else if self.match_token(&THROW) {
+            self.throw_statement()
+        } else if self.match_token(&TRY) {
+            self.try_statement()
         } else {
             self.expression_statement()
         }
     }
 
     fn block_statement(&mut self) -> Result<Stmt, String> {
+        let brace = self.previous();
+        Ok(Stmt::Block {
+            brace,
+            statements: self.block()?,
+        })
+    }
+
+    // Parses the statements of a block whose '{' has already been consumed.
+    fn block(&mut self) -> Result<Vec<Stmt>, String> {
+        self.block_depth += 1;
+        let statements = self.block_statements();
+        self.block_depth -= 1;
+        statements
+    }
+
+    fn block_statements(&mut self) -> Result<Vec<Stmt>, String> {
         let mut statements = vec![];
 
         while !self.check(RIGHT_BRACE) && !self.is_at_end() {
-            let decl = self.declaration()?;
-            statements.push(decl);
+            statements.push(self.declaration_or_error());
+        }
+        self.consume(RIGHT_BRACE, "Expected '}' after a block")?;
+        Ok(statements)
+    }
+
+    fn if_statement(&mut self) -> Result<Stmt, String> {
+        let keyword = self.previous();
+        self.consume(LEFT_PAREN, "Expected '(' after 'if'")?;
+        let condition = self.expression()?;
+        self.consume(RIGHT_PAREN, "Expected ')' after if condition")?;
+
+        let then_branch = Box::new(self.statement()?);
+        let else_branch = if self.match_token(&ELSE) {
+            Some(Box::new(self.statement()?))
+        } else {
+            None
+        };
+
+        Ok(Stmt::If {
+            keyword,
+            condition,
+            then_branch,
+            else_branch,
+        })
+    }
+
+    fn while_statement(&mut self) -> Result<Stmt, String> {
+        let keyword = self.previous();
+        self.consume(LEFT_PAREN, "Expected '(' after 'while'")?;
+        let condition = self.expression()?;
+        self.consume(RIGHT_PAREN, "Expected ')' after while condition")?;
+        let body = Box::new(self.statement()?);
+
+        Ok(Stmt::While { keyword, condition, body })
+    }
+
+    // A for loop is sugar for a while loop wrapped in a block:
+    // for (init; cond; incr) body  =>  { init; while (cond) { body; incr; } }
+    fn for_statement(&mut self) -> Result<Stmt, String> {
+        let keyword = self.previous();
+        self.consume(LEFT_PAREN, "Expected '(' after 'for'")?;
+
+        let initialiser = if self.match_token(&SEMICOLON) {
+            None
+        } else if self.match_token(&VAR) {
+            Some(self.var_declaration()?)
+        } else {
+            Some(self.expression_statement()?)
+        };
+
+        let condition = if self.check(SEMICOLON) {
+            None
+        } else {
+            Some(self.expression()?)
+        };
+        let semicolon = self.consume(SEMICOLON, "Expected ';' after loop condition")?;
+
+        let increment = if self.check(RIGHT_PAREN) {
+            None
+        } else {
+            Some(self.expression()?)
+        };
+        self.consume(RIGHT_PAREN, "Expected ')' after for clauses")?;
+
+        let mut body = self.statement()?;
+
+        if let Some(increment) = increment {
+            body = Stmt::Block {
+                brace: keyword.clone(),
+                statements: vec![body, Stmt::Expression { expression: increment }],
+            };
+        }
+
+        body = Stmt::While {
+            keyword: keyword.clone(),
+            condition: condition.unwrap_or(Literal {
+                token: semicolon,
+                value: LiteralValue::True,
+            }),
+            body: Box::new(body),
+        };
+
+        if let Some(initialiser) = initialiser {
+            body = Stmt::Block {
+                brace: keyword,
+                statements: vec![initialiser, body],
+            };
+        }
+
+        Ok(body)
+    }
+
+    fn return_statement(&mut self) -> Result<Stmt, String> {
+        let keyword = self.previous();
+        if self.function_depth == 0 {
+            return Err(format!("Can't return from top-level code at line {}", keyword.line_number));
+        }
+
+        let value = if self.check(SEMICOLON) {
+            None
+        } else {
+            Some(self.expression()?)
+        };
+        self.consume(SEMICOLON, "Expected ';' after return value")?;
+
+        Ok(Stmt::Return { keyword, value })
+    }
+
+    fn throw_statement(&mut self) -> Result<Stmt, String> {
+        let keyword = self.previous();
+        let value = self.expression()?;
+        self.consume(SEMICOLON, "Expected ';' after thrown value")?;
+        Ok(Stmt::Throw { keyword, value })
+    }
+
+    fn try_statement(&mut self) -> Result<Stmt, String> {
+        let keyword = self.previous();
+        self.consume(LEFT_BRACE, "Expected '{' after 'try'")?;
+        let body = self.block()?;
+
+        let catch_clause = if self.match_token(&CATCH) {
+            self.consume(LEFT_PAREN, "Expected '(' after 'catch'")?;
+            let name = self.consume(IDENTIFIER, "Expected error variable name in catch clause")?;
+            self.consume(RIGHT_PAREN, "Expected ')' after catch variable")?;
+            self.consume(LEFT_BRACE, "Expected '{' after catch clause")?;
+            Some((name, self.block()?))
+        } else {
+            None
+        };
+
+        let finally_body = if self.match_token(&FINALLY) {
+            self.consume(LEFT_BRACE, "Expected '{' after 'finally'")?;
+            Some(self.block()?)
+        } else {
+            None
+        };
+
+        if catch_clause.is_none() && finally_body.is_none() {
+            return Err("Expected 'catch' or 'finally' after try block".to_string());
         }
-        self.consume(RIGHT_BRACE, "eXPECTED: '} AFTER A BLOCK");
-        Ok( Stmt::Block { statements} )
+
+        Ok(Stmt::Try {
+            keyword,
+            body,
+            catch_clause,
+            finally_body,
+        })
     }
 
     fn print_statement(&mut self) -> Result<Stmt, String> {
+        let keyword = self.previous();
         let value = self.expression()?;
         self.consume(SEMICOLON, "Expected ';' after '.'")?;
-        Ok(Stmt::Print { expression: value })
+        Ok(Stmt::Print {
+            keyword,
+            expression: value,
+        })
     }
 
     fn expression_statement(&mut self) -> Result<Stmt, String> {
@@ -115,7 +442,60 @@ impl Parser {
     }
 
     pub fn expression(&mut self) -> Result<Expr, String> {
-        self.equality()
+        self.assignment()
+    }
+
+    fn assignment(&mut self) -> Result<Expr, String> {
+        let expr = self.or()?;
+
+        if self.match_token(&EQUAL) {
+            let equals = self.previous();
+            let value = self.assignment()?;
+
+            return match expr {
+                Variable { name } => Ok(Assign {
+                    name,
+                    value: Box::new(value),
+                }),
+                Index { object, bracket, index } => Ok(SetIndex {
+                    object,
+                    bracket,
+                    index,
+                    value: Box::new(value),
+                }),
+                _ => Err(format!("Invalid assignment target at line {}", equals.line_number)),
+            };
+        }
+
+        Ok(expr)
+    }
+
+    fn or(&mut self) -> Result<Expr, String> {
+        let mut expr = self.and()?;
+        while self.match_token(&OR) {
+            let operator = self.previous();
+            let rhs = self.and()?;
+            expr = Logical {
+                left: Box::new(expr),
+                operator,
+                right: Box::new(rhs),
+            };
+        }
+        Ok(expr)
+    }
+
+    fn and(&mut self) -> Result<Expr, String> {
+        let mut expr = self.equality()?;
+        while self.match_token(&AND) {
+            let operator = self.previous();
+            let rhs = self.equality()?;
+            expr = Logical {
+                left: Box::new(expr),
+                operator,
+                right: Box::new(rhs),
+            };
+        }
+        Ok(expr)
     }
 
     fn equality(&mut self) -> Result<Expr, String> {
@@ -183,37 +563,132 @@ impl Parser {
                 right: Box::new(rhs),
             })
         } else {
-            self.primary()
+            self.call()
+        }
+    }
+
+    fn call(&mut self) -> Result<Expr, String> {
+        let mut expr = self.primary()?;
+        loop {
+            if self.match_token(&LEFT_PAREN) {
+                expr = self.finish_call(expr)?;
+            } else if self.match_token(&DOT) {
+                let name = self.consume(IDENTIFIER, "Expected property name after '.'")?;
+                expr = Get {
+                    object: Box::new(expr),
+                    name,
+                };
+            } else if self.match_token(&LEFT_BRACKET) {
+                let bracket = self.previous();
+                let index = self.expression()?;
+                self.consume(RIGHT_BRACKET, "Expected ']' after index")?;
+                expr = Index {
+                    object: Box::new(expr),
+                    bracket,
+                    index: Box::new(index),
+                };
+            } else {
+                break;
+            }
         }
+        Ok(expr)
+    }
+
+    fn finish_call(&mut self, callee: Expr) -> Result<Expr, String> {
+        let mut arguments = vec![];
+        if !self.check(RIGHT_PAREN) {
+            loop {
+                if arguments.len() >= MAX_ARGUMENTS {
+                    return Err(format!(
+                        "Can't have more than {MAX_ARGUMENTS} arguments at line {}",
+                        self.peek().line_number
+                    ));
+                }
+                arguments.push(self.expression()?);
+                if !self.match_token(&COMMA) {
+                    break;
+                }
+            }
+        }
+        let paren = self.consume(RIGHT_PAREN, "Expected ')' after arguments")?;
+
+        Ok(Call {
+            callee: Box::new(callee),
+            paren,
+            arguments,
+        })
     }
 
     //here we are checking for the parenthesis
     fn primary(&mut self) -> Result<Expr, String> {
         let token = self.peek().clone(); //returns the token where i am currently at
 
-        let result;
-        match token.token_type {
+        let result = match token.token_type {
             LEFT_PAREN => {
                 self.advance();
                 let expr = self.expression()?;
                 self.consume(RIGHT_PAREN, "Expected: ')'")?;
-                result = Ok(Grouping {
+                Grouping {
+                    paren: token,
                     expression: Box::from(expr),
-                });
+                }
             }
             FALSE | TRUE | NIL | NUMBER | STRING => {
                 self.advance();
-                result = Ok(Literal {
+                Literal {
+                    token: token.clone(),
                     value: LiteralValue::from_token(token.clone()),
-                })
+                }
             }
-            Var => {
+            IDENTIFIER => {
                 self.advance();
-                result = Ok(Variable { name: self.previous() });
+                Variable { name: self.previous() }
             }
-            _ => return Err("Expected expression".to_string()),
-        }
-        result
+            FUN => {
+                self.advance();
+                self.consume(LEFT_PAREN, "Expected '(' after 'fun'")?;
+                let (params, body) = self.function_rest("function")?;
+                Lambda {
+                    keyword: token,
+                    params,
+                    body,
+                }
+            }
+            LEFT_BRACE => {
+                self.advance();
+                let mut entries = vec![];
+                while !self.check(RIGHT_BRACE) {
+                    let key = self.expression()?;
+                    self.consume(COLON, "Expected ':' after map key")?;
+                    entries.push((key, self.expression()?));
+                    if !self.match_token(&COMMA) {
+                        break;
+                    }
+                }
+                self.consume(RIGHT_BRACE, "Expected '}' after map entries")?;
+                Map {
+                    brace: token,
+                    entries,
+                }
+            }
+            LEFT_BRACKET => {
+                self.advance();
+                let mut elements = vec![];
+                while !self.check(RIGHT_BRACKET) {
+                    elements.push(self.expression()?);
+                    if !self.match_token(&COMMA) {
+                        break;
+                    }
+                }
+                self.consume(RIGHT_BRACKET, "Expected ']' after list elements")?;
+                List {
+                    bracket: token,
+                    elements,
+                }
+            }
+            _ => return Err(format!("Expected expression at line {}", token.line_number)),
+        };
+        Ok(result)
     }
 
     fn consume(&mut self, token_type: TokenType, msg: &str) -> Result<Token, String> {
@@ -222,7 +697,7 @@ impl Parser {
             let token = self.previous();
             Ok(token)
         } else {
-            Err(msg.to_string())
+            Err(format!("{} at line {}", msg, self.peek().line_number))
         }
     }
 
@@ -230,6 +705,16 @@ impl Parser {
         self.peek().token_type == typ
     }
 
+    // Matches an identifier used as a keyword only in one position, like `as`.
+    fn match_contextual(&mut self, word: &str) -> bool {
+        if self.peek().token_type == IDENTIFIER && self.peek().lexeme == word {
+            self.advance();
+            true
+        } else {
+            false
+        }
+    }
+
     fn match_token(&mut self, typ: &TokenType) -> bool {
         if self.is_at_end() {
             false
@@ -261,11 +746,21 @@ This method returns the token just before the current one in a parser or tokeniz
         self.tokens[self.current.saturating_sub(1)].clone()
     }
 
+    /// True once every token has been consumed.
+    pub fn is_finished(&self) -> bool {
+        self.is_at_end()
+    }
+
     fn is_at_end(&self) -> bool {
         self.peek().token_type == EoF
     }
 
+    // Skips to the start of the next statement. Inside a block, the `}`
+    // that closes it is left for the block.
     fn synchronize(&mut self) {
+        if self.block_depth > 0 && self.check(RIGHT_BRACE) {
+            return;
+        }
         self.advance();
 
         while !self.is_at_end() {
@@ -274,7 +769,8 @@ This method returns the token just before the current one in a parser or tokeniz
             }
 
             match self.peek().token_type {
-                CLASS | FUN | VAR | FOR | IF | WHILE | PRINT | RETURN => return,
+                CLASS | FUN | VAR | FOR | IF | WHILE | PRINT | RETURN | THROW | TRY | IMPORT | EXPORT => return,
+                RIGHT_BRACE if self.block_depth > 0 => return,
                 _ => (),
             }
 
@@ -287,6 +783,7 @@ This method returns the token just before the current one in a parser or tokeniz
 mod tests {
     use super::*;
     use crate::scanner::LiteralValue::*;
+    use crate::scanner::Scanner;
 
     #[test]
     fn test_addition() {
@@ -296,24 +793,28 @@ mod tests {
                 lexeme: "1".to_string(),
                 literal: Some(IntValue(1)),
                 line_number: 0,
+                offset: 0,
             },
             Token {
                 token_type: PLUS,
                 lexeme: "+".to_string(),
                 literal: None,
                 line_number: 0,
+                offset: 0,
             },
             Token {
                 token_type: NUMBER,
                 lexeme: "2".to_string(),
                 literal: Some(IntValue(2)),
                 line_number: 0,
+                offset: 0,
             },
             Token {
                 token_type: SEMICOLON,
                 lexeme: ";".to_string(),
                 literal: None,
                 line_number: 0,
+                offset: 0,
             },
         ];
         let mut parser = Parser::new(tokens);
@@ -331,24 +832,28 @@ mod tests {
                 lexeme: "1".to_string(),
                 literal: Some(IntValue(1)),
                 line_number: 0,
+                offset: 0,
             },
             Token {
                 token_type: GREATER,
                 lexeme: ">".to_string(),
                 literal: None,
                 line_number: 0,
+                offset: 0,
             },
             Token {
                 token_type: NUMBER,
                 lexeme: "2".to_string(),
                 literal: Some(IntValue(2)),
                 line_number: 0,
+                offset: 0,
             },
             Token {
                 token_type: SEMICOLON,
                 lexeme: ";".to_string(),
                 literal: None,
                 line_number: 0,
+                offset: 0,
             },
         ];
         let mut parser = Parser::new(tokens);
@@ -364,7 +869,7 @@ mod tests {
         let tokens = scanner.scan_tokens().unwrap();
         let mut parser = Parser::new(tokens);
 
-        let mut parsed_expr = parser.expression().unwrap();
+        let parsed_expr = parser.expression().unwrap();
         let string_expr = parsed_expr.to_string();
 
         println!("{}", string_expr);
@@ -380,11 +885,45 @@ mod tests {
         let tokens = scanner.scan_tokens().unwrap();
         let mut parser = Parser::new(tokens);
 
-        let mut parsed_expr = parser.expression().unwrap();
+        let parsed_expr = parser.expression().unwrap();
         let string_expr = parsed_expr.to_string();
 
         println!("{}", string_expr);
 
         assert_eq!(string_expr, "(== 1 (group (+ 2 2)))");
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn parse_partial_recovers_inside_blocks() {
+        let source = "fun f() {\n    var x = ;\n    print x;\n}\nprint (1;\nreturn;\nprint 2;";
+        let tokens = Scanner::new(source).scan_tokens().unwrap();
+        let (stmts, errors) = Parser::new(tokens).parse_partial();
+
+        let printed: Vec<String> = stmts.iter().map(Stmt::to_string).collect();
+        assert_eq!(
+            printed,
+            vec![
+                "(fun f () (block (error \"Expected expression at line 2\"),(print (var x))))",
+                "(error \"Expected: ')' at line 5\")",
+                "(error \"Can't return from top-level code at line 6\")",
+                "(print 2)",
+            ]
+        );
+        assert_eq!(
+            errors,
+            vec![
+                "Expected expression at line 2",
+                "Expected: ')' at line 5",
+                "Can't return from top-level code at line 6",
+            ]
+        );
+        // The error statement holds the token the parser stopped at.
+        assert!(matches!(&stmts[1], Stmt::Error { token, .. } if token.lexeme == ";"));
+
+        // A `}` that closes a block is not skipped while recovering.
+        let tokens = Scanner::new("{ print (1 } print 2;").scan_tokens().unwrap();
+        let (stmts, errors) = Parser::new(tokens).parse_partial();
+        assert_eq!(errors.len(), 1);
+        assert_eq!(stmts.last().unwrap().to_string(), "(print 2)");
+    }
+}
@@ -0,0 +1,65 @@
+use std::fmt;
+
+use regex::Regex;
+use regex_syntax::ast::Span;
+
+/// A compiled regular expression, created with `regex.compile`. Two
+/// patterns are equal when they were compiled from the same source.
+pub struct Pattern {
+    pub regex: Regex,
+}
+
+impl Pattern {
+    /// Compiles `source`. Syntax errors say at which character of the
+    /// pattern they were found: "Invalid regex 'a(b' at position 1:
+    /// unclosed group".
+    pub fn new(source: &str) -> Result<Self, String> {
+        let invalid = |span: &Span, reason: &dyn fmt::Display| {
+            let position = source[..span.start.offset].chars().count();
+            format!("Invalid regex '{}' at position {}: {}", source, position, reason)
+        };
+        if let Err(err) = regex_syntax::Parser::new().parse(source) {
+            return Err(match &err {
+                regex_syntax::Error::Parse(err) => invalid(err.span(), err.kind()),
+                regex_syntax::Error::Translate(err) => invalid(err.span(), err.kind()),
+                _ => format!("Invalid regex '{}': {}", source, err),
+            });
+        }
+        // The pattern is valid, but can still be too big to compile.
+        Regex::new(source)
+            .map(|regex| Self { regex })
+            .map_err(|err| format!("Invalid regex '{}': {}", source, err))
+    }
+
+    pub fn source(&self) -> &str {
+        self.regex.as_str()
+    }
+}
+
+impl PartialEq for Pattern {
+    fn eq(&self, other: &Self) -> bool {
+        self.source() == other.source()
+    }
+}
+
+impl fmt::Debug for Pattern {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "<regex {}>", self.source())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn errors_point_at_the_offending_character() {
+        assert_eq!(
+            Pattern::new("ab(c").unwrap_err(),
+            "Invalid regex 'ab(c' at position 2: unclosed group"
+        );
+        assert!(Pattern::new("é[z-a]").unwrap_err().contains("at position 2:"));
+        assert!(Pattern::new(r"\p{Nope}").unwrap_err().contains("at position 0:"));
+        assert_eq!(Pattern::new("a+").unwrap(), Pattern::new("a+").unwrap());
+    }
+}
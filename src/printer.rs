@@ -0,0 +1,295 @@
+//! Prints syntax trees back as source code that parses to the same tree.
+//! Comments are not part of the tree and `for` loops come back as the
+//! `while` loops they stand for. Error nodes print as comments.
+
+use crate::expr::{Expr, LiteralValue};
+use crate::scanner::Token;
+use crate::stmt::Stmt;
+
+const INDENT: &str = "    ";
+
+/// A whole program, one statement per line.
+pub fn program(stmts: &[Stmt]) -> String {
+    let mut printer = Printer::default();
+    for stmt in stmts {
+        printer.stmt(stmt);
+        printer.out.push('\n');
+    }
+    printer.out
+}
+
+/// A function declaration, `fun name(params) { ... }`.
+pub fn function(name: &str, params: &[Token], body: &[Stmt]) -> String {
+    let mut printer = Printer::default();
+    printer.out.push_str("fun ");
+    printer.out.push_str(name);
+    printer.function_rest(params, body);
+    printer.out
+}
+
+/// An anonymous function expression, `fun (params) { ... }`.
+pub fn lambda(params: &[Token], body: &[Stmt]) -> String {
+    let mut printer = Printer::default();
+    printer.out.push_str("fun ");
+    printer.function_rest(params, body);
+    printer.out
+}
+
+#[derive(Default)]
+struct Printer {
+    out: String,
+    depth: usize,
+}
+
+impl Printer {
+    fn stmt(&mut self, stmt: &Stmt) {
+        match stmt {
+            Stmt::Expression { expression } => {
+                self.expr(expression);
+                self.out.push(';');
+            }
+            Stmt::Print { keyword: _, expression } => {
+                self.out.push_str("print ");
+                self.expr(expression);
+                self.out.push(';');
+            }
+            Stmt::Var { keyword: _, name, initialiser } => {
+                self.out.push_str("var ");
+                self.out.push_str(&name.lexeme);
+                if !matches!(initialiser, Expr::Literal { value: LiteralValue::Nil, .. }) {
+                    self.out.push_str(" = ");
+                    self.expr(initialiser);
+                }
+                self.out.push(';');
+            }
+            Stmt::Block { brace: _, statements } => self.block(statements),
+            Stmt::Function {
+                keyword: _,
+                name,
+                params,
+                body,
+            } => {
+                self.out.push_str("fun ");
+                self.out.push_str(&name.lexeme);
+                self.function_rest(params, body);
+            }
+            Stmt::If {
+                keyword: _,
+                condition,
+                then_branch,
+                else_branch,
+            } => {
+                self.out.push_str("if (");
+                self.expr(condition);
+                self.out.push_str(") ");
+                self.stmt(then_branch);
+                if let Some(else_branch) = else_branch {
+                    self.out.push_str(" else ");
+                    self.stmt(else_branch);
+                }
+            }
+            Stmt::While { keyword: _, condition, body } => {
+                self.out.push_str("while (");
+                self.expr(condition);
+                self.out.push_str(") ");
+                self.stmt(body);
+            }
+            Stmt::Return { keyword: _, value } => {
+                self.out.push_str("return");
+                if let Some(value) = value {
+                    self.out.push(' ');
+                    self.expr(value);
+                }
+                self.out.push(';');
+            }
+            Stmt::Throw { keyword: _, value } => {
+                self.out.push_str("throw ");
+                self.expr(value);
+                self.out.push(';');
+            }
+            Stmt::Import { keyword: _, path, alias } => {
+                self.out.push_str(&format!("import {} as {};", path.lexeme, alias.lexeme));
+            }
+            Stmt::ImportFrom { keyword: _, path, names } => {
+                let names: Vec<&str> = names.iter().map(|name| name.lexeme.as_str()).collect();
+                self.out.push_str(&format!("from {} import {};", path.lexeme, names.join(", ")));
+            }
+            Stmt::Export { keyword: _, declaration } => {
+                self.out.push_str("export ");
+                self.stmt(declaration);
+            }
+            Stmt::Try {
+                keyword: _,
+                body,
+                catch_clause,
+                finally_body,
+            } => {
+                self.out.push_str("try ");
+                self.block(body);
+                if let Some((name, statements)) = catch_clause {
+                    self.out.push_str(&format!(" catch ({}) ", name.lexeme));
+                    self.block(statements);
+                }
+                if let Some(statements) = finally_body {
+                    self.out.push_str(" finally ");
+                    self.block(statements);
+                }
+            }
+            // Error nodes only ever appear in statement lists, which put
+            // each statement on a line of its own.
+            Stmt::Error { token: _, message } => {
+                self.out.push_str("// error: ");
+                self.out.push_str(message);
+            }
+        }
+    }
+
+    fn block(&mut self, statements: &[Stmt]) {
+        if statements.is_empty() {
+            self.out.push_str("{}");
+            return;
+        }
+        self.out.push_str("{\n");
+        self.depth += 1;
+        for stmt in statements {
+            self.out.push_str(&INDENT.repeat(self.depth));
+            self.stmt(stmt);
+            self.out.push('\n');
+        }
+        self.depth -= 1;
+        self.out.push_str(&INDENT.repeat(self.depth));
+        self.out.push('}');
+    }
+
+    fn function_rest(&mut self, params: &[Token], body: &[Stmt]) {
+        let params: Vec<&str> = params.iter().map(|param| param.lexeme.as_str()).collect();
+        self.out.push_str(&format!("({}) ", params.join(", ")));
+        self.block(body);
+    }
+
+    fn expr(&mut self, expr: &Expr) {
+        match expr {
+            Expr::Assign { name, value } => {
+                self.out.push_str(&name.lexeme);
+                self.out.push_str(" = ");
+                self.expr(value);
+            }
+            Expr::Binary { left, operator, right } | Expr::Logical { left, operator, right } => {
+                self.expr(left);
+                self.out.push_str(&format!(" {} ", operator.lexeme));
+                self.expr(right);
+            }
+            Expr::Unary { operator, right } => {
+                self.out.push_str(&operator.lexeme);
+                self.expr(right);
+            }
+            Expr::Call { callee, paren: _, arguments } => {
+                self.expr(callee);
+                self.out.push('(');
+                self.list(arguments);
+                self.out.push(')');
+            }
+            Expr::Get { object, name } => {
+                self.expr(object);
+                self.out.push('.');
+                self.out.push_str(&name.lexeme);
+            }
+            Expr::Index { object, bracket: _, index } => {
+                self.expr(object);
+                self.out.push('[');
+                self.expr(index);
+                self.out.push(']');
+            }
+            Expr::SetIndex {
+                object,
+                bracket: _,
+                index,
+                value,
+            } => {
+                self.expr(object);
+                self.out.push('[');
+                self.expr(index);
+                self.out.push_str("] = ");
+                self.expr(value);
+            }
+            Expr::Lambda { keyword: _, params, body } => {
+                self.out.push_str("fun ");
+                self.function_rest(params, body);
+            }
+            Expr::List { bracket: _, elements } => {
+                self.out.push('[');
+                self.list(elements);
+                self.out.push(']');
+            }
+            Expr::Map { brace: _, entries } => {
+                self.out.push('{');
+                for (i, (key, value)) in entries.iter().enumerate() {
+                    if i > 0 {
+                        self.out.push_str(", ");
+                    }
+                    self.expr(key);
+                    self.out.push_str(": ");
+                    self.expr(value);
+                }
+                self.out.push('}');
+            }
+            Expr::Grouping { paren: _, expression } => {
+                self.out.push('(');
+                self.expr(expression);
+                self.out.push(')');
+            }
+            Expr::Literal { token: _, value } => match value {
+                LiteralValue::StringValue(s) => self.out.push_str(&format!("\"{}\"", s)),
+                other => self.out.push_str(&other.to_string()),
+            },
+            Expr::Variable { name } => self.out.push_str(&name.lexeme),
+        }
+    }
+
+    fn list(&mut self, exprs: &[Expr]) {
+        for (i, expr) in exprs.iter().enumerate() {
+            if i > 0 {
+                self.out.push_str(", ");
+            }
+            self.expr(expr);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::Parser;
+    use crate::scanner::Scanner;
+
+    fn parse(source: &str) -> Vec<Stmt> {
+        Parser::new(Scanner::new(source).scan_tokens().unwrap()).parse().unwrap()
+    }
+
+    #[test]
+    fn printed_programs_parse_to_the_same_tree() {
+        let source = r#"
+            import "lib/util.fnky" as util;
+            from "lib/util.fnky" import a, b;
+            var xs = [1, -2.5, "s", nil, {"k": !true, 3: (1 + 2) * 3}];
+            fun apply(f, x) {
+                if (x > 1 and x <= 10 or x == nil) return f(x); else { print x; }
+                while (x != 0) x = x - 1;
+                try { throw util.error; } catch (e) { xs[0] = e.message; } finally {}
+                return fun (y) { return xs[y].len(); };
+            }
+            for (var i = 0; i < 3; i = i + 1) print i;
+        "#;
+        let printed = program(&parse(source));
+        let reparsed = program(&parse(&printed));
+        assert_eq!(printed, reparsed);
+        // Assignments show their target token, position included.
+        let line_numbers = regex::Regex::new(r"(line_number|offset): \d+").unwrap();
+        let tree = |stmts: Vec<Stmt>| {
+            let printed: Vec<String> = stmts.iter().map(Stmt::to_string).collect();
+            line_numbers.replace_all(&printed.join("\n"), "").into_owned()
+        };
+        assert_eq!(tree(parse(source)), tree(parse(&printed)));
+        assert!(printed.contains("fun apply(f, x) {\n    if (x > 1 and x <= 10 or x == nil) return f(x); else {\n        print x;\n    }\n"));
+    }
+}
@@ -0,0 +1,231 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::lint::LintConfig;
+
+pub const MANIFEST_FILE: &str = "fnky.toml";
+
+/// A project described by a `fnky.toml` manifest:
+///
+/// ```toml
+/// [package]
+/// name = "demo"
+/// entry = "src/main.fnky"
+///
+/// [dependencies]
+/// strings = "../strings"
+/// shapes = { path = "vendor/shapes" }
+///
+/// [lint]
+/// shadowed-binding = "off"
+/// ```
+///
+/// Dependencies are local directories only; `import "strings/pad.fnky"`
+/// then resolves inside `../strings`. All paths are relative to the
+/// directory holding the manifest. The optional `[lint]` section configures
+/// `fnky lint`. `entry` is only needed by `fnky run`, so a manifest holding
+/// just lint settings is fine too.
+#[derive(Debug, PartialEq)]
+pub struct Manifest {
+    pub name: Option<String>,
+    pub entry: Option<PathBuf>,
+    pub dependencies: Vec<(String, PathBuf)>,
+    pub lint: LintConfig,
+}
+
+impl Manifest {
+    /// Finds the nearest `fnky.toml` in `start` or one of its ancestors.
+    pub fn find(start: &Path) -> Option<PathBuf> {
+        start
+            .ancestors()
+            .map(|dir| dir.join(MANIFEST_FILE))
+            .find(|candidate| candidate.is_file())
+    }
+
+    pub fn load(path: &Path) -> Result<Self, String> {
+        let source = fs::read_to_string(path)
+            .map_err(|err| format!("Could not read {}: {}", path.display(), err))?;
+        let dir = path.parent().unwrap_or(Path::new("."));
+        Self::parse(&source, dir).map_err(|msg| format!("{}: {}", path.display(), msg))
+    }
+
+    /// Parses the small subset of TOML a manifest needs: `[section]`
+    /// headers, `key = "string"` pairs, `key = { path = "..." }` inline
+    /// tables and `#` comments.
+    pub fn parse(source: &str, dir: &Path) -> Result<Self, String> {
+        let mut name = None;
+        let mut entry = None;
+        let mut dependencies = vec![];
+        let mut lint = LintConfig::default();
+        let mut section = String::new();
+
+        for (index, raw_line) in source.lines().enumerate() {
+            let line_number = index + 1;
+            let line = strip_comment(raw_line).trim();
+            if line.is_empty() {
+                continue;
+            }
+
+            if let Some(header) = line.strip_prefix('[') {
+                section = header
+                    .strip_suffix(']')
+                    .ok_or_else(|| format!("Expected ']' at line {}", line_number))?
+                    .trim()
+                    .to_string();
+                continue;
+            }
+
+            let (key, value) = line
+                .split_once('=')
+                .ok_or_else(|| format!("Expected 'key = value' at line {}", line_number))?;
+            let key = key.trim();
+            let value = value.trim();
+
+            match (section.as_str(), key) {
+                ("package", "name") => name = Some(parse_string(value, line_number)?),
+                ("package", "entry") => entry = Some(dir.join(parse_string(value, line_number)?)),
+                ("package", _) => return Err(format!("Unknown package key '{}' at line {}", key, line_number)),
+                ("dependencies", _) => {
+                    let path = parse_dependency(value, line_number)?;
+                    dependencies.push((key.to_string(), dir.join(path)));
+                }
+                ("lint", _) => lint
+                    .set_level(key, &parse_string(value, line_number)?)
+                    .map_err(|msg| format!("{} at line {}", msg, line_number))?,
+                _ => return Err(format!("Unknown section '[{}]' at line {}", section, line_number)),
+            }
+        }
+
+        Ok(Self {
+            name,
+            entry,
+            dependencies,
+            lint,
+        })
+    }
+}
+
+fn strip_comment(line: &str) -> &str {
+    let mut in_string = false;
+    for (index, ch) in line.char_indices() {
+        match ch {
+            '"' => in_string = !in_string,
+            '#' if !in_string => return &line[..index],
+            _ => (),
+        }
+    }
+    line
+}
+
+fn parse_string(value: &str, line_number: usize) -> Result<String, String> {
+    value
+        .strip_prefix('"')
+        .and_then(|rest| rest.strip_suffix('"'))
+        .map(str::to_string)
+        .ok_or_else(|| format!("Expected a quoted string at line {}", line_number))
+}
+
+// A dependency is either a path string or an inline table with a `path` key.
+fn parse_dependency(value: &str, line_number: usize) -> Result<String, String> {
+    let path = match value.strip_prefix('{').and_then(|rest| rest.strip_suffix('}')) {
+        Some(table) => {
+            let mut path = None;
+            for pair in table.split(',').map(str::trim).filter(|pair| !pair.is_empty()) {
+                match pair.split_once('=') {
+                    Some((key, value)) if key.trim() == "path" => path = Some(parse_string(value.trim(), line_number)?),
+                    Some((key, _)) => {
+                        return Err(format!(
+                            "Unsupported dependency key '{}' at line {}: only local 'path' dependencies are allowed",
+                            key.trim(),
+                            line_number
+                        ))
+                    }
+                    None => return Err(format!("Expected 'key = value' at line {}", line_number)),
+                }
+            }
+            path.ok_or_else(|| format!("Expected 'path' in dependency at line {}", line_number))?
+        }
+        None => parse_string(value, line_number)?,
+    };
+
+    if path.contains("://") {
+        return Err(format!(
+            "Dependency at line {} must be a local directory, not '{}'",
+            line_number, path
+        ));
+    }
+    Ok(path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lint::{Rule, Severity};
+    use crate::testing::TempDir;
+
+    #[test]
+    fn parse_manifest() {
+        let source = r#"
+            # demo project
+            [package]
+            name = "demo"
+            entry = "src/main.fnky"   # run by `fnky run`
+
+            [dependencies]
+            strings = "../strings"
+            shapes = { path = "vendor/shapes" }
+
+            [lint]
+            unused-variable = "error"
+        "#;
+        let manifest = Manifest::parse(source, Path::new("/work/demo")).unwrap();
+        let mut lint = LintConfig::default();
+        lint.set(Rule::UnusedVariable, Some(Severity::Error));
+        assert_eq!(
+            manifest,
+            Manifest {
+                name: Some("demo".to_string()),
+                entry: Some(PathBuf::from("/work/demo/src/main.fnky")),
+                dependencies: vec![
+                    ("strings".to_string(), PathBuf::from("/work/demo/../strings")),
+                    ("shapes".to_string(), PathBuf::from("/work/demo/vendor/shapes")),
+                ],
+                lint,
+            }
+        );
+    }
+
+    #[test]
+    fn reject_remote_dependencies() {
+        let git = "[package]\nentry = \"main.fnky\"\n[dependencies]\nx = { git = \"https://example.com/x\" }\n";
+        assert!(Manifest::parse(git, Path::new(".")).unwrap_err().contains("only local 'path'"));
+
+        let url = "[package]\nentry = \"main.fnky\"\n[dependencies]\nx = \"https://example.com/x\"\n";
+        assert!(Manifest::parse(url, Path::new(".")).unwrap_err().contains("must be a local directory"));
+    }
+
+    #[test]
+    fn reject_unknown_lint_rules() {
+        let source = "[package]\nentry = \"main.fnky\"\n[lint]\nunused = \"off\"\n";
+        assert_eq!(
+            Manifest::parse(source, Path::new(".")).unwrap_err(),
+            "Unknown lint rule 'unused' at line 4"
+        );
+    }
+
+    #[test]
+    fn entry_is_optional() {
+        let manifest = Manifest::parse("[lint]\nshadowed-binding = \"off\"\n", Path::new(".")).unwrap();
+        assert_eq!(manifest.entry, None);
+    }
+
+    #[test]
+    fn find_searches_ancestors() {
+        let root = TempDir::new("project-find");
+        let nested = root.join("src/deep");
+        fs::create_dir_all(&nested).unwrap();
+        fs::write(root.join(MANIFEST_FILE), "[package]\nentry = \"main.fnky\"\n").unwrap();
+
+        assert_eq!(Manifest::find(&nested), Some(root.join(MANIFEST_FILE)));
+    }
+}
@@ -0,0 +1,467 @@
+//! The interactive prompt `fnky` starts when it is given no script.
+
+use std::env;
+use std::fs;
+use std::path::PathBuf;
+use std::time::Instant;
+
+use rustyline::completion::Completer;
+use rustyline::config::{CompletionType, Config};
+use rustyline::error::ReadlineError;
+use rustyline::highlight::Highlighter;
+use rustyline::hint::Hinter;
+use rustyline::history::DefaultHistory;
+use rustyline::validate::{ValidationContext, ValidationResult, Validator};
+use rustyline::{Context, Editor, Helper};
+
+use crate::error::EvalError;
+use crate::expr::LiteralValue;
+use crate::interpreter::Interpreter;
+use crate::parser::Parser;
+use crate::scanner::{self, Scanner, TokenType};
+use crate::session;
+use crate::stdlib;
+use crate::stmt::Stmt;
+
+const PROMPT: &str = "> ";
+
+const HELP: &str = "\
+Enter code to run it; the value of a trailing expression is shown.
+  :help           show this message
+  :vars           list global variables
+  :type <expr>    show the type of an expression's value
+  :ast <code>     show how code parses
+  :tokens <code>  show how code scans
+  :load <file>    run a file in this session
+  :reset          start over with a fresh interpreter
+  :save <file>    save the global variables to a file
+  :restore <file> start over from a file written by :save
+  :time <code>    run code and show how long it took
+Ctrl-C discards the current input and Ctrl-D exits.";
+
+/// What handling one piece of input produced.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Reply {
+    /// Text to show, which may be empty.
+    Output(String),
+    Error(String),
+    /// A script called `os.exit`.
+    Exit(i32),
+}
+
+/// An interactive session: runs code in one interpreter so definitions
+/// carry over from one input to the next, and answers `:` commands.
+pub struct Repl {
+    interpreter: Interpreter,
+    // Builds the interpreter `:reset` switches to, configured like the
+    // first one.
+    fresh: Box<dyn Fn() -> Interpreter>,
+}
+
+impl Repl {
+    pub fn new(fresh: impl Fn() -> Interpreter + 'static) -> Self {
+        Self {
+            interpreter: fresh(),
+            fresh: Box::new(fresh),
+        }
+    }
+
+    /// Reads and handles input until the end of input (Ctrl-D) or until a
+    /// script calls `os.exit`, and returns the status the process should
+    /// exit with.
+    ///
+    /// Lines are edited with the usual readline keys and kept in a history
+    /// file, `~/.fnky_history` unless `FNKY_HISTORY` names another one.
+    /// Input with unclosed brackets or strings continues on the next line,
+    /// Ctrl-C discards the input typed so far, and Tab completes keywords,
+    /// global variables and, after a `.`, members.
+    pub fn run(&mut self) -> Result<i32, String> {
+        let config = Config::builder().completion_type(CompletionType::List).build();
+        let mut editor: Editor<InputHelper, DefaultHistory> =
+            Editor::with_config(config).map_err(|err| err.to_string())?;
+        editor.set_helper(Some(InputHelper { globals: vec![] }));
+        let history = history_path();
+        if let Some(path) = &history {
+            // There is no history yet on the first run.
+            let _ = editor.load_history(path);
+        }
+
+        let status = loop {
+            if let Some(helper) = editor.helper_mut() {
+                helper.globals = self.interpreter.globals();
+            }
+            let input = match editor.readline(PROMPT) {
+                Ok(input) => input,
+                Err(ReadlineError::Interrupted) => continue,
+                Err(ReadlineError::Eof) => break 0,
+                Err(err) => return Err(err.to_string()),
+            };
+            if input.trim().is_empty() {
+                continue;
+            }
+            let _ = editor.add_history_entry(input.as_str());
+
+            match self.handle(&input) {
+                Reply::Output(text) if text.is_empty() => (),
+                Reply::Output(text) => println!("{}", text),
+                Reply::Error(message) => eprintln!("{}", message),
+                Reply::Exit(code) => break code,
+            }
+        };
+
+        if let Some(path) = &history {
+            editor
+                .save_history(path)
+                .map_err(|err| format!("Could not save history to {}: {}", path.display(), err))?;
+        }
+        Ok(status)
+    }
+
+    /// Runs one complete piece of input: either code or a `:` command.
+    pub fn handle(&mut self, input: &str) -> Reply {
+        let input = input.trim();
+        let Some(command) = input.strip_prefix(':') else {
+            return self.eval(input);
+        };
+        let (name, argument) = match command.split_once(char::is_whitespace) {
+            Some((name, argument)) => (name, argument.trim()),
+            None => (command, ""),
+        };
+        let placeholder = match name {
+            "type" => Some("<expression>"),
+            "ast" | "tokens" | "time" => Some("<code>"),
+            "load" | "save" | "restore" => Some("<file>"),
+            _ => None,
+        };
+        if let Some(placeholder) = placeholder
+            && argument.is_empty()
+        {
+            return Reply::Error(format!("Usage: :{} {}", name, placeholder));
+        }
+
+        match name {
+            "help" => Reply::Output(HELP.to_string()),
+            "vars" => Reply::Output(
+                self.interpreter
+                    .user_globals()
+                    .iter()
+                    .map(|(name, value)| format!("{} = {}", name, value.repr()))
+                    .collect::<Vec<String>>()
+                    .join("\n"),
+            ),
+            "type" => match self.interpreter.eval(argument) {
+                Ok(value) => Reply::Output(value.to_type().to_string()),
+                Err(err) => error_reply(err),
+            },
+            "ast" => match ast(argument) {
+                Ok(tree) => Reply::Output(tree),
+                Err(message) => Reply::Error(message),
+            },
+            "tokens" => match Scanner::new(argument).scan_tokens() {
+                Ok(tokens) => Reply::Output(
+                    tokens
+                        .iter()
+                        .filter(|token| token.token_type != TokenType::EoF)
+                        .map(|token| token.to_string())
+                        .collect::<Vec<String>>()
+                        .join("\n"),
+                ),
+                Err(message) => Reply::Error(message.trim_end().to_string()),
+            },
+            "load" => match fs::read_to_string(argument) {
+                Ok(source) => self.eval(&source),
+                Err(err) => Reply::Error(format!("Could not read '{}': {}", argument, err)),
+            },
+            "reset" => {
+                self.interpreter = (self.fresh)();
+                Reply::Output(String::new())
+            }
+            "save" => {
+                let snapshot = session::snapshot(&self.interpreter);
+                let contents = format!("// Fnky session, load it with :restore\n{}", snapshot.source);
+                if let Err(err) = fs::write(argument, contents) {
+                    return Reply::Error(format!("Could not write '{}': {}", argument, err));
+                }
+                let count = snapshot.saved.len();
+                let noun = if count == 1 { "variable" } else { "variables" };
+                let mut lines = vec![format!("Saved {} {} to {}", count, noun, argument)];
+                for (name, reason) in snapshot.skipped {
+                    lines.push(format!("Skipped {}: {}", name, reason));
+                }
+                Reply::Output(lines.join("\n"))
+            }
+            // The current session is kept if the file fails to run.
+            "restore" => {
+                let source = match fs::read_to_string(argument) {
+                    Ok(source) => source,
+                    Err(err) => return Reply::Error(format!("Could not read '{}': {}", argument, err)),
+                };
+                let mut interpreter = (self.fresh)();
+                match interpreter.eval(&source) {
+                    Ok(_) => {
+                        self.interpreter = interpreter;
+                        Reply::Output(format!("Restored {}", argument))
+                    }
+                    Err(err) => error_reply(err),
+                }
+            }
+            "time" => {
+                let start = Instant::now();
+                let reply = self.eval(argument);
+                let elapsed = format!("({:.3} ms)", start.elapsed().as_secs_f64() * 1e3);
+                match reply {
+                    Reply::Output(text) if text.is_empty() => Reply::Output(elapsed),
+                    Reply::Output(text) => Reply::Output(format!("{}\n{}", text, elapsed)),
+                    other => other,
+                }
+            }
+            _ => Reply::Error(format!("Unknown command ':{}'. Type :help for a list of commands.", name)),
+        }
+    }
+
+    // Runs code and shows the value of its trailing expression, as its
+    // `repr` so strings show their quotes. Nil is not shown, so statements
+    // like `print` don't echo anything.
+    fn eval(&mut self, source: &str) -> Reply {
+        match self.interpreter.eval(source) {
+            Ok(LiteralValue::Nil) => Reply::Output(String::new()),
+            Ok(value) => Reply::Output(value.repr()),
+            Err(err) => error_reply(err),
+        }
+    }
+}
+
+fn error_reply(err: EvalError) -> Reply {
+    match err {
+        EvalError::Exit(code) => Reply::Exit(code),
+        err => Reply::Error(err.to_string()),
+    }
+}
+
+// The syntax tree of an expression, or of each statement of a program.
+fn ast(source: &str) -> Result<String, String> {
+    let tokens = Scanner::new(source)
+        .scan_tokens()
+        .map_err(|err| err.trim_end().to_string())?;
+    let mut parser = Parser::new(tokens.clone());
+    if let Ok(expression) = parser.expression()
+        && parser.is_finished()
+    {
+        return Ok(expression.to_string());
+    }
+    let stmts = Parser::new(tokens).parse()?;
+    Ok(stmts.iter().map(Stmt::to_string).collect::<Vec<String>>().join("\n"))
+}
+
+/// Completes the word that ends at `pos` in `line`, returning where the
+/// word starts and the names it could become. After `name.` those are the
+/// members of `name`, which may be a chain like `a.b` through modules;
+/// otherwise they are keywords and the names in `globals`.
+pub fn complete(line: &str, pos: usize, globals: &[(String, LiteralValue)]) -> (usize, Vec<String>) {
+    let before = &line[..pos];
+    let start = word_start(before, is_word_char);
+    let prefix = &before[start..];
+
+    let mut names: Vec<String> = match before[..start].strip_suffix('.') {
+        Some(object) => {
+            let path = &object[word_start(object, |c| is_word_char(c) || c == '.')..];
+            resolve(path, globals).map(|value| members(&value)).unwrap_or_default()
+        }
+        None => scanner::get_keywords_hashmap()
+            .into_keys()
+            .map(String::from)
+            .chain(globals.iter().map(|(name, _)| name.clone()))
+            .collect(),
+    };
+    names.retain(|name| name.starts_with(prefix));
+    names.sort();
+    names.dedup();
+    (start, names)
+}
+
+fn is_word_char(c: char) -> bool {
+    c.is_alphanumeric() || c == '_'
+}
+
+// Where the run of characters matching `part_of_word` at the end of `text`
+// begins.
+fn word_start(text: &str, part_of_word: impl Fn(char) -> bool) -> usize {
+    text.char_indices()
+        .rev()
+        .take_while(|&(_, c)| part_of_word(c))
+        .last()
+        .map_or(text.len(), |(index, _)| index)
+}
+
+// The value a dotted path of global variables and module members refers
+// to. Nothing is evaluated, so completing never runs code.
+pub(crate) fn resolve(path: &str, globals: &[(String, LiteralValue)]) -> Option<LiteralValue> {
+    let mut parts = path.split('.');
+    let first = parts.next()?;
+    let mut value = globals.iter().find(|(name, _)| name == first)?.1.clone();
+    for part in parts {
+        value = match &value {
+            LiteralValue::Module(module) => module.get(part)?,
+            _ => return None,
+        };
+    }
+    Some(value)
+}
+
+fn members(value: &LiteralValue) -> Vec<String> {
+    match value {
+        LiteralValue::Module(module) => module.exports.iter().cloned().collect(),
+        LiteralValue::Error(_) => ["kind", "message", "line", "trace"].map(String::from).to_vec(),
+        other => stdlib::method_names(other).iter().map(|name| name.to_string()).collect(),
+    }
+}
+
+fn history_path() -> Option<PathBuf> {
+    match env::var_os("FNKY_HISTORY") {
+        Some(path) => Some(PathBuf::from(path)),
+        None => env::home_dir().map(|home| home.join(".fnky_history")),
+    }
+}
+
+/// Whether `source` stops inside a string or with brackets left open, so
+/// the prompt should ask for another line. Stray closing brackets count as
+/// complete and are left for the parser to report.
+pub fn is_incomplete(source: &str) -> bool {
+    let mut depth = 0i64;
+    let mut chars = source.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '(' | '{' | '[' => depth += 1,
+            ')' | '}' | ']' => depth -= 1,
+            '"' if !chars.by_ref().any(|c| c == '"') => return true,
+            '/' if chars.peek() == Some(&'/') => {
+                chars.by_ref().find(|&c| c == '\n');
+            }
+            _ => (),
+        }
+    }
+    depth > 0
+}
+
+// Tells the line editor when to keep reading instead of submitting, and
+// what Tab can complete to.
+struct InputHelper {
+    // The interpreter's globals as of the start of the current input.
+    globals: Vec<(String, LiteralValue)>,
+}
+
+impl Validator for InputHelper {
+    fn validate(&self, ctx: &mut ValidationContext) -> rustyline::Result<ValidationResult> {
+        if is_incomplete(ctx.input()) {
+            Ok(ValidationResult::Incomplete)
+        } else {
+            Ok(ValidationResult::Valid(None))
+        }
+    }
+}
+
+impl Completer for InputHelper {
+    type Candidate = String;
+
+    fn complete(&self, line: &str, pos: usize, _: &Context<'_>) -> rustyline::Result<(usize, Vec<String>)> {
+        Ok(complete(line, pos, &self.globals))
+    }
+}
+
+impl Hinter for InputHelper {
+    type Hint = String;
+}
+
+impl Highlighter for InputHelper {}
+
+impl Helper for InputHelper {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::output::OutputBuffer;
+    use crate::testing::TempDir;
+
+    fn output(text: &str) -> Reply {
+        Reply::Output(text.to_string())
+    }
+
+    #[test]
+    fn unbalanced_input_continues() {
+        assert!(is_incomplete("fun f(x) {"));
+        assert!(is_incomplete("var xs = [1,\n2"));
+        assert!(is_incomplete("print \"still open"));
+        assert!(!is_incomplete("fun f(x) {\n  return x;\n}"));
+        assert!(!is_incomplete("print \"{\";"));
+        assert!(!is_incomplete("print 1; // ( not code"));
+        assert!(!is_incomplete("print 1);"));
+    }
+
+    #[test]
+    fn completes_keywords_globals_and_members() {
+        let mut interpreter = Interpreter::new();
+        interpreter.eval("var words = [\"a\"]; var whole = 1; var err = nil; try { throw \"x\"; } catch (e) { err = e; }").unwrap();
+        let globals = interpreter.globals();
+        let at_end = |line: &str| complete(line, line.len(), &globals);
+
+        assert_eq!(at_end("wh"), (0, vec!["while".to_string(), "whole".to_string()]));
+        assert_eq!(at_end("print ma"), (6, vec!["math".to_string()]));
+        assert_eq!(at_end("x = math.sq"), (9, vec!["sqrt".to_string()]));
+        assert_eq!(at_end("words.f").1, ["filter", "flat_map"]);
+        assert_eq!(at_end("err.m").1, ["message"]);
+        assert_eq!(at_end("whole.").1, Vec::<String>::new());
+        assert_eq!(at_end("missing.x").1, Vec::<String>::new());
+        assert!(at_end("").1.contains(&"print".to_string()));
+        assert_eq!(complete("math.sq + 1", 7, &globals), (5, vec!["sqrt".to_string()]));
+    }
+
+    #[test]
+    fn echoes_trailing_expressions() {
+        let printed = OutputBuffer::new();
+        let buffer = printed.clone();
+        let mut repl = Repl::new(move || {
+            let mut interpreter = Interpreter::new();
+            interpreter.set_output(buffer.clone());
+            interpreter
+        });
+        assert_eq!(repl.handle("1 + 2;"), output("3"));
+        assert_eq!(repl.handle("var name = \"fnky\";"), output(""));
+        assert_eq!(repl.handle("name"), output("\"fnky\""));
+        assert_eq!(repl.handle("print name;"), output(""));
+        assert_eq!(printed.contents(), "fnky\n");
+        assert!(matches!(repl.handle("missing;"), Reply::Error(message) if message.starts_with("NameError")));
+        assert_eq!(repl.handle("os.exit(4);"), Reply::Exit(4));
+    }
+
+    #[test]
+    fn meta_commands() {
+        let mut repl = Repl::new(Interpreter::new);
+        repl.handle("var b = [1]; var a = \"x\"; fun f() {}");
+        assert_eq!(repl.handle(":vars"), output("a = \"x\"\nb = [1]\nf = <fn f>"));
+        assert_eq!(repl.handle(":type b"), output("List"));
+        assert_eq!(repl.handle(":ast 1 + 2 * x"), output("(+ 1 (* 2 (var x)))"));
+        assert_eq!(repl.handle(":tokens a["), output("IDENTIFIER a None\nLEFT_BRACKET [ None"));
+        assert!(matches!(repl.handle(":time 40 + 2"), Reply::Output(text) if text.starts_with("42\n(") && text.ends_with(" ms)")));
+        assert_eq!(repl.handle(":type"), Reply::Error("Usage: :type <expression>".to_string()));
+        assert!(matches!(repl.handle(":load /nonexistent/file.fnky"), Reply::Error(_)));
+        assert!(matches!(repl.handle(":frobnicate"), Reply::Error(_)));
+
+        let dir = TempDir::new("repl");
+        let file = dir.join("session.fnks");
+        let file = file.display();
+        repl.handle("var lines = io.read_lines(\"Cargo.toml\");");
+        assert_eq!(
+            repl.handle(&format!(":save {}", file)),
+            output(&format!("Saved 3 variables to {}\nSkipped lines: Iterator values can't be saved", file))
+        );
+        repl.handle("a = 1;");
+        assert_eq!(repl.handle(&format!(":restore {}", file)), output(&format!("Restored {}", file)));
+        assert_eq!(repl.handle("[a, b, f]"), output("[\"x\", [1], <fn f>]"));
+
+        repl.handle(":reset");
+        assert_eq!(repl.handle(":vars"), output(""));
+        repl.handle("var only = 1;");
+        assert_eq!(repl.handle(&format!(":save {}", file)), output(&format!("Saved 1 variable to {}", file)));
+        assert!(matches!(repl.handle(":help"), Reply::Output(text) if text.contains(":reset")));
+    }
+}
@@ -0,0 +1,170 @@
+use std::env;
+use std::path::{Component, Path, PathBuf};
+
+use crate::error::RuntimeError;
+
+/// Which files a script may touch.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Access {
+    All,
+    Nothing,
+    /// Only paths inside one of these directories.
+    Within(Vec<PathBuf>),
+}
+
+/// What scripts may do outside the interpreter through the standard
+/// library. Hosts embedding untrusted scripts can start from
+/// [`Sandbox::locked`] and open up what they need. Imports are not
+/// affected; they are governed by the module search paths.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Sandbox {
+    pub read: Access,
+    pub write: Access,
+    pub stdin: bool,
+    /// Reading environment variables and the working directory.
+    pub env: bool,
+    /// Running other programs and ending the process with `os.exit`.
+    pub process: bool,
+}
+
+impl Sandbox {
+    /// Everything is allowed. This is what the `fnky` command uses.
+    pub fn unrestricted() -> Self {
+        Self {
+            read: Access::All,
+            write: Access::All,
+            stdin: true,
+            env: true,
+            process: true,
+        }
+    }
+
+    /// Nothing is allowed.
+    pub fn locked() -> Self {
+        Self {
+            read: Access::Nothing,
+            write: Access::Nothing,
+            stdin: false,
+            env: false,
+            process: false,
+        }
+    }
+
+    pub fn check_read(&self, path: &Path) -> Result<(), RuntimeError> {
+        check(&self.read, path, "read")
+    }
+
+    pub fn check_write(&self, path: &Path) -> Result<(), RuntimeError> {
+        check(&self.write, path, "write")
+    }
+
+    pub fn check_stdin(&self) -> Result<(), RuntimeError> {
+        if self.stdin {
+            Ok(())
+        } else {
+            Err(permission_error("Reading standard input is not allowed".to_string()))
+        }
+    }
+
+    pub fn check_env(&self) -> Result<(), RuntimeError> {
+        if self.env {
+            Ok(())
+        } else {
+            Err(permission_error("Reading the environment is not allowed".to_string()))
+        }
+    }
+
+    pub fn check_process(&self) -> Result<(), RuntimeError> {
+        if self.process {
+            Ok(())
+        } else {
+            Err(permission_error("Running or exiting processes is not allowed".to_string()))
+        }
+    }
+}
+
+impl Default for Sandbox {
+    fn default() -> Self {
+        Self::unrestricted()
+    }
+}
+
+pub fn permission_error(message: String) -> RuntimeError {
+    RuntimeError::new("PermissionError", message, 0)
+}
+
+fn check(access: &Access, path: &Path, action: &str) -> Result<(), RuntimeError> {
+    let allowed = match access {
+        Access::All => true,
+        Access::Nothing => false,
+        Access::Within(roots) => {
+            let path = resolve(path);
+            roots.iter().any(|root| path.starts_with(resolve(root)))
+        }
+    };
+    if allowed {
+        Ok(())
+    } else {
+        Err(permission_error(format!(
+            "Not allowed to {} '{}'",
+            action,
+            path.display()
+        )))
+    }
+}
+
+// An absolute path without `.`, `..` or symlinks, so `allowed/../secret`
+// cannot escape a root. Paths that do not exist yet (files about to be
+// written) are resolved through their closest existing ancestor.
+fn resolve(path: &Path) -> PathBuf {
+    let absolute = env::current_dir().unwrap_or_default().join(path);
+    let mut existing = absolute.as_path();
+    let mut rest = vec![];
+    loop {
+        if let Ok(canonical) = existing.canonicalize() {
+            let mut resolved = canonical;
+            for component in rest.iter().rev() {
+                match component {
+                    Component::ParentDir => {
+                        resolved.pop();
+                    }
+                    Component::Normal(name) => resolved.push(name),
+                    _ => (),
+                }
+            }
+            return resolved;
+        }
+        match (existing.parent(), existing.components().next_back()) {
+            (Some(parent), Some(last)) => {
+                rest.push(last);
+                existing = parent;
+            }
+            _ => return absolute,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::TempDir;
+    use std::fs;
+
+    #[test]
+    fn within_rejects_paths_escaping_the_root() {
+        let root = TempDir::new("sandbox");
+        fs::create_dir_all(root.join("data")).unwrap();
+        let sandbox = Sandbox {
+            read: Access::Within(vec![root.join("data")]),
+            ..Sandbox::locked()
+        };
+
+        assert!(sandbox.check_read(&root.join("data/new/file.txt")).is_ok());
+        assert!(sandbox.check_read(&root.join("data/../secret.txt")).is_err());
+        assert!(sandbox.check_read(&root.join("data/missing/../../secret.txt")).is_err());
+        let err = sandbox.check_write(&root.join("data/out.txt")).unwrap_err();
+        assert_eq!(err.kind, "PermissionError");
+        assert!(Sandbox::unrestricted().check_stdin().is_ok());
+        assert!(Sandbox::locked().check_stdin().is_err());
+    }
+}
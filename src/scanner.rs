@@ -1,38 +1,41 @@
 use std::collections::HashMap;
-use std::fmt::{self, format};
+use std::fmt;
 use std::string::String;
 
 fn is_digit(ch: char) -> bool {
-    return ch as u8 >= '0' as u8 && ch as u8 <= '9' as u8;
+    ch.is_ascii_digit()
 }
 
 fn is_alpha(ch: char) -> bool {
-    let uch = ch as u8;
-    return (uch >= 'a' as u8 && uch <= 'z' as u8)
-        || (uch >= 'A' as u8 && uch <= 'Z' as u8)
-        || (ch == '_');
+    ch.is_ascii_alphabetic() || ch == '_'
 }
 
 fn is_alpha_numeric(ch: char) -> bool {
     is_alpha(ch) || is_digit(ch)
 }
 
-fn get_keywords_hashmap() -> HashMap<&'static str, TokenType> {
+pub fn get_keywords_hashmap() -> HashMap<&'static str, TokenType> {
     HashMap::from([
         ("and", AND),
+        ("catch", CATCH),
         ("class", CLASS),
         ("else", ELSE),
+        ("export", EXPORT),
         ("false", FALSE),
+        ("finally", FINALLY),
         ("for", FOR),
         ("fun", FUN),
         ("if", IF),
+        ("import", IMPORT),
         ("nil", NIL),
         ("or", OR),
         ("print", PRINT),
         ("return", RETURN),
         ("super", SUPER),
         ("this", THIS),
+        ("throw", THROW),
         ("true", TRUE),
+        ("try", TRY),
         ("var", VAR),
         ("while", WHILE),
     ])
@@ -44,6 +47,11 @@ pub struct Scanner {
     start: usize,
     current: usize,
     line: u64,
+    // Whether `//` comments become COMMENT tokens instead of being skipped.
+    keep_comments: bool,
+    // Whether whitespace becomes WHITESPACE tokens and unreadable text
+    // UNKNOWN tokens instead of being skipped or reported.
+    keep_trivia: bool,
 
     keywords: HashMap<&'static str, TokenType>,
 }
@@ -57,11 +65,33 @@ impl Scanner {
             start: 0,       //starting index is 0 for now
             current: 0,     //current indesx is also 9
             line: 1,        //line indexing starts from 1
+            keep_comments: false,
+            keep_trivia: false,
             keywords: get_keywords_hashmap(), //all the keywords that needs to be recoginised by the interpreter for now
         }
     }
 
-    pub fn scan_tokens(self: &mut Self) -> Result<Vec<Token>, String> {
+    /// A scanner that keeps `//` comments as COMMENT tokens, for tools such
+    /// as the formatter. The parser does not accept them.
+    pub fn with_comments(source: &str) -> Self {
+        Self {
+            keep_comments: true,
+            ..Self::new(source)
+        }
+    }
+
+    /// A scanner whose tokens cover every byte of the source: besides
+    /// comments it keeps each run of whitespace as a WHITESPACE token, and
+    /// text it cannot scan becomes an UNKNOWN token instead of an error.
+    pub fn lossless(source: &str) -> Self {
+        Self {
+            keep_comments: true,
+            keep_trivia: true,
+            ..Self::new(source)
+        }
+    }
+
+    pub fn scan_tokens(&mut self) -> Result<Vec<Token>, String> {
         //from the source strinf we need to extract the tokens and retuiirn them as vector in cxase of success and a string error in case of failure
         let mut errors = vec![]; //lets initialise a vector of strings which wuill store our errors
 
@@ -71,6 +101,7 @@ impl Scanner {
             match self.scan_token() {
                 //at the end (when we encounter \n)
                 Ok(_) => (),
+                Err(_) if self.keep_trivia => self.add_token(UNKNOWN),
                 Err(msg) => errors.push(msg),
             }
         }
@@ -80,14 +111,15 @@ impl Scanner {
             lexeme: "".to_string(),
             literal: None,
             line_number: self.line,
+            offset: self.source.len(),
         });
 
-        if errors.len() > 0 {
+        if !errors.is_empty() {
             //lets retirn all the errors stored in the errors vector by cocating as a string with line gap of 1 between each error
             let mut joined = "".to_string();
             for error in errors {
                 joined.push_str(&error);
-                joined.push_str("\n");
+                joined.push('\n');
             }
             return Err(joined);
         }
@@ -106,7 +138,10 @@ impl Scanner {
             ')' => self.add_token(TokenType::RIGHT_PAREN),
             '{' => self.add_token(TokenType::LEFT_BRACE),
             '}' => self.add_token(TokenType::RIGHT_BRACE),
+            '[' => self.add_token(TokenType::LEFT_BRACKET),
+            ']' => self.add_token(TokenType::RIGHT_BRACKET),
             ',' => self.add_token(TokenType::COMMA),
+            ':' => self.add_token(TokenType::COLON),
             '.' => self.add_token(TokenType::DOT),
             '+' => self.add_token(TokenType::PLUS),
             '-' => self.add_token(TokenType::MINUS),
@@ -152,12 +187,26 @@ impl Scanner {
                         }
                         self.advance();
                     }
+                    if self.keep_comments {
+                        self.add_token(COMMENT);
+                    }
                 } else {
                     self.add_token(SLASH);
                 }
             }
-            ' ' | '\r' | '\t' => {}
-            '\n' => self.line += 1,
+            ' ' | '\r' | '\t' | '\n' => {
+                if c == '\n' {
+                    self.line += 1;
+                }
+                if self.keep_trivia {
+                    while matches!(self.peek(), ' ' | '\r' | '\t' | '\n') {
+                        if self.advance() == '\n' {
+                            self.line += 1;
+                        }
+                    }
+                    self.add_token(WHITESPACE);
+                }
+            }
 
             '"' => self.string()?,
             c => {
@@ -187,7 +236,7 @@ impl Scanner {
         Ok(())
     }
 
-    fn number(self: &mut Self) -> Result<(), String> {
+    fn number(&mut self) -> Result<(), String> {
         while is_digit(self.peek()) {
             self.advance();
         }
@@ -205,22 +254,19 @@ impl Scanner {
         let value = substring.parse::<f64>();
 
         match value {
-            Ok(value) => self.add_token_lit(NUMBER, Some(FValue(value))),
-            Err(_) => return Err("Failed to parse number".to_string()),
+            Ok(value) if value.is_finite() => self.add_token_lit(NUMBER, Some(FValue(value))),
+            Ok(_) => return Err(format!("Number is too large at line {}", self.line)),
+            Err(_) => return Err(format!("Failed to parse number at line {}", self.line)),
         }
 
         Ok(())
     }
 
-    fn peek_next(self: &mut Self) -> char {
-        if self.current >= self.source.len() {
-            return '\0';
-        }
-
-        self.source.chars().nth(self.current + 1).unwrap()
+    fn peek_next(&mut self) -> char {
+        self.source[self.current..].chars().nth(1).unwrap_or('\0')
     }
 
-    fn string(self: &mut Self) -> Result<(), String> {
+    fn string(&mut self) -> Result<(), String> {
         // "ajajnka"
         while self.peek() != '"' && !self.is_at_end() {
             if self.peek() == '\n' {
@@ -241,31 +287,32 @@ impl Scanner {
         Ok(())
     }
 
-    fn peek(self: &Self) -> char {
+    fn peek(&self) -> char {
         if self.is_at_end() {
             //checks if we are at the end of input string if so return a null character
             return '\0';
         }
-        self.source.chars().nth(self.current).unwrap() //returns the current character
+        self.source[self.current..].chars().next().unwrap() //returns the current character
     }
 
-    fn char_match(self: &mut Self, ch: char) -> bool {
+    fn char_match(&mut self, ch: char) -> bool {
         if self.is_at_end() {
             //if we reach end without charecter marching
             return false;
         }
-        if self.source.chars().nth(self.current).unwrap() != ch {
-            return false; //if it does not match return false
+        if self.peek() != ch {
+            false //if it does not match return false
         } else {
-            self.current += 1; //if matched increase the current index and return true
-            return true;
+            self.current += ch.len_utf8(); //if matched increase the current index and return true
+            true
         }
     }
 
     fn advance(&mut self) -> char {
         //self.source.chars() converts self.source (which is string or &str) into a iterator of characters
-        let c = self.source.chars().nth(self.current).unwrap_or('\0');
-        self.current += 1; //advancing the current index
+        // `current` is a byte offset, so step over the whole UTF-8 sequence
+        let c = self.source[self.current..].chars().next().unwrap_or('\0');
+        self.current += c.len_utf8(); //advancing the current index
 
         c
     }
@@ -282,15 +329,20 @@ impl Scanner {
             lexeme: text,
             literal,
             line_number: self.line,
+            offset: self.start,
         });
     }
 }
 #[derive(Debug, Copy, Clone, PartialEq)]
+#[allow(non_camel_case_types, clippy::upper_case_acronyms)]
 pub enum TokenType {
     LEFT_PAREN,
     RIGHT_PAREN,
     LEFT_BRACE,
     RIGHT_BRACE,
+    LEFT_BRACKET,
+    RIGHT_BRACKET,
+    COLON,
     COMMA,
     DOT,
     MINUS,
@@ -309,20 +361,29 @@ pub enum TokenType {
     IDENTIFIER,
     STRING,
     NUMBER,
+    COMMENT,
+    WHITESPACE,
+    UNKNOWN,
     AND,
+    CATCH,
     CLASS,
     ELSE,
+    EXPORT,
     FALSE,
+    FINALLY,
     FUN,
     FOR,
     IF,
+    IMPORT,
     NIL,
     OR,
     PRINT,
     RETURN,
     SUPER,
     THIS,
+    THROW,
     TRUE,
+    TRY,
     VAR,
     WHILE,
     EoF,
@@ -338,6 +399,7 @@ impl std::fmt::Display for TokenType {
 
 //make a enum to define the datatypes of the values which could be entered in the input string
 #[derive(Debug, Clone)]
+#[allow(dead_code)]
 pub enum LiteralValue {
     IntValue(i64),
     FValue(f64),
@@ -353,23 +415,29 @@ pub struct Token {
     pub lexeme: String, //it is just the string part of the token which stores the contents
     pub literal: Option<LiteralValue>, //either Some of Nonec (in case of we have not defined themm in the enum variants)
     pub line_number: u64,
+    // The byte offset of the lexeme in the source.
+    pub offset: usize,
 }
 
+#[allow(dead_code)]
 impl Token {
     pub fn new(
         token_type: TokenType,
         lexeme: String,
         literal: Option<LiteralValue>,
         line_number: u64,
+        offset: usize,
     ) -> Self {
         Self {
             token_type,
             lexeme,
             literal,
             line_number,
+            offset,
         }
     }
 
+    #[allow(clippy::inherent_to_string)]
     pub fn to_string(&self) -> String {
         format!("{} {} {:?}", self.token_type, self.lexeme, self.literal)
     }
@@ -383,7 +451,7 @@ mod tests {
     fn handle_one_char_tokens() {
         let source = "((  ))";
         let mut scanner = Scanner::new(source);
-        scanner.scan_tokens();
+        scanner.scan_tokens().unwrap();
         println!("{:?}", scanner.tokens);
         assert_eq!(scanner.tokens.len(), 5);
         assert_eq!(scanner.tokens[0].token_type, LEFT_PAREN);
@@ -397,7 +465,7 @@ mod tests {
     fn handle_two_char_tokens() {
         let source = "! != == >=";
         let mut scanner = Scanner::new(source);
-        scanner.scan_tokens();
+        scanner.scan_tokens().unwrap();
         println!("{:?}", scanner.tokens);
         assert_eq!(scanner.tokens.len(), 5);
         assert_eq!(scanner.tokens[0].token_type, BANG);
@@ -448,6 +516,21 @@ mod tests {
         }
     }
 
+    #[test]
+    fn handle_unicode_string_lit() {
+        let source = "var s = \"héllo wörld 🎉\"; [s]";
+        let mut scanner = Scanner::new(source);
+        scanner.scan_tokens().expect("Failed to scan tokens");
+        assert_eq!(scanner.tokens.len(), 9);
+        assert_eq!(scanner.tokens[5].token_type, LEFT_BRACKET);
+        assert_eq!(scanner.tokens[7].token_type, RIGHT_BRACKET);
+
+        match scanner.tokens[3].literal.as_ref().unwrap() {
+            StringValue(val) => assert_eq!(val, "héllo wörld 🎉"),
+            _ => panic!("Incorrect literal type"),
+        }
+    }
+
     #[test]
     fn number_literals() {
         let source = "123.123\n121.0\n5"; // Corrected input
@@ -469,6 +552,8 @@ mod tests {
             FValue(val) => assert_eq!(*val, 5.0),
             _ => panic!("Incorrect literal type"),
         }
+        let huge = "9".repeat(400);
+        assert!(Scanner::new(&huge).scan_tokens().unwrap_err().contains("Number is too large at line 1"));
     }
 
     #[test]
@@ -506,4 +591,45 @@ mod tests {
         assert_eq!(scanner.tokens[11].token_type, SEMICOLON);
         assert_eq!(scanner.tokens[12].token_type, EoF);
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn keep_comments() {
+        let source = "x = 1; // one\n// two\n";
+        assert_eq!(Scanner::new(source).scan_tokens().unwrap().len(), 5);
+
+        let tokens = Scanner::with_comments(source).scan_tokens().unwrap();
+        assert_eq!(tokens.len(), 7);
+        assert_eq!(tokens[4].token_type, COMMENT);
+        assert_eq!(tokens[4].lexeme, "// one");
+        assert_eq!(tokens[5].lexeme, "// two");
+        assert_eq!(tokens[5].line_number, 2);
+    }
+
+    #[test]
+    fn lossless_tokens_cover_the_source() {
+        let source = "var x = 1; // one\n\t@ \"open";
+        let tokens = Scanner::lossless(source).scan_tokens().unwrap();
+        let kinds: Vec<TokenType> = tokens.iter().map(|token| token.token_type).collect();
+        assert_eq!(
+            kinds,
+            vec![
+                VAR, WHITESPACE, IDENTIFIER, WHITESPACE, EQUAL, WHITESPACE, NUMBER, SEMICOLON, WHITESPACE, COMMENT,
+                WHITESPACE, UNKNOWN, WHITESPACE, UNKNOWN, EoF
+            ]
+        );
+        let text: String = tokens.iter().map(|token| token.lexeme.as_str()).collect();
+        assert_eq!(text, source);
+        assert_eq!(tokens[10].line_number, 2);
+    }
+
+    #[test]
+    fn token_offsets() {
+        let source = "var x = \"é\nb\";\ny";
+        let tokens = Scanner::new(source).scan_tokens().unwrap();
+        let offsets: Vec<usize> = tokens.iter().map(|token| token.offset).collect();
+        assert_eq!(offsets, [0, 4, 6, 8, 14, 16, 17]);
+        for token in &tokens {
+            assert_eq!(&source[token.offset..token.offset + token.lexeme.len()], token.lexeme);
+        }
+    }
+}
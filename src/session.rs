@@ -0,0 +1,290 @@
+//! Saving an interpreter's global variables as a script that recreates
+//! them, for the REPL's `:save` and `:restore`.
+
+use std::collections::HashMap;
+use std::rc::Rc;
+
+use crate::expr::LiteralValue;
+use crate::interpreter::Interpreter;
+use crate::printer;
+
+/// The global variables of an interpreter written out as Fnky source:
+/// running `source` in a fresh interpreter defines them again.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Snapshot {
+    pub source: String,
+    pub saved: Vec<String>,
+    /// Variables that could not be written out, with the reason.
+    pub skipped: Vec<(String, String)>,
+}
+
+/// Writes out the variables scripts defined. Functions are saved as their
+/// source and imported modules as the import that loaded them. A list or
+/// map that is the value of a variable is written once, and every other
+/// variable holding it, whole or inside its own value, refers to that
+/// variable, so the restored variables share it too; such variables are
+/// written first. Values that only exist at runtime, such as native
+/// functions, iterators and closures over local variables, are skipped, as
+/// are values sharing a list or map nested inside another variable's value.
+pub fn snapshot(interpreter: &Interpreter) -> Snapshot {
+    let globals = interpreter.user_globals();
+    let mut owners: Vec<(*const (), String)> = vec![];
+    for (name, value) in &globals {
+        if let Some(id) = shared_id(value)
+            && !owners.iter().any(|(owner, _)| *owner == id)
+        {
+            owners.push((id, name.clone()));
+        }
+    }
+    let mut writer = Writer {
+        interpreter,
+        globals: &globals,
+        owners,
+        parts: vec![],
+        states: HashMap::new(),
+        snapshot: Snapshot {
+            source: String::new(),
+            saved: vec![],
+            skipped: vec![],
+        },
+    };
+    for (name, _) in &globals {
+        let _ = writer.write(name);
+    }
+    writer.snapshot
+}
+
+// The identity of a list or map, which can be shared between values.
+fn shared_id(value: &LiteralValue) -> Option<*const ()> {
+    match value {
+        LiteralValue::List(items) => Some(Rc::as_ptr(items) as *const ()),
+        LiteralValue::Map(map) => Some(Rc::as_ptr(map) as *const ()),
+        _ => None,
+    }
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum State {
+    Writing,
+    Saved,
+    Skipped,
+}
+
+struct Writer<'a> {
+    interpreter: &'a Interpreter,
+    globals: &'a [(String, LiteralValue)],
+    // Each list or map that is the value of a variable, with the first
+    // such variable, which writes it out.
+    owners: Vec<(*const (), String)>,
+    // The lists and maps written out as part of a variable's value, with
+    // that variable.
+    parts: Vec<(*const (), String)>,
+    states: HashMap<String, State>,
+    snapshot: Snapshot,
+}
+
+impl Writer<'_> {
+    // Writes the declaration of the global `name` unless that was done
+    // already, and returns whether it was saved.
+    fn write(&mut self, name: &str) -> State {
+        if let Some(&state) = self.states.get(name) {
+            return state;
+        }
+        let globals = self.globals;
+        let Some((_, value)) = globals.iter().find(|(global, _)| global == name) else {
+            return State::Skipped;
+        };
+        self.states.insert(name.to_string(), State::Writing);
+        let state = match self.declaration(name, value) {
+            Ok(declaration) => {
+                self.snapshot.source.push_str(&declaration);
+                self.snapshot.source.push('\n');
+                self.snapshot.saved.push(name.to_string());
+                State::Saved
+            }
+            Err(reason) => {
+                self.parts.retain(|(_, variable)| variable != name);
+                self.snapshot.skipped.push((name.to_string(), reason));
+                State::Skipped
+            }
+        };
+        self.states.insert(name.to_string(), state);
+        state
+    }
+
+    fn declaration(&mut self, name: &str, value: &LiteralValue) -> Result<String, String> {
+        match value {
+            LiteralValue::Function(function)
+                if function.name.lexeme == name && self.interpreter.is_global_scope(&function.closure) =>
+            {
+                Ok(printer::function(name, &function.params, &function.body))
+            }
+            LiteralValue::Module(module) if module.path.is_absolute() => {
+                Ok(format!("import {} as {};", string_literal(&module.path.display().to_string()), name))
+            }
+            value => Ok(format!("var {} = {};", name, self.expression(value, name, &mut vec![])?)),
+        }
+    }
+
+    // An expression evaluating to a copy of `value`, the value of `variable`
+    // or part of it. `open` holds the lists and maps being written, to stop
+    // at ones that contain themselves.
+    fn expression(&mut self, value: &LiteralValue, variable: &str, open: &mut Vec<*const ()>) -> Result<String, String> {
+        Ok(match value {
+            LiteralValue::Number(x) if x.is_nan() => "math.NAN".to_string(),
+            LiteralValue::Number(x) if x.is_infinite() => if *x > 0.0 { "math.INF" } else { "-math.INF" }.to_string(),
+            LiteralValue::Number(x) => x.to_string(),
+            LiteralValue::StringValue(s) => string_literal(s),
+            LiteralValue::True | LiteralValue::False | LiteralValue::Nil => value.to_string(),
+            LiteralValue::List(items) => {
+                let id = Rc::as_ptr(items) as *const ();
+                if let Some(reference) = self.shared(id, variable, open)? {
+                    return Ok(reference);
+                }
+                open.push(id);
+                let items = items
+                    .borrow()
+                    .iter()
+                    .map(|item| self.expression(item, variable, open))
+                    .collect::<Result<Vec<String>, String>>()?;
+                open.pop();
+                format!("[{}]", items.join(", "))
+            }
+            LiteralValue::Map(map) => {
+                let id = Rc::as_ptr(map) as *const ();
+                if let Some(reference) = self.shared(id, variable, open)? {
+                    return Ok(reference);
+                }
+                open.push(id);
+                let entries = map
+                    .borrow()
+                    .iter()
+                    .map(|(key, value)| {
+                        Ok(format!(
+                            "{}: {}",
+                            self.expression(&key.to_value(), variable, open)?,
+                            self.expression(value, variable, open)?
+                        ))
+                    })
+                    .collect::<Result<Vec<String>, String>>()?;
+                open.pop();
+                format!("{{{}}}", entries.join(", "))
+            }
+            LiteralValue::Date(date) => format!("time.parse(\"{}\")", date),
+            LiteralValue::Regex(pattern) => format!("regex.compile({})", string_literal(pattern.source())),
+            LiteralValue::Function(function) if self.interpreter.is_global_scope(&function.closure) => {
+                printer::lambda(&function.params, &function.body)
+            }
+            LiteralValue::Function(function) => {
+                return Err(format!("function '{}' uses local variables", function.name.lexeme));
+            }
+            LiteralValue::Module(module) if !module.path.is_absolute() => module.name(),
+            other => return Err(format!("{} values can't be saved", other.to_type())),
+        })
+    }
+
+    // Checks the list or map `id` before `variable` writes it out. One that
+    // is another variable's value is referred to by that variable's name,
+    // which is written out first. Otherwise it is written in place, unless
+    // it contains itself or was already written as part of a value.
+    fn shared(&mut self, id: *const (), variable: &str, open: &[*const ()]) -> Result<Option<String>, String> {
+        if open.contains(&id) {
+            return Err("it contains itself".to_string());
+        }
+        if let Some((_, owner)) = self.owners.iter().find(|(owner, _)| *owner == id)
+            && !(owner == variable && open.is_empty())
+        {
+            let owner = owner.clone();
+            return match self.write(&owner) {
+                State::Saved => Ok(Some(owner)),
+                State::Writing => Err(format!("it and '{}' contain each other", owner)),
+                State::Skipped => Err(format!("it shares a list or map with '{}', which was skipped", owner)),
+            };
+        }
+        match self.parts.iter().find(|(part, _)| *part == id) {
+            Some((_, earlier)) if earlier == variable => Err("it holds the same list or map twice".to_string()),
+            Some((_, earlier)) => Err(format!("it shares a list or map with '{}'", earlier)),
+            None => {
+                self.parts.push((id, variable.to_string()));
+                Ok(None)
+            }
+        }
+    }
+}
+
+// String literals have no escapes, so strings containing a quote are built
+// from their character codes instead.
+fn string_literal(s: &str) -> String {
+    if s.contains('"') {
+        let codes: Vec<String> = s.chars().map(|c| u32::from(c).to_string()).collect();
+        format!("string.from_codes([{}])", codes.join(", "))
+    } else {
+        format!("\"{}\"", s)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn restoring_a_snapshot_recreates_the_globals() {
+        let mut interpreter = Interpreter::new();
+        let source = r#"
+            var data = {"quoted": string.from_codes([34, 104, 105, 34]), 2: [1.5, -3, nil, true], "when": time.date(2024, 3, 1)};
+            var limits = [math.INF, -math.INF];
+            fun twice(x) {
+                if (x > 10) { return x; }
+                return x * factor;
+            }
+            var factor = 2;
+            var double = fun (x) { return twice(x); };
+            var m = math;
+            fun counter() { var n = 0; return fun () { n = n + 1; return n; }; }
+            var next = counter();
+            var lines = [io.read_lines("Cargo.toml")];
+        "#;
+        interpreter.eval(source).unwrap();
+        let saved = snapshot(&interpreter);
+        assert_eq!(saved.saved, ["counter", "data", "double", "factor", "limits", "m", "twice"]);
+        assert_eq!(
+            saved.skipped,
+            [
+                ("lines".to_string(), "Iterator values can't be saved".to_string()),
+                ("next".to_string(), "function '<anonymous>' uses local variables".to_string()),
+            ]
+        );
+
+        let mut restored = Interpreter::new();
+        restored.eval(&saved.source).unwrap();
+        let check = "[data, limits, double(4), twice(20), m.sqrt(factor * 8), counter()()]";
+        assert_eq!(restored.eval(check).unwrap().to_string(), interpreter.eval(check).unwrap().to_string());
+        assert_eq!(snapshot(&restored).source, saved.source);
+    }
+
+    #[test]
+    fn shared_lists_and_maps_stay_shared() {
+        let mut interpreter = Interpreter::new();
+        let source = r#"
+            var xs = [1];
+            var ys = xs;
+            var zs = {"a": xs, "b": xs};
+            var b = [[2]];
+            var c = [b[0]];
+            var later = [3];
+            var a = {"k": later};
+        "#;
+        interpreter.eval(source).unwrap();
+        let saved = snapshot(&interpreter);
+        assert_eq!(
+            saved.source,
+            "var later = [3];\nvar a = {\"k\": later};\nvar b = [[2]];\nvar xs = [1];\nvar ys = xs;\nvar zs = {\"a\": xs, \"b\": xs};\n"
+        );
+        assert_eq!(saved.skipped, [("c".to_string(), "it shares a list or map with 'b'".to_string())]);
+
+        let mut restored = Interpreter::new();
+        restored.eval(&saved.source).unwrap();
+        restored.eval("ys.push(2); later.push(4);").unwrap();
+        assert_eq!(restored.eval("[xs, zs.get(\"b\"), a.get(\"k\")]").unwrap().to_string(), "[[1, 2], [1, 2], [3, 4]]");
+    }
+}
@@ -0,0 +1,186 @@
+use std::fs::{self, OpenOptions};
+use std::io::{BufRead, BufReader, Write};
+use std::path::Path;
+use std::rc::Rc;
+
+use crate::callable::Arity;
+use crate::convert::{argument, IntoValue};
+use crate::error::RuntimeError;
+use crate::expr::LiteralValue;
+use crate::iterator::NativeIterator;
+use crate::stdlib::ModuleBuilder;
+
+/// The `io` module: files, directories and standard input. Every function
+/// checks the interpreter's [`Sandbox`](crate::Sandbox) first, and failures
+/// raise catchable `IOError`s (or `PermissionError`s). Relative paths are
+/// relative to the working directory.
+pub fn module() -> LiteralValue {
+    ModuleBuilder::new("io")
+        .native("read_file", Arity::Exact(1), |interpreter, args| {
+            let path: String = argument("io.read_file", 0, &args[0])?;
+            interpreter.sandbox().check_read(Path::new(&path))?;
+            let contents = fs::read_to_string(&path).map_err(|err| io_error("read", &path, err))?;
+            Ok(contents.into_value())
+        })
+        .native("write_file", Arity::Exact(2), |interpreter, args| {
+            let path: String = argument("io.write_file", 0, &args[0])?;
+            let contents: String = argument("io.write_file", 1, &args[1])?;
+            interpreter.sandbox().check_write(Path::new(&path))?;
+            fs::write(&path, contents).map_err(|err| io_error("write", &path, err))?;
+            Ok(LiteralValue::Nil)
+        })
+        .native("append_file", Arity::Exact(2), |interpreter, args| {
+            let path: String = argument("io.append_file", 0, &args[0])?;
+            let contents: String = argument("io.append_file", 1, &args[1])?;
+            interpreter.sandbox().check_write(Path::new(&path))?;
+            OpenOptions::new()
+                .create(true)
+                .append(true)
+                .open(&path)
+                .and_then(|mut file| file.write_all(contents.as_bytes()))
+                .map_err(|err| io_error("append to", &path, err))?;
+            Ok(LiteralValue::Nil)
+        })
+        // Lines are read from the file as the iterator is advanced, so large
+        // files are never held in memory at once.
+        .native("read_lines", Arity::Exact(1), |interpreter, args| {
+            let path: String = argument("io.read_lines", 0, &args[0])?;
+            interpreter.sandbox().check_read(Path::new(&path))?;
+            let file = fs::File::open(&path).map_err(|err| io_error("read", &path, err))?;
+            let lines = BufReader::new(file).lines().map(move |line| {
+                line.map(IntoValue::into_value)
+                    .map_err(|err| io_error("read", &path, err))
+            });
+            Ok(LiteralValue::Iterator(Rc::new(NativeIterator::new("lines", lines))))
+        })
+        .native("exists", Arity::Exact(1), |interpreter, args| {
+            let path: String = argument("io.exists", 0, &args[0])?;
+            interpreter.sandbox().check_read(Path::new(&path))?;
+            Ok(Path::new(&path).exists().into_value())
+        })
+        // The names of the entries of a directory, sorted.
+        .native("list_dir", Arity::Exact(1), |interpreter, args| {
+            let path: String = argument("io.list_dir", 0, &args[0])?;
+            interpreter.sandbox().check_read(Path::new(&path))?;
+            let mut names = fs::read_dir(&path)
+                .and_then(|entries| {
+                    entries
+                        .map(|entry| entry.map(|entry| entry.file_name().to_string_lossy().to_string()))
+                        .collect::<Result<Vec<String>, _>>()
+                })
+                .map_err(|err| io_error("list", &path, err))?;
+            names.sort();
+            Ok(names.into_value())
+        })
+        // Reads a line from standard input, after printing the optional
+        // prompt. Returns nil at the end of the input.
+        .native("input", Arity::Range(0, 1), |interpreter, args| {
+            interpreter.sandbox().check_stdin()?;
+            if let Some(prompt) = args.first() {
+                let prompt: String = argument("io.input", 0, prompt)?;
+                interpreter
+                    .write_output(&prompt)
+                    .map_err(|err| RuntimeError::new("IOError", format!("Could not write prompt: {}", err), 0))?;
+            }
+            let line = interpreter
+                .read_input_line()
+                .map_err(|err| RuntimeError::new("IOError", format!("Could not read input: {}", err), 0))?;
+            Ok(line.into_value())
+        })
+        .build()
+}
+
+fn io_error(action: &str, path: &str, err: std::io::Error) -> RuntimeError {
+    RuntimeError::new("IOError", format!("Could not {} '{}': {}", action, path, err), 0)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+    use std::io::Cursor;
+
+    use crate::error::EvalError;
+    use crate::expr::LiteralValue;
+    use crate::interpreter::Interpreter;
+    use crate::output::OutputBuffer;
+    use crate::sandbox::{Access, Sandbox};
+    use crate::testing::TempDir;
+
+    fn error_kind(result: Result<LiteralValue, EvalError>) -> String {
+        match result {
+            Err(EvalError::Runtime(err)) => err.kind,
+            other => panic!("expected a runtime error, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn files_round_trip() {
+        let dir = TempDir::new("io-files");
+        let mut interpreter = Interpreter::new();
+        interpreter.set_global("dir", dir.to_str().unwrap());
+        let source = r#"
+            var path = dir + "/notes.txt";
+            io.write_file(path, "one
+");
+            io.append_file(path, "two
+three");
+            io.write_file(dir + "/b.txt", "");
+            var lines = io.read_lines(path);
+            var first = lines.next();
+            [first, lines.collect(), lines.next(), io.read_file(path).len(), io.exists(path), io.list_dir(dir)];
+        "#;
+        assert_eq!(
+            interpreter.eval(source).unwrap().to_string(),
+            r#"["one", ["two", "three"], nil, 13, true, ["b.txt", "notes.txt"]]"#
+        );
+    }
+
+    #[test]
+    fn failures_are_catchable() {
+        let dir = TempDir::new("io-failures");
+        let mut interpreter = Interpreter::new();
+        interpreter.set_global("missing", dir.join("missing.txt").to_str().unwrap());
+        let source = r#"
+            var kind = nil;
+            try { io.read_file(missing); } catch (e) { kind = e.kind; }
+            kind;
+        "#;
+        assert_eq!(interpreter.eval(source).unwrap(), LiteralValue::StringValue("IOError".to_string()));
+        assert_eq!(error_kind(interpreter.eval("io.read_lines(missing)")), "IOError");
+        assert_eq!(error_kind(interpreter.eval("io.list_dir(missing)")), "IOError");
+    }
+
+    #[test]
+    fn sandbox_limits_paths_and_stdin() {
+        let dir = TempDir::new("io-sandbox");
+        fs::create_dir_all(dir.join("public")).unwrap();
+        fs::write(dir.join("public/ok.txt"), "fine").unwrap();
+        fs::write(dir.join("secret.txt"), "hidden").unwrap();
+
+        let mut interpreter = Interpreter::new();
+        interpreter.set_sandbox(Sandbox {
+            read: Access::Within(vec![dir.join("public")]),
+            ..Sandbox::locked()
+        });
+        interpreter.set_global("dir", dir.to_str().unwrap());
+
+        assert_eq!(interpreter.eval(r#"io.read_file(dir + "/public/ok.txt")"#).unwrap().to_string(), "fine");
+        assert_eq!(error_kind(interpreter.eval(r#"io.read_file(dir + "/public/../secret.txt")"#)), "PermissionError");
+        assert_eq!(error_kind(interpreter.eval(r#"io.write_file(dir + "/public/new.txt", "x")"#)), "PermissionError");
+        assert_eq!(error_kind(interpreter.eval("io.input()")), "PermissionError");
+        assert!(!dir.join("public/new.txt").exists());
+    }
+
+    #[test]
+    fn input_reads_lines_after_a_prompt() {
+        let output = OutputBuffer::new();
+        let mut interpreter = Interpreter::new();
+        interpreter.set_output(output.clone());
+        interpreter.set_input(Cursor::new("Ada\r\nlast"));
+
+        assert_eq!(interpreter.eval(r#"io.input("name? ")"#).unwrap().to_string(), "Ada");
+        assert_eq!(interpreter.eval("io.input()").unwrap().to_string(), "last");
+        assert_eq!(interpreter.eval("io.input()").unwrap(), LiteralValue::Nil);
+        assert_eq!(output.contents(), "name? ");
+    }
+}
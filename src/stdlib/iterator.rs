@@ -0,0 +1,35 @@
+use std::rc::Rc;
+
+use crate::callable::Arity;
+use crate::error::RuntimeError;
+use crate::expr::LiteralValue;
+use crate::interpreter::Interpreter;
+use crate::iterator::NativeIterator;
+use crate::stdlib::{bind, lookup, Methods};
+
+/// The methods of iterators and their arities. `method` looks names up here
+/// and completion lists them.
+pub const METHODS: Methods<Rc<NativeIterator>> = &[
+    ("next", Arity::Exact(0), next),
+    ("collect", Arity::Exact(0), collect),
+];
+
+/// Looks up the method `name` of an iterator: `next()` returns the next
+/// item, or nil once the iterator is exhausted, and `collect()` gathers the
+/// remaining items into a list.
+pub fn method(receiver: &Rc<NativeIterator>, name: &str) -> Option<LiteralValue> {
+    let (arity, method) = lookup(METHODS, name)?;
+    Some(bind(format!("Iterator.{}", name), receiver.clone(), arity, method))
+}
+
+fn next(_: &mut Interpreter, iterator: &Rc<NativeIterator>, _: &[LiteralValue]) -> Result<LiteralValue, RuntimeError> {
+    iterator.next().unwrap_or(Ok(LiteralValue::Nil))
+}
+
+fn collect(_: &mut Interpreter, iterator: &Rc<NativeIterator>, _: &[LiteralValue]) -> Result<LiteralValue, RuntimeError> {
+    let mut items = vec![];
+    while let Some(item) = iterator.next() {
+        items.push(item?);
+    }
+    Ok(LiteralValue::list(items))
+}
@@ -0,0 +1,74 @@
+use crate::callable::Arity;
+use crate::convert::{argument, IntoValue};
+use crate::error::RuntimeError;
+use crate::expr::LiteralValue;
+use crate::json;
+use crate::stdlib::ModuleBuilder;
+
+/// The `json` module: `json.parse(text)` and `json.stringify(value, indent?)`.
+/// Invalid JSON raises a `ValueError` naming the line and column.
+pub fn module() -> LiteralValue {
+    ModuleBuilder::new("json")
+        .function("parse", |text: String| {
+            json::parse(&text).map_err(|message| RuntimeError::new("ValueError", message, 0))
+        })
+        .native("stringify", Arity::Range(1, 2), |_, args| {
+            let indent: Option<usize> = match args.get(1) {
+                Some(indent) => argument("json.stringify", 1, indent)?,
+                None => None,
+            };
+            Ok(json::stringify(&args[0], indent)?.into_value())
+        })
+        .build()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::error::EvalError;
+    use crate::expr::LiteralValue;
+    use crate::interpreter::Interpreter;
+
+    #[test]
+    fn parse_and_stringify_from_scripts() {
+        let mut interpreter = Interpreter::new();
+        interpreter.set_global("text", r#"{"port": 8080, "hosts": ["a", "b"]}"#);
+        let source = r#"
+            var config = json.parse(text);
+            config["port"] = config["port"] + 1;
+            config["hosts"].push("c");
+            json.stringify(config);
+        "#;
+        assert_eq!(
+            interpreter.eval(source).unwrap().to_string(),
+            r#"{"port":8081,"hosts":["a","b","c"]}"#
+        );
+        assert_eq!(
+            interpreter.eval(r#"json.stringify([1, {}], 1)"#).unwrap().to_string(),
+            "[\n 1,\n {}\n]"
+        );
+    }
+
+    #[test]
+    fn errors_are_catchable() {
+        let kind = |source: &str| match Interpreter::new().eval(source) {
+            Err(EvalError::Runtime(err)) => err.kind,
+            other => panic!("expected a runtime error, got {:?}", other),
+        };
+        assert_eq!(kind(r#"json.parse("[1,")"#), "ValueError");
+        assert_eq!(kind("json.stringify([math.sqrt])"), "TypeError");
+        assert_eq!(kind("fun f() {} json.stringify({\"f\": f});"), "TypeError");
+        assert_eq!(kind("var xs = []; xs.push(xs); json.stringify(xs);"), "ValueError");
+        assert_eq!(kind("json.stringify(1, -1)"), "TypeError");
+
+        let mut interpreter = Interpreter::new();
+        let source = r#"
+            var message = nil;
+            try { json.parse("{]"); } catch (e) { message = e.message; }
+            message;
+        "#;
+        assert_eq!(
+            interpreter.eval(source).unwrap(),
+            LiteralValue::StringValue("Invalid JSON at line 1, column 2: expected a string key".to_string())
+        );
+    }
+}
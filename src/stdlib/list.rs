@@ -0,0 +1,336 @@
+use std::cell::RefCell;
+use std::cmp::Ordering;
+use std::rc::Rc;
+
+use crate::callable::Arity;
+use crate::convert::IntoValue;
+use crate::error::RuntimeError;
+use crate::expr::LiteralValue;
+use crate::interpreter::Interpreter;
+use crate::map::{Key, Map};
+use crate::stdlib::{bind, lookup, Methods};
+
+type Items = Rc<RefCell<Vec<LiteralValue>>>;
+
+/// The methods of lists and their arities. `method` looks names up here
+/// and completion lists them.
+pub const METHODS: Methods<Items> = &[
+    ("len", Arity::Exact(0), len),
+    ("push", Arity::Exact(1), push),
+    ("pop", Arity::Exact(0), pop),
+    ("map", Arity::Exact(1), map),
+    ("filter", Arity::Exact(1), filter),
+    ("reduce", Arity::Range(1, 2), reduce),
+    ("sort", Arity::Range(0, 1), sort),
+    ("reverse", Arity::Exact(0), reverse),
+    ("zip", Arity::Exact(1), zip),
+    ("enumerate", Arity::Exact(0), enumerate),
+    ("any", Arity::Exact(1), any),
+    ("all", Arity::Exact(1), all),
+    ("flat_map", Arity::Exact(1), flat_map),
+    ("group_by", Arity::Exact(1), group_by),
+];
+
+/// Looks up the method `name` of the list `receiver`, as in `xs.push(1)`.
+///
+/// Lists are shared by reference: `push` and `pop` modify the list and are
+/// seen through every variable holding it. The higher-order methods (`map`,
+/// `filter`, `sort`, ...) leave the list alone and return a new one. Their
+/// callbacks can be any function, including closures and natives.
+pub fn method(receiver: &Items, name: &str) -> Option<LiteralValue> {
+    let (arity, method) = lookup(METHODS, name)?;
+    Some(bind(format!("List.{}", name), receiver.clone(), arity, method))
+}
+
+type MethodResult = Result<LiteralValue, RuntimeError>;
+
+// Callbacks may modify the list they iterate over, so they run over a copy
+// of its items rather than while it is borrowed.
+fn snapshot(items: &Items) -> Vec<LiteralValue> {
+    items.borrow().clone()
+}
+
+fn len(_: &mut Interpreter, items: &Items, _: &[LiteralValue]) -> MethodResult {
+    Ok(items.borrow().len().into_value())
+}
+
+fn push(_: &mut Interpreter, items: &Items, args: &[LiteralValue]) -> MethodResult {
+    items.borrow_mut().push(args[0].clone());
+    Ok(LiteralValue::Nil)
+}
+
+fn pop(_: &mut Interpreter, items: &Items, _: &[LiteralValue]) -> MethodResult {
+    items
+        .borrow_mut()
+        .pop()
+        .ok_or_else(|| RuntimeError::new("IndexError", "List.pop() on an empty list".to_string(), 0))
+}
+
+fn map(interpreter: &mut Interpreter, items: &Items, args: &[LiteralValue]) -> MethodResult {
+    let mut result = vec![];
+    for item in snapshot(items) {
+        result.push(interpreter.call(&args[0], vec![item], 0)?);
+    }
+    Ok(result.into_value())
+}
+
+fn filter(interpreter: &mut Interpreter, items: &Items, args: &[LiteralValue]) -> MethodResult {
+    let mut result = vec![];
+    for item in snapshot(items) {
+        if !interpreter.call(&args[0], vec![item.clone()], 0)?.is_falsy() {
+            result.push(item);
+        }
+    }
+    Ok(result.into_value())
+}
+
+// Folds the list from the left. Without an initial value the first item is
+// used, which makes reducing an empty list an error.
+fn reduce(interpreter: &mut Interpreter, items: &Items, args: &[LiteralValue]) -> MethodResult {
+    let mut items = snapshot(items).into_iter();
+    let mut accumulator = match args.get(1) {
+        Some(initial) => initial.clone(),
+        None => items.next().ok_or_else(|| {
+            RuntimeError::new(
+                "ValueError",
+                "List.reduce() of an empty list needs an initial value".to_string(),
+                0,
+            )
+        })?,
+    };
+    for item in items {
+        accumulator = interpreter.call(&args[0], vec![accumulator, item], 0)?;
+    }
+    Ok(accumulator)
+}
+
+// A stable sort. Without a comparator, lists of numbers or of strings sort
+// in ascending order. A comparator `fun (a, b)` returns a negative number
+// when `a` goes first, a positive one when `b` does, and 0 when they are
+// equal.
+fn sort(interpreter: &mut Interpreter, items: &Items, args: &[LiteralValue]) -> MethodResult {
+    let sorted = match args.first() {
+        Some(comparator) => merge_sort(snapshot(items), &mut |a, b| {
+            match interpreter.call(comparator, vec![a.clone(), b.clone()], 0)? {
+                LiteralValue::Number(x) => Ok(x.partial_cmp(&0.0).unwrap_or(Ordering::Equal)),
+                other => Err(RuntimeError::type_error(
+                    format!("List.sort() comparator must return a Number, not {}", other.to_type()),
+                    0,
+                )),
+            }
+        })?,
+        None => merge_sort(snapshot(items), &mut natural_order)?,
+    };
+    Ok(sorted.into_value())
+}
+
+fn natural_order(a: &LiteralValue, b: &LiteralValue) -> Result<Ordering, RuntimeError> {
+    match (a, b) {
+        (LiteralValue::Number(x), LiteralValue::Number(y)) => Ok(x.total_cmp(y)),
+        (LiteralValue::StringValue(x), LiteralValue::StringValue(y)) => Ok(x.cmp(y)),
+        _ => Err(RuntimeError::type_error(
+            format!("List.sort() cannot compare {} and {} without a comparator", a.to_type(), b.to_type()),
+            0,
+        )),
+    }
+}
+
+// The comparison can fail or be inconsistent (it may be a script function),
+// so this sorts with a merge sort that stops at the first error instead of
+// relying on `slice::sort_by`, which expects a total order.
+fn merge_sort(
+    mut items: Vec<LiteralValue>,
+    compare: &mut dyn FnMut(&LiteralValue, &LiteralValue) -> Result<Ordering, RuntimeError>,
+) -> Result<Vec<LiteralValue>, RuntimeError> {
+    if items.len() <= 1 {
+        return Ok(items);
+    }
+    let right = items.split_off(items.len() / 2);
+    let left = merge_sort(items, compare)?;
+    let right = merge_sort(right, compare)?;
+
+    let mut merged = Vec::with_capacity(left.len() + right.len());
+    let mut left = left.into_iter().peekable();
+    let mut right = right.into_iter().peekable();
+    while let (Some(a), Some(b)) = (left.peek(), right.peek()) {
+        // Taking from the left on ties keeps the sort stable.
+        if compare(b, a)? == Ordering::Less {
+            merged.extend(right.next());
+        } else {
+            merged.extend(left.next());
+        }
+    }
+    merged.extend(left);
+    merged.extend(right);
+    Ok(merged)
+}
+
+fn reverse(_: &mut Interpreter, items: &Items, _: &[LiteralValue]) -> MethodResult {
+    let mut reversed = snapshot(items);
+    reversed.reverse();
+    Ok(reversed.into_value())
+}
+
+// Pairs up items of both lists, stopping at the end of the shorter one.
+fn zip(_: &mut Interpreter, items: &Items, args: &[LiteralValue]) -> MethodResult {
+    let other = match &args[0] {
+        LiteralValue::List(other) => snapshot(other),
+        other => {
+            return Err(RuntimeError::type_error(
+                format!("List.zip() argument 1 must be List, not {}", other.to_type()),
+                0,
+            ))
+        }
+    };
+    Ok(snapshot(items)
+        .into_iter()
+        .zip(other)
+        .map(|(a, b)| vec![a, b])
+        .collect::<Vec<_>>()
+        .into_value())
+}
+
+// A list of `[index, item]` pairs.
+fn enumerate(_: &mut Interpreter, items: &Items, _: &[LiteralValue]) -> MethodResult {
+    Ok(snapshot(items)
+        .into_iter()
+        .enumerate()
+        .map(|(index, item)| vec![index.into_value(), item])
+        .collect::<Vec<_>>()
+        .into_value())
+}
+
+fn any(interpreter: &mut Interpreter, items: &Items, args: &[LiteralValue]) -> MethodResult {
+    for item in snapshot(items) {
+        if !interpreter.call(&args[0], vec![item], 0)?.is_falsy() {
+            return Ok(LiteralValue::True);
+        }
+    }
+    Ok(LiteralValue::False)
+}
+
+fn all(interpreter: &mut Interpreter, items: &Items, args: &[LiteralValue]) -> MethodResult {
+    for item in snapshot(items) {
+        if interpreter.call(&args[0], vec![item], 0)?.is_falsy() {
+            return Ok(LiteralValue::False);
+        }
+    }
+    Ok(LiteralValue::True)
+}
+
+fn flat_map(interpreter: &mut Interpreter, items: &Items, args: &[LiteralValue]) -> MethodResult {
+    let mut result = vec![];
+    for item in snapshot(items) {
+        match interpreter.call(&args[0], vec![item], 0)? {
+            LiteralValue::List(inner) => result.extend(snapshot(&inner)),
+            other => {
+                return Err(RuntimeError::type_error(
+                    format!("List.flat_map() callback must return a List, not {}", other.to_type()),
+                    0,
+                ))
+            }
+        }
+    }
+    Ok(result.into_value())
+}
+
+// A map from each key the callback returns to the list of items that
+// produced it, in order of first appearance.
+fn group_by(interpreter: &mut Interpreter, items: &Items, args: &[LiteralValue]) -> MethodResult {
+    let mut groups = Map::new();
+    for item in snapshot(items) {
+        let key = interpreter.call(&args[0], vec![item.clone()], 0)?;
+        let key = Key::new(&key).map_err(|message| RuntimeError::type_error(message, 0))?;
+        match groups.get(&key) {
+            Some(LiteralValue::List(group)) => group.borrow_mut().push(item),
+            _ => groups.insert(key, LiteralValue::list(vec![item])),
+        }
+    }
+    Ok(LiteralValue::map(groups))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::error::EvalError;
+    use crate::expr::LiteralValue;
+    use crate::interpreter::Interpreter;
+
+    fn eval(source: &str) -> String {
+        Interpreter::new().eval(source).unwrap().to_string()
+    }
+
+    #[test]
+    fn lists_are_shared_and_mutable() {
+        let mut interpreter = Interpreter::new();
+        let source = r#"
+            var xs = [1, "two", [3]];
+            var ys = xs;
+            ys.push(4);
+            xs[0] = xs.len();
+            var last = xs.pop();
+            xs;
+        "#;
+        assert_eq!(interpreter.eval(source).unwrap().to_string(), r#"[4, "two", [3]]"#);
+        assert_eq!(interpreter.eval("last").unwrap(), LiteralValue::Number(4.0));
+        assert_eq!(interpreter.eval("[] == [] and [1, [2]] == [1, [2]]").unwrap(), LiteralValue::True);
+    }
+
+    #[test]
+    fn lists_that_contain_themselves() {
+        let source = "var a = [1]; a.push(a); var b = [1]; b.push(b); [a == a, a == b, a == [1, a], a == [1, 2]];";
+        assert_eq!(eval(source), "[true, true, true, false]");
+        assert_eq!(eval("var xs = [1]; xs.push([xs]); xs;"), "[1, [[...]]]");
+    }
+
+    #[test]
+    fn higher_order_methods_call_back_into_scripts() {
+        assert_eq!(eval("[1, 2, 3].map(fun (x) { return x * 10; })"), "[10, 20, 30]");
+        assert_eq!(eval("[1, 2, 3, 4].filter(fun (x) { return x > 2; })"), "[3, 4]");
+        assert_eq!(eval("[1, 2, 3].reduce(fun (a, b) { return a + b; })"), "6");
+        assert_eq!(eval(r#"["a", "b"].reduce(fun (a, b) { return a + b; }, ">")"#), ">ab");
+        assert_eq!(eval("[1, 2].reverse()"), "[2, 1]");
+        assert_eq!(eval(r#"[1, 2, 3].zip(["a", "b"])"#), r#"[[1, "a"], [2, "b"]]"#);
+        assert_eq!(eval(r#"["x", "y"].enumerate()"#), r#"[[0, "x"], [1, "y"]]"#);
+        assert_eq!(eval("[1, 3].any(fun (x) { return x > 2; })"), "true");
+        assert_eq!(eval("[1, 3].all(fun (x) { return x > 2; })"), "false");
+        assert_eq!(eval("[1, 2].flat_map(fun (x) { return [x, x]; })"), "[1, 1, 2, 2]");
+        assert_eq!(
+            eval(r#"["apple", "avocado", "banana"].group_by(fun (s) { return s.substring(0, 1); })"#),
+            r#"{"a": ["apple", "avocado"], "b": ["banana"]}"#
+        );
+
+        let source = r#"
+            var offset = 100;
+            fun shift(x) { return x + offset; }
+            [1, 2].map(shift);
+        "#;
+        assert_eq!(eval(source), "[101, 102]");
+    }
+
+    #[test]
+    fn sort_is_stable_and_takes_a_comparator() {
+        assert_eq!(eval("[3, 1, 2].sort()"), "[1, 2, 3]");
+        assert_eq!(eval(r#"["b", "c", "a"].sort()"#), r#"["a", "b", "c"]"#);
+        let source = r#"
+            var people = [["ann", 30], ["bob", 25], ["cat", 30], ["dan", 25]];
+            people.sort(fun (a, b) { return a[1] - b[1]; }).map(fun (p) { return p[0]; });
+        "#;
+        assert_eq!(eval(source), r#"["bob", "dan", "ann", "cat"]"#);
+        assert_eq!(eval("var xs = [2, 1]; xs.sort(); xs;"), "[2, 1]");
+    }
+
+    #[test]
+    fn callback_errors_propagate() {
+        let err = |source: &str| match Interpreter::new().eval(source) {
+            Err(EvalError::Runtime(err)) => err,
+            other => panic!("expected a runtime error, got {:?}", other),
+        };
+        assert_eq!(err(r#"[1, "a"].sort()"#).kind, "TypeError");
+        assert_eq!(err("[].reduce(fun (a, b) { return a; })").kind, "ValueError");
+        let thrown = err("[1].map(fun (x) {\n throw \"bad\";\n})");
+        assert_eq!(thrown.message, "bad");
+        assert_eq!(thrown.trace, vec!["at <anonymous> (<stdin>:2)", "at <script> (<stdin>:3)"]);
+        assert_eq!(err("[1].map(2)").message, "Can only call functions, not Number");
+        assert_eq!(err("[1, 2].sort(fun (a, b) { return true; })").kind, "TypeError");
+    }
+}
@@ -0,0 +1,119 @@
+use std::cell::RefCell;
+use std::rc::Rc;
+
+use crate::callable::Arity;
+use crate::convert::IntoValue;
+use crate::error::RuntimeError;
+use crate::expr::LiteralValue;
+use crate::interpreter::Interpreter;
+use crate::map::{Key, Map};
+use crate::stdlib::{bind, lookup, Methods};
+
+type Entries = Rc<RefCell<Map>>;
+
+/// The methods of maps and their arities. `method` looks names up here
+/// and completion lists them.
+pub const METHODS: Methods<Entries> = &[
+    ("len", Arity::Exact(0), len),
+    ("keys", Arity::Exact(0), keys),
+    ("values", Arity::Exact(0), values),
+    ("entries", Arity::Exact(0), entries),
+    ("has", Arity::Exact(1), has),
+    ("get", Arity::Range(1, 2), get),
+    ("remove", Arity::Exact(1), remove),
+];
+
+/// Looks up the method `name` of the map `receiver`, as in `m.keys()`.
+/// Like lists, maps are shared by reference.
+pub fn method(receiver: &Entries, name: &str) -> Option<LiteralValue> {
+    let (arity, method) = lookup(METHODS, name)?;
+    Some(bind(format!("Map.{}", name), receiver.clone(), arity, method))
+}
+
+type MethodResult = Result<LiteralValue, RuntimeError>;
+
+fn key(value: &LiteralValue) -> Result<Key, RuntimeError> {
+    Key::new(value).map_err(|message| RuntimeError::type_error(message, 0))
+}
+
+fn len(_: &mut Interpreter, map: &Entries, _: &[LiteralValue]) -> MethodResult {
+    Ok(map.borrow().len().into_value())
+}
+
+fn keys(_: &mut Interpreter, map: &Entries, _: &[LiteralValue]) -> MethodResult {
+    Ok(map.borrow().iter().map(|(key, _)| key.to_value()).collect::<Vec<_>>().into_value())
+}
+
+fn values(_: &mut Interpreter, map: &Entries, _: &[LiteralValue]) -> MethodResult {
+    Ok(map.borrow().iter().map(|(_, value)| value.clone()).collect::<Vec<_>>().into_value())
+}
+
+// A list of `[key, value]` pairs.
+fn entries(_: &mut Interpreter, map: &Entries, _: &[LiteralValue]) -> MethodResult {
+    Ok(map
+        .borrow()
+        .iter()
+        .map(|(key, value)| vec![key.to_value(), value.clone()])
+        .collect::<Vec<_>>()
+        .into_value())
+}
+
+fn has(_: &mut Interpreter, map: &Entries, args: &[LiteralValue]) -> MethodResult {
+    Ok(map.borrow().get(&key(&args[0])?).is_some().into_value())
+}
+
+// The value for a key, or the default (nil unless given) when it is missing.
+fn get(_: &mut Interpreter, map: &Entries, args: &[LiteralValue]) -> MethodResult {
+    let default = args.get(1).cloned().unwrap_or(LiteralValue::Nil);
+    Ok(map.borrow().get(&key(&args[0])?).cloned().unwrap_or(default))
+}
+
+// Removes a key and returns its value, or nil when it was missing.
+fn remove(_: &mut Interpreter, map: &Entries, args: &[LiteralValue]) -> MethodResult {
+    Ok(map.borrow_mut().remove(&key(&args[0])?).into_value())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::error::EvalError;
+    use crate::expr::LiteralValue;
+    use crate::interpreter::Interpreter;
+
+    #[test]
+    fn map_literals_indexing_and_methods() {
+        let mut interpreter = Interpreter::new();
+        let source = r#"
+            var ages = {"ada": 36, "alan": 41};
+            ages["grace"] = 85;
+            ages["ada"] = ages["ada"] + 1;
+            var removed = ages.remove("alan");
+            ages;
+        "#;
+        assert_eq!(interpreter.eval(source).unwrap().to_string(), r#"{"ada": 37, "grace": 85}"#);
+        assert_eq!(interpreter.eval("removed").unwrap(), LiteralValue::Number(41.0));
+        assert_eq!(interpreter.eval("ages.keys()").unwrap().to_string(), r#"["ada", "grace"]"#);
+        assert_eq!(interpreter.eval("ages.entries()").unwrap().to_string(), r#"[["ada", 37], ["grace", 85]]"#);
+        assert_eq!(interpreter.eval(r#"ages.get("bob", 0) + ages.len()"#).unwrap(), LiteralValue::Number(2.0));
+        assert_eq!(interpreter.eval(r#"{1: "a", nil: 2} == {nil: 2, 1: "a"}"#).unwrap(), LiteralValue::True);
+        assert_eq!(interpreter.eval("{}").unwrap().to_string(), "{}");
+    }
+
+    #[test]
+    fn maps_that_contain_themselves() {
+        let eval = |source: &str| Interpreter::new().eval(source).unwrap().to_string();
+        let source = r#"var m = {}; m["a"] = m; var n = {}; n["a"] = n; [m == m, m == n, m == {"a": 1}];"#;
+        assert_eq!(eval(source), "[true, true, false]");
+        assert_eq!(eval(r#"var m = {"xs": []}; m["xs"].push(m); m;"#), r#"{"xs": [{...}]}"#);
+    }
+
+    #[test]
+    fn missing_and_invalid_keys_are_errors() {
+        let kind = |source: &str| match Interpreter::new().eval(source) {
+            Err(EvalError::Runtime(err)) => err.kind,
+            other => panic!("expected a runtime error, got {:?}", other),
+        };
+        assert_eq!(kind(r#"{"a": 1}["b"]"#), "KeyError");
+        assert_eq!(kind(r#"{[1]: 1}"#), "TypeError");
+        assert_eq!(kind(r#"{}.has([])"#), "TypeError");
+    }
+}
@@ -0,0 +1,153 @@
+use std::f64::consts;
+
+use crate::callable::Arity;
+use crate::convert::argument;
+use crate::error::RuntimeError;
+use crate::expr::LiteralValue;
+use crate::stdlib::ModuleBuilder;
+
+/// The `math` module: constants, rounding, powers, trigonometry and
+/// integer helpers. Functions taking integers reject numbers with a
+/// fractional part.
+pub fn module() -> LiteralValue {
+    ModuleBuilder::new("math")
+        .value("PI", consts::PI)
+        .value("E", consts::E)
+        .value("INF", f64::INFINITY)
+        .value("NAN", f64::NAN)
+        .function("abs", f64::abs)
+        .function("sign", |x: f64| if x == 0.0 || x.is_nan() { x } else { x.signum() })
+        .function("floor", f64::floor)
+        .function("ceil", f64::ceil)
+        // Halfway cases round away from zero.
+        .function("round", f64::round)
+        .function("trunc", f64::trunc)
+        .function("sqrt", f64::sqrt)
+        .function("pow", f64::powf)
+        .function("exp", f64::exp)
+        .native("log", Arity::Range(1, 2), |_, args| {
+            let x: f64 = argument("math.log", 0, &args[0])?;
+            match args.get(1) {
+                Some(base) => Ok(LiteralValue::Number(x.log(argument("math.log", 1, base)?))),
+                None => Ok(LiteralValue::Number(x.ln())),
+            }
+        })
+        .function("log2", f64::log2)
+        .function("log10", f64::log10)
+        .function("sin", f64::sin)
+        .function("cos", f64::cos)
+        .function("tan", f64::tan)
+        .function("asin", f64::asin)
+        .function("acos", f64::acos)
+        .function("atan", f64::atan)
+        .function("atan2", f64::atan2)
+        .function("hypot", f64::hypot)
+        .function("is_nan", f64::is_nan)
+        .function("is_finite", f64::is_finite)
+        .native("min", Arity::AtLeast(1), |_, args| extreme("math.min", &args, f64::min))
+        .native("max", Arity::AtLeast(1), |_, args| extreme("math.max", &args, f64::max))
+        .function("clamp", |x: f64, low: f64, high: f64| {
+            if low > high {
+                Err(value_error(format!("math.clamp() lower bound {} is above upper bound {}", low, high)))
+            } else {
+                Ok(x.clamp(low, high))
+            }
+        })
+        .function("gcd", gcd)
+        .function("lcm", |a: i64, b: i64| {
+            if a == 0 || b == 0 {
+                return Ok(0);
+            }
+            (a / gcd(a, b)?)
+                .checked_mul(b)
+                .and_then(i64::checked_abs)
+                .ok_or_else(|| value_error("math.lcm() result is too large".to_string()))
+        })
+        .build()
+}
+
+fn value_error(message: String) -> RuntimeError {
+    RuntimeError::new("ValueError", message, 0)
+}
+
+// The result is 2^63 when both arguments are i64::MIN, or one is and the
+// other is 0, which does not fit.
+fn gcd(a: i64, b: i64) -> Result<i64, RuntimeError> {
+    let (mut a, mut b) = (a.unsigned_abs(), b.unsigned_abs());
+    while b != 0 {
+        (a, b) = (b, a % b);
+    }
+    i64::try_from(a).map_err(|_| value_error("math.gcd() result is too large".to_string()))
+}
+
+fn extreme(name: &str, args: &[LiteralValue], pick: fn(f64, f64) -> f64) -> Result<LiteralValue, RuntimeError> {
+    let mut result: f64 = argument(name, 0, &args[0])?;
+    for (index, value) in args.iter().enumerate().skip(1) {
+        result = pick(result, argument(name, index, value)?);
+    }
+    Ok(LiteralValue::Number(result))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::error::EvalError;
+    use crate::expr::LiteralValue;
+    use crate::interpreter::Interpreter;
+
+    fn eval(source: &str) -> Result<LiteralValue, EvalError> {
+        Interpreter::new().eval(source)
+    }
+
+    fn number(source: &str) -> f64 {
+        match eval(source).unwrap() {
+            LiteralValue::Number(x) => x,
+            other => panic!("expected a number, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn functions_and_constants() {
+        assert_eq!(number("math.sqrt(16)"), 4.0);
+        assert_eq!(number("math.pow(2, 10)"), 1024.0);
+        assert_eq!(number("math.abs(-3)"), 3.0);
+        assert_eq!(number("math.floor(-1.5) + math.ceil(1.2)"), 0.0);
+        assert_eq!(number("math.round(2.5)"), 3.0);
+        assert_eq!(number("math.log(8, 2)"), 3.0);
+        assert_eq!(number("math.log(math.E)"), 1.0);
+        assert_eq!(number("math.min(3, 1, 2)"), 1.0);
+        assert_eq!(number("math.max(3, 1, 2)"), 3.0);
+        assert_eq!(number("math.clamp(12, 0, 10)"), 10.0);
+        assert_eq!(number("math.gcd(12, -18)"), 6.0);
+        assert_eq!(number("math.lcm(4, 6)"), 12.0);
+        assert!((number("math.sin(math.PI / 2)") - 1.0).abs() < 1e-12);
+        assert!(number("math.NAN").is_nan());
+        assert_eq!(eval("math.INF > 1000000").unwrap(), LiteralValue::True);
+    }
+
+    #[test]
+    fn bad_arguments_are_catchable_errors() {
+        let mut interpreter = Interpreter::new();
+        let source = r#"
+            var message = nil;
+            try { math.sqrt("nine"); } catch (e) { message = e.kind + ": " + e.message; }
+            message;
+        "#;
+        assert_eq!(
+            interpreter.eval(source).unwrap(),
+            LiteralValue::StringValue("TypeError: math.sqrt() argument 1 must be Number, not String".to_string())
+        );
+
+        let err = match eval("math.gcd(1.5, 2)") {
+            Err(EvalError::Runtime(err)) => err,
+            other => panic!("expected a runtime error, got {:?}", other),
+        };
+        assert_eq!(err.message, "math.gcd() argument 1 must be integer, not Number");
+        assert_eq!(err.line, 1);
+
+        assert!(matches!(eval("math.clamp(1, 5, 0)"), Err(EvalError::Runtime(err)) if err.kind == "ValueError"));
+        for overflow in ["math.gcd(-9223372036854775808, 0)", "math.lcm(-9223372036854775808, 1)"] {
+            assert!(matches!(eval(overflow), Err(EvalError::Runtime(err)) if err.kind == "ValueError"));
+        }
+        assert!(matches!(eval("math.max()"), Err(EvalError::Runtime(err)) if err.kind == "TypeError"));
+    }
+}
@@ -0,0 +1,164 @@
+//! The built-in modules every script can use without importing them, such
+//! as `math`, and the methods of built-in types like strings.
+
+use std::cell::RefCell;
+use std::collections::HashSet;
+use std::path::PathBuf;
+use std::rc::Rc;
+
+use crate::callable::{Arity, NativeFunction};
+use crate::convert::{IntoNative, IntoValue};
+use crate::environment::Environment;
+use crate::error::RuntimeError;
+use crate::expr::LiteralValue;
+use crate::interpreter::Interpreter;
+use crate::module::Module;
+
+pub mod io;
+pub mod iterator;
+pub mod json;
+pub mod list;
+pub mod map;
+pub mod math;
+pub mod os;
+pub mod random;
+pub mod regex;
+pub mod string;
+pub mod time;
+
+/// The global names defined for every script and module.
+pub fn builtins() -> Vec<(String, LiteralValue)> {
+    vec![
+        ("io".to_string(), io::module()),
+        ("json".to_string(), json::module()),
+        ("math".to_string(), math::module()),
+        ("os".to_string(), os::module()),
+        ("random".to_string(), random::module()),
+        ("regex".to_string(), regex::module()),
+        ("string".to_string(), string::module()),
+        ("time".to_string(), time::module()),
+    ]
+}
+
+/// The methods values of this type have, such as `split` for strings.
+pub fn method_names(value: &LiteralValue) -> Vec<&'static str> {
+    match value {
+        LiteralValue::StringValue(_) => names(string::METHODS),
+        LiteralValue::List(_) => names(list::METHODS),
+        LiteralValue::Map(_) => names(map::METHODS),
+        LiteralValue::Iterator(_) => names(iterator::METHODS),
+        LiteralValue::Date(_) => names(time::METHODS),
+        LiteralValue::Regex(_) => names(regex::METHODS),
+        _ => Vec::new(),
+    }
+}
+
+fn names<T>(methods: Methods<T>) -> Vec<&'static str> {
+    methods.iter().map(|(name, ..)| *name).collect()
+}
+
+/// A method of a built-in type. It receives the value it was read from
+/// along with the call's arguments.
+pub type Method<T> = fn(&mut Interpreter, &T, &[LiteralValue]) -> Result<LiteralValue, RuntimeError>;
+
+/// The methods of a built-in type, by name.
+pub type Methods<T> = &'static [(&'static str, Arity, Method<T>)];
+
+/// Finds the method `name` in `methods`.
+pub fn lookup<T>(methods: Methods<T>, name: &str) -> Option<(Arity, Method<T>)> {
+    methods.iter().find(|(method, ..)| *method == name).map(|&(_, arity, method)| (arity, method))
+}
+
+/// Binds `method` to `receiver`, producing the function that an expression
+/// like `receiver.name` evaluates to.
+pub fn bind<T: 'static>(qualified_name: String, receiver: T, arity: Arity, method: Method<T>) -> LiteralValue {
+    LiteralValue::Native(Rc::new(NativeFunction {
+        name: qualified_name,
+        arity,
+        fun: Rc::new(move |interpreter, arguments| method(interpreter, &receiver, &arguments)),
+    }))
+}
+
+/// Collects the members of a built-in module. Functions are named
+/// `module.member` so that argument errors say which function failed.
+pub struct ModuleBuilder {
+    name: String,
+    members: Environment,
+    exports: HashSet<String>,
+}
+
+impl ModuleBuilder {
+    pub fn new(name: &str) -> Self {
+        Self {
+            name: name.to_string(),
+            members: Environment::new(),
+            exports: HashSet::new(),
+        }
+    }
+
+    pub fn value<T: IntoValue>(mut self, name: &str, value: T) -> Self {
+        self.members.define(name.to_string(), value.into_value());
+        self.exports.insert(name.to_string());
+        self
+    }
+
+    /// Adds a Rust closure whose arity and argument types come from its
+    /// signature, like [`Interpreter::register_fn`].
+    pub fn function<Args, F: IntoNative<Args>>(self, name: &str, fun: F) -> Self {
+        let qualified = format!("{}.{}", self.name, name);
+        let native = NativeFunction {
+            arity: Arity::Exact(F::arity()),
+            fun: fun.into_native(&qualified),
+            name: qualified,
+        };
+        self.value(name, LiteralValue::Native(Rc::new(native)))
+    }
+
+    /// Adds a function working on raw runtime values, like
+    /// [`Interpreter::define_native`].
+    pub fn native<F>(self, name: &str, arity: Arity, fun: F) -> Self
+    where
+        F: Fn(&mut Interpreter, Vec<LiteralValue>) -> Result<LiteralValue, RuntimeError> + 'static,
+    {
+        let native = NativeFunction {
+            name: format!("{}.{}", self.name, name),
+            arity,
+            fun: Rc::new(fun),
+        };
+        self.value(name, LiteralValue::Native(Rc::new(native)))
+    }
+
+    pub fn build(self) -> LiteralValue {
+        LiteralValue::Module(Rc::new(Module {
+            path: PathBuf::from(self.name),
+            globals: Rc::new(RefCell::new(self.members)),
+            exports: self.exports,
+        }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn method_names_all_resolve() {
+        let mut interpreter = Interpreter::new();
+        let samples = [
+            r#""text""#,
+            "[1]",
+            "{}",
+            r#"io.read_lines("Cargo.toml")"#,
+            "time.now()",
+            r#"regex.compile("a")"#,
+        ];
+        for sample in samples {
+            let value = interpreter.eval(sample).unwrap();
+            assert!(!method_names(&value).is_empty(), "{}", sample);
+            for name in method_names(&value) {
+                let method = interpreter.eval(&format!("({}).{}", sample, name));
+                assert!(matches!(method, Ok(LiteralValue::Native(_))), "{}.{}", sample, name);
+            }
+        }
+    }
+}
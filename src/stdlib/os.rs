@@ -0,0 +1,171 @@
+use std::env;
+use std::io::Write;
+use std::process::{Command, Stdio};
+
+use crate::callable::Arity;
+use crate::convert::{argument, IntoValue};
+use crate::error::RuntimeError;
+use crate::expr::LiteralValue;
+use crate::map::{Key, Map};
+use crate::stdlib::ModuleBuilder;
+
+/// The `os` module: the script's command-line arguments, environment
+/// variables, the working directory, running other programs and exiting.
+/// Everything but `args()` is subject to the interpreter's
+/// [`Sandbox`](crate::Sandbox).
+pub fn module() -> LiteralValue {
+    ModuleBuilder::new("os")
+        // The arguments given after the script name: `fnky script.fnky a b`
+        // gives ["a", "b"].
+        .native("args", Arity::Exact(0), |interpreter, _| Ok(interpreter.args().to_vec().into_value()))
+        // The value of an environment variable, or `default` (nil if not
+        // given) when it is not set.
+        .native("env", Arity::Range(1, 2), |interpreter, args| {
+            let name: String = argument("os.env", 0, &args[0])?;
+            interpreter.sandbox().check_env()?;
+            match env::var_os(&name) {
+                Some(value) => Ok(value.to_string_lossy().into_owned().into_value()),
+                None => Ok(args.get(1).cloned().unwrap_or(LiteralValue::Nil)),
+            }
+        })
+        .native("env_vars", Arity::Exact(0), |interpreter, _| {
+            interpreter.sandbox().check_env()?;
+            let mut vars: Vec<(String, String)> = env::vars_os()
+                .map(|(name, value)| (name.to_string_lossy().into_owned(), value.to_string_lossy().into_owned()))
+                .collect();
+            vars.sort();
+            let mut map = Map::new();
+            for (name, value) in vars {
+                map.insert(Key::String(name), value.into_value());
+            }
+            Ok(LiteralValue::map(map))
+        })
+        .native("cwd", Arity::Exact(0), |interpreter, _| {
+            interpreter.sandbox().check_env()?;
+            let cwd = env::current_dir().map_err(|err| {
+                RuntimeError::new("IOError", format!("Could not read the working directory: {}", err), 0)
+            })?;
+            Ok(cwd.to_string_lossy().into_owned().into_value())
+        })
+        // Stops the script. `eval` returns `EvalError::Exit(code)` and the
+        // `fnky` command exits with `code`; `catch` clauses do not see it,
+        // but `finally` blocks still run.
+        .native("exit", Arity::Range(0, 1), |interpreter, args| {
+            let code: i32 = match args.first() {
+                Some(code) => argument("os.exit", 0, code)?,
+                None => 0,
+            };
+            interpreter.sandbox().check_process()?;
+            Err(interpreter.exit(code))
+        })
+        // run(command, input?): runs the program `command[0]` with the
+        // arguments `command[1:]`, without a shell, and waits for it to
+        // finish. Returns a map with its exit `status` (nil if it was killed
+        // by a signal) and what it wrote to `stdout` and `stderr`.
+        .native("run", Arity::Range(1, 2), |interpreter, args| {
+            let command: Vec<String> = argument("os.run", 0, &args[0])?;
+            let input: Option<String> = match args.get(1) {
+                Some(input) => Some(argument("os.run", 1, input)?),
+                None => None,
+            };
+            interpreter.sandbox().check_process()?;
+            let Some((program, program_args)) = command.split_first() else {
+                return Err(RuntimeError::new("ValueError", "os.run() command is empty".to_string(), 0));
+            };
+            let run_error = |err: std::io::Error| {
+                RuntimeError::new("IOError", format!("Could not run '{}': {}", program, err), 0)
+            };
+
+            let mut child = Command::new(program)
+                .args(program_args)
+                .stdin(if input.is_some() { Stdio::piped() } else { Stdio::null() })
+                .stdout(Stdio::piped())
+                .stderr(Stdio::piped())
+                .spawn()
+                .map_err(run_error)?;
+            // Dropping stdin closes it, so the program sees the end of its
+            // input.
+            if let (Some(input), Some(mut stdin)) = (input, child.stdin.take()) {
+                stdin.write_all(input.as_bytes()).map_err(run_error)?;
+            }
+            let output = child.wait_with_output().map_err(run_error)?;
+
+            let mut result = Map::new();
+            result.insert(Key::String("status".to_string()), output.status.code().into_value());
+            result.insert(
+                Key::String("stdout".to_string()),
+                String::from_utf8_lossy(&output.stdout).into_owned().into_value(),
+            );
+            result.insert(
+                Key::String("stderr".to_string()),
+                String::from_utf8_lossy(&output.stderr).into_owned().into_value(),
+            );
+            Ok(LiteralValue::map(result))
+        })
+        .build()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::error::EvalError;
+    use crate::expr::LiteralValue;
+    use crate::interpreter::Interpreter;
+    use crate::output::OutputBuffer;
+    use crate::sandbox::Sandbox;
+
+    fn error_kind(result: Result<LiteralValue, EvalError>) -> String {
+        match result {
+            Err(EvalError::Runtime(err)) => err.kind,
+            other => panic!("expected a runtime error, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn args_env_and_cwd() {
+        let mut interpreter = Interpreter::new();
+        interpreter.set_args(vec!["--fast".to_string(), "in.txt".to_string()]);
+        assert_eq!(interpreter.eval("os.args()").unwrap().to_string(), r#"["--fast", "in.txt"]"#);
+        assert_eq!(interpreter.eval(r#"os.env("FNKY_SURELY_UNSET", "none")"#).unwrap().to_string(), "none");
+        assert_eq!(interpreter.eval(r#"os.env("PATH") == os.env_vars()["PATH"]"#).unwrap(), LiteralValue::True);
+        let cwd = std::env::current_dir().unwrap();
+        assert_eq!(interpreter.eval("os.cwd()").unwrap().to_string(), cwd.to_string_lossy());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn run_captures_output_and_status() {
+        let mut interpreter = Interpreter::new();
+        let source = r#"
+            var result = os.run(["sh", "-c", "read line; echo got $line; echo oops >&2; exit 3"], "hi");
+            [result["status"], result["stdout"], result["stderr"]];
+        "#;
+        assert_eq!(interpreter.eval(source).unwrap().to_string(), "[3, \"got hi\\n\", \"oops\\n\"]");
+        assert_eq!(error_kind(interpreter.eval(r#"os.run(["fnky-no-such-program"])"#)), "IOError");
+        assert_eq!(error_kind(interpreter.eval("os.run([])")), "ValueError");
+    }
+
+    #[test]
+    fn exit_skips_catch_but_runs_finally() {
+        let output = OutputBuffer::new();
+        let mut interpreter = Interpreter::new();
+        interpreter.set_output(output.clone());
+        let source = r#"
+            try { os.exit(2); } catch (e) { print "caught"; } finally { print "cleanup"; }
+            print "after";
+        "#;
+        assert_eq!(interpreter.eval(source), Err(EvalError::Exit(2)));
+        assert_eq!(output.contents(), "cleanup\n");
+        assert_eq!(interpreter.eval("1 + 1").unwrap(), LiteralValue::Number(2.0));
+    }
+
+    #[test]
+    fn sandbox_blocks_environment_and_processes() {
+        let mut interpreter = Interpreter::new();
+        interpreter.set_sandbox(Sandbox::locked());
+        assert_eq!(error_kind(interpreter.eval(r#"os.env("PATH")"#)), "PermissionError");
+        assert_eq!(error_kind(interpreter.eval("os.cwd()")), "PermissionError");
+        assert_eq!(error_kind(interpreter.eval(r#"os.run(["true"])"#)), "PermissionError");
+        assert_eq!(error_kind(interpreter.eval("os.exit(1)")), "PermissionError");
+        assert_eq!(interpreter.eval("os.args()").unwrap().to_string(), "[]");
+    }
+}
@@ -0,0 +1,241 @@
+use std::rc::Rc;
+
+use regex::Captures;
+
+use crate::callable::Arity;
+use crate::convert::{argument, FromValue, IntoValue};
+use crate::error::RuntimeError;
+use crate::expr::LiteralValue;
+use crate::interpreter::Interpreter;
+use crate::map::{Key, Map};
+use crate::pattern::Pattern;
+use crate::stdlib::{bind, lookup, Methods, ModuleBuilder};
+
+/// The `regex` module. `compile(pattern)` returns a regex value whose
+/// methods do the matching; `escape(text)` quotes the special characters of
+/// `text` so it matches literally. Invalid patterns raise a `ValueError`
+/// naming the character position of the problem.
+pub fn module() -> LiteralValue {
+    ModuleBuilder::new("regex")
+        .function("compile", |source: String| compile(&source))
+        .function("escape", |text: String| regex::escape(&text))
+        .build()
+}
+
+pub fn compile(source: &str) -> Result<LiteralValue, RuntimeError> {
+    Pattern::new(source)
+        .map(|pattern| LiteralValue::Regex(Rc::new(pattern)))
+        .map_err(|message| RuntimeError::new("ValueError", message, 0))
+}
+
+/// The methods of compiled regexes and their arities. `method` looks names up here
+/// and completion lists them.
+pub const METHODS: Methods<Rc<Pattern>> = &[
+    ("source", Arity::Exact(0), |_, pattern, _| Ok(pattern.source().into_value())),
+    ("is_match", Arity::Exact(1), |_, pattern, args| {
+        Ok(pattern.regex.is_match(&arg::<String>("is_match", args, 0)?).into_value())
+    }),
+    ("find", Arity::Exact(1), |_, pattern, args| Ok(find(pattern, &arg::<String>("find", args, 0)?))),
+    ("find_all", Arity::Exact(1), |_, pattern, args| {
+        Ok(find_all(pattern, &arg::<String>("find_all", args, 0)?))
+    }),
+    ("captures", Arity::Exact(1), |_, pattern, args| {
+        Ok(captures(pattern, &arg::<String>("captures", args, 0)?))
+    }),
+    ("replace", Arity::Range(2, 3), |interpreter, pattern, args| {
+        let text: String = arg("replace", args, 0)?;
+        let limit: usize = match args.get(2) {
+            Some(_) => arg("replace", args, 2)?,
+            None => 0,
+        };
+        replace(interpreter, pattern, &text, &args[1], limit)
+    }),
+    ("split", Arity::Exact(1), |_, pattern, args| Ok(split(pattern, &arg::<String>("split", args, 0)?))),
+];
+
+/// Looks up the method `name` of a compiled regex, as in `re.find(text)`.
+///
+/// Matches are maps with the matched `text` and its `start` and `end`,
+/// which count characters like the string methods do.
+pub fn method(receiver: &Rc<Pattern>, name: &str) -> Option<LiteralValue> {
+    let (arity, method) = lookup(METHODS, name)?;
+    Some(bind(format!("Regex.{}", name), receiver.clone(), arity, method))
+}
+
+fn arg<T: FromValue>(method: &str, args: &[LiteralValue], index: usize) -> Result<T, RuntimeError> {
+    argument(&format!("Regex.{}", method), index, &args[index])
+}
+
+/// The first match in `text`, or nil.
+pub fn find(pattern: &Pattern, text: &str) -> LiteralValue {
+    let mut chars = CharIndex::default();
+    match pattern.regex.find(text) {
+        Some(found) => match_value(text, found.range(), &mut chars),
+        None => LiteralValue::Nil,
+    }
+}
+
+/// Every non-overlapping match in `text`, as a list.
+pub fn find_all(pattern: &Pattern, text: &str) -> LiteralValue {
+    let mut chars = CharIndex::default();
+    let matches = pattern
+        .regex
+        .find_iter(text)
+        .map(|found| match_value(text, found.range(), &mut chars))
+        .collect();
+    LiteralValue::list(matches)
+}
+
+/// The groups of the first match, or nil if there is none. Groups are
+/// keyed by number, 0 being the whole match, and named groups also by
+/// name. Groups that took no part in the match are nil.
+pub fn captures(pattern: &Pattern, text: &str) -> LiteralValue {
+    match pattern.regex.captures(text) {
+        Some(groups) => groups_value(pattern, &groups),
+        None => LiteralValue::Nil,
+    }
+}
+
+/// Replaces the first `limit` matches (all of them when `limit` is 0).
+/// `replacement` is either a string, in which `$1` or `${name}` stand for
+/// a group and `$$` for a dollar sign, or a function that is called with
+/// the groups of each match and returns the text to put in its place.
+pub fn replace(
+    interpreter: &mut Interpreter,
+    pattern: &Pattern,
+    text: &str,
+    replacement: &LiteralValue,
+    limit: usize,
+) -> Result<LiteralValue, RuntimeError> {
+    match replacement {
+        LiteralValue::StringValue(template) => {
+            Ok(pattern.regex.replacen(text, limit, template.as_str()).into_value())
+        }
+        LiteralValue::Function(_) | LiteralValue::Native(_) => {
+            let mut result = String::new();
+            let mut copied = 0;
+            let limit = if limit == 0 { usize::MAX } else { limit };
+            for groups in pattern.regex.captures_iter(text).take(limit) {
+                let whole = groups.get(0).expect("group 0 is the whole match");
+                let value = interpreter.call(replacement, vec![groups_value(pattern, &groups)], 0)?;
+                result.push_str(&text[copied..whole.start()]);
+                result.push_str(&value.to_string());
+                copied = whole.end();
+            }
+            result.push_str(&text[copied..]);
+            Ok(result.into_value())
+        }
+        other => Err(RuntimeError::type_error(
+            format!("Regex.replace() replacement must be String or Function, not {}", other.to_type()),
+            0,
+        )),
+    }
+}
+
+pub fn split(pattern: &Pattern, text: &str) -> LiteralValue {
+    pattern.regex.split(text).collect::<Vec<&str>>().into_value()
+}
+
+fn match_value(text: &str, range: std::ops::Range<usize>, chars: &mut CharIndex) -> LiteralValue {
+    let mut map = Map::new();
+    map.insert(Key::String("text".to_string()), text[range.clone()].into_value());
+    map.insert(Key::String("start".to_string()), chars.at(text, range.start).into_value());
+    map.insert(Key::String("end".to_string()), chars.at(text, range.end).into_value());
+    LiteralValue::map(map)
+}
+
+fn groups_value(pattern: &Pattern, groups: &Captures) -> LiteralValue {
+    let mut map = Map::new();
+    for (index, name) in pattern.regex.capture_names().enumerate() {
+        let value = groups.get(index).map(|group| group.as_str()).into_value();
+        map.insert(Key::Number((index as f64).to_bits()), value.clone());
+        if let Some(name) = name {
+            map.insert(Key::String(name.to_string()), value);
+        }
+    }
+    LiteralValue::map(map)
+}
+
+// Turns byte offsets into character offsets. Offsets must be asked for in
+// increasing order, so that finding every match in a string only walks it
+// once.
+#[derive(Default)]
+struct CharIndex {
+    byte: usize,
+    chars: usize,
+}
+
+impl CharIndex {
+    fn at(&mut self, text: &str, byte: usize) -> usize {
+        self.chars += text[self.byte..byte].chars().count();
+        self.byte = byte;
+        self.chars
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::error::EvalError;
+    use crate::expr::LiteralValue;
+    use crate::interpreter::Interpreter;
+
+    fn eval(source: &str) -> Result<LiteralValue, EvalError> {
+        Interpreter::new().eval(source)
+    }
+
+    fn string(source: &str) -> String {
+        eval(source).unwrap().to_string()
+    }
+
+    #[test]
+    fn matching_and_captures() {
+        let date = r#"var re = regex.compile("(?P<year>\d{4})-(\d{2})"); "#;
+        assert_eq!(string(&format!(r#"{}re.is_match("on 2024-03");"#, date)), "true");
+        assert_eq!(
+            string(&format!(r#"{}re.find("é 2024-03");"#, date)),
+            r#"{"text": "2024-03", "start": 2, "end": 9}"#
+        );
+        assert_eq!(
+            string(&format!(r#"{}re.captures("2024-03");"#, date)),
+            r#"{0: "2024-03", 1: "2024", "year": "2024", 2: "03"}"#
+        );
+        assert_eq!(string(&format!(r#"{}re.captures("none");"#, date)), "nil");
+        assert_eq!(string(r#"regex.compile("a(x)?b").captures("ab")[1]"#), "nil");
+        assert_eq!(
+            string(r#"regex.compile("[0-9]+").find_all("a1 b22 c333").map(fun (m) { return m["text"]; })"#),
+            r#"["1", "22", "333"]"#
+        );
+        assert_eq!(string(r#"regex.compile(regex.escape("1+1")).is_match("1+1=2")"#), "true");
+    }
+
+    #[test]
+    fn replace_with_group_references_and_functions() {
+        assert_eq!(
+            string(r#"regex.compile("(\w+)@(\w+)").replace("ann@home bob@work", "${2}:$1")"#),
+            "home:ann work:bob"
+        );
+        assert_eq!(string(r#"regex.compile("o").replace("foo boo", "0", 2)"#), "f00 boo");
+        assert_eq!(
+            string(r#"regex.compile("\d+").replace("3 apples, 12 pears", fun (m) { return m[0].repeat(2); })"#),
+            "33 apples, 1212 pears"
+        );
+    }
+
+    #[test]
+    fn string_methods_accept_a_regex() {
+        assert_eq!(string(r#""a1b22c".split(regex.compile("\d+"))"#), r#"["a", "b", "c"]"#);
+        assert_eq!(string(r#""x = 1;  y=2".replace(regex.compile("\s*=\s*"), ": ")"#), "x: 1;  y: 2");
+        assert_eq!(string(r#""añb7".find(regex.compile("\d"))"#), "3");
+        assert_eq!(string(r#""abc".contains(regex.compile("^B"))"#), "false");
+    }
+
+    #[test]
+    fn invalid_patterns_are_catchable() {
+        let source = r#"
+            var message = nil;
+            try { regex.compile("(a|b"); } catch (e) { message = e.kind + ": " + e.message; }
+            message;
+        "#;
+        assert_eq!(string(source), "ValueError: Invalid regex '(a|b' at position 0: unclosed group");
+    }
+}
@@ -0,0 +1,252 @@
+use std::rc::Rc;
+
+use crate::callable::Arity;
+use crate::convert::{argument, FromValue, IntoValue};
+use crate::error::RuntimeError;
+use crate::expr::LiteralValue;
+use crate::interpreter::Interpreter;
+use crate::stdlib::{bind, lookup, regex, Methods, ModuleBuilder};
+
+/// The `string` module, for building strings from character codes.
+pub fn module() -> LiteralValue {
+    ModuleBuilder::new("string")
+        .function("from_codes", |codes: Vec<u32>| {
+            codes
+                .into_iter()
+                .map(|code| {
+                    char::from_u32(code).ok_or_else(|| {
+                        RuntimeError::new("ValueError", format!("{} is not a valid character code", code), 0)
+                    })
+                })
+                .collect::<Result<String, RuntimeError>>()
+        })
+        .build()
+}
+
+/// The methods of strings and their arities. `method` looks names up here
+/// and completion lists them.
+pub const METHODS: Methods<Rc<str>> = &[
+    ("len", Arity::Exact(0), len),
+    ("upper", Arity::Exact(0), upper),
+    ("lower", Arity::Exact(0), lower),
+    ("trim", Arity::Exact(0), trim),
+    ("trim_start", Arity::Exact(0), trim_start),
+    ("trim_end", Arity::Exact(0), trim_end),
+    ("split", Arity::Range(0, 1), split),
+    ("join", Arity::Exact(1), join),
+    ("replace", Arity::Range(2, 3), replace),
+    ("find", Arity::Exact(1), find),
+    ("contains", Arity::Exact(1), contains),
+    ("starts_with", Arity::Exact(1), starts_with),
+    ("ends_with", Arity::Exact(1), ends_with),
+    ("repeat", Arity::Exact(1), repeat),
+    ("chars", Arity::Exact(0), chars),
+    ("codes", Arity::Exact(0), codes),
+    ("substring", Arity::Range(1, 2), substring),
+];
+
+/// Looks up the method `name` of the string `receiver`, as in `s.split(",")`.
+///
+/// Lengths and positions count characters (Unicode scalar values), not
+/// bytes, so `"héllo".len()` is 5 and `"héllo".substring(1, 2)` is `"é"`.
+/// `split`, `replace`, `find` and `contains` also take a compiled regex in
+/// place of a plain string.
+pub fn method(receiver: &str, name: &str) -> Option<LiteralValue> {
+    let (arity, method) = lookup(METHODS, name)?;
+    Some(bind(format!("String.{}", name), Rc::from(receiver), arity, method))
+}
+
+type MethodResult = Result<LiteralValue, RuntimeError>;
+
+fn arg<T: FromValue>(method: &str, args: &[LiteralValue], index: usize) -> Result<T, RuntimeError> {
+    argument(&format!("String.{}", method), index, &args[index])
+}
+
+fn len(_: &mut Interpreter, s: &Rc<str>, _: &[LiteralValue]) -> MethodResult {
+    Ok(s.chars().count().into_value())
+}
+
+fn upper(_: &mut Interpreter, s: &Rc<str>, _: &[LiteralValue]) -> MethodResult {
+    Ok(s.to_uppercase().into_value())
+}
+
+fn lower(_: &mut Interpreter, s: &Rc<str>, _: &[LiteralValue]) -> MethodResult {
+    Ok(s.to_lowercase().into_value())
+}
+
+fn trim(_: &mut Interpreter, s: &Rc<str>, _: &[LiteralValue]) -> MethodResult {
+    Ok(s.trim().into_value())
+}
+
+fn trim_start(_: &mut Interpreter, s: &Rc<str>, _: &[LiteralValue]) -> MethodResult {
+    Ok(s.trim_start().into_value())
+}
+
+fn trim_end(_: &mut Interpreter, s: &Rc<str>, _: &[LiteralValue]) -> MethodResult {
+    Ok(s.trim_end().into_value())
+}
+
+// Without a separator, splits on runs of whitespace and drops empty parts.
+fn split(_: &mut Interpreter, s: &Rc<str>, args: &[LiteralValue]) -> MethodResult {
+    match args.first() {
+        None => return Ok(s.split_whitespace().collect::<Vec<&str>>().into_value()),
+        Some(LiteralValue::Regex(pattern)) => return Ok(regex::split(pattern, s)),
+        Some(_) => (),
+    }
+    let separator: String = arg("split", args, 0)?;
+    if separator.is_empty() {
+        return Err(RuntimeError::new("ValueError", "String.split() separator is empty".to_string(), 0));
+    }
+    Ok(s.split(separator.as_str()).collect::<Vec<&str>>().into_value())
+}
+
+fn join(_: &mut Interpreter, s: &Rc<str>, args: &[LiteralValue]) -> MethodResult {
+    let parts: Vec<String> = arg("join", args, 0)?;
+    Ok(parts.join(s).into_value())
+}
+
+// With a regex, the replacement can refer to groups and a third argument
+// limits the number of replacements, as in `Regex.replace`.
+fn replace(interpreter: &mut Interpreter, s: &Rc<str>, args: &[LiteralValue]) -> MethodResult {
+    if let LiteralValue::Regex(pattern) = &args[0] {
+        let limit: usize = match args.get(2) {
+            Some(_) => arg("replace", args, 2)?,
+            None => 0,
+        };
+        return regex::replace(interpreter, pattern, s, &args[1], limit);
+    }
+    if args.len() > 2 {
+        return Err(RuntimeError::type_error(
+            "String.replace() only takes a limit when replacing a regex".to_string(),
+            0,
+        ));
+    }
+    let from: String = arg("replace", args, 0)?;
+    let to: String = arg("replace", args, 1)?;
+    Ok(s.replace(&from, &to).into_value())
+}
+
+// The character index of the first occurrence, or nil.
+fn find(_: &mut Interpreter, s: &Rc<str>, args: &[LiteralValue]) -> MethodResult {
+    if let LiteralValue::Regex(pattern) = &args[0] {
+        return Ok(pattern.regex.find(s).map(|found| s[..found.start()].chars().count()).into_value());
+    }
+    let needle: String = arg("find", args, 0)?;
+    Ok(s.find(&needle).map(|byte| s[..byte].chars().count()).into_value())
+}
+
+fn contains(_: &mut Interpreter, s: &Rc<str>, args: &[LiteralValue]) -> MethodResult {
+    if let LiteralValue::Regex(pattern) = &args[0] {
+        return Ok(pattern.regex.is_match(s).into_value());
+    }
+    let needle: String = arg("contains", args, 0)?;
+    Ok(s.contains(&needle).into_value())
+}
+
+fn starts_with(_: &mut Interpreter, s: &Rc<str>, args: &[LiteralValue]) -> MethodResult {
+    let prefix: String = arg("starts_with", args, 0)?;
+    Ok(s.starts_with(&prefix).into_value())
+}
+
+fn ends_with(_: &mut Interpreter, s: &Rc<str>, args: &[LiteralValue]) -> MethodResult {
+    let suffix: String = arg("ends_with", args, 0)?;
+    Ok(s.ends_with(&suffix).into_value())
+}
+
+fn repeat(_: &mut Interpreter, s: &Rc<str>, args: &[LiteralValue]) -> MethodResult {
+    let count: usize = arg("repeat", args, 0)?;
+    if s.len().checked_mul(count).is_none() {
+        return Err(RuntimeError::new("ValueError", "String.repeat() result is too long".to_string(), 0));
+    }
+    Ok(s.repeat(count).into_value())
+}
+
+fn chars(_: &mut Interpreter, s: &Rc<str>, _: &[LiteralValue]) -> MethodResult {
+    Ok(s.chars().map(String::from).collect::<Vec<String>>().into_value())
+}
+
+fn codes(_: &mut Interpreter, s: &Rc<str>, _: &[LiteralValue]) -> MethodResult {
+    Ok(s.chars().map(u32::from).collect::<Vec<u32>>().into_value())
+}
+
+// Characters from `start` up to, but not including, `end` (default: the
+// end of the string).
+fn substring(_: &mut Interpreter, s: &Rc<str>, args: &[LiteralValue]) -> MethodResult {
+    let length = s.chars().count();
+    let start: usize = arg("substring", args, 0)?;
+    let end: usize = match args.get(1) {
+        Some(_) => arg("substring", args, 1)?,
+        None => length,
+    };
+    if start > end || end > length {
+        return Err(RuntimeError::new(
+            "IndexError",
+            format!("String.substring() range {}..{} out of bounds for length {}", start, end, length),
+            0,
+        ));
+    }
+    Ok(s.chars().skip(start).take(end - start).collect::<String>().into_value())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::error::EvalError;
+    use crate::expr::LiteralValue;
+    use crate::interpreter::Interpreter;
+
+    fn eval(source: &str) -> Result<LiteralValue, EvalError> {
+        Interpreter::new().eval(source)
+    }
+
+    fn string(source: &str) -> String {
+        eval(source).unwrap().to_string()
+    }
+
+    #[test]
+    fn methods_count_characters_not_bytes() {
+        assert_eq!(string(r#""héllo wörld 🎉".len()"#), "13");
+        assert_eq!(string(r#""héllo".substring(1, 2)"#), "é");
+        assert_eq!(string(r#""héllo".substring(3)"#), "lo");
+        assert_eq!(string(r#""añb".find("b")"#), "2");
+        assert_eq!(string(r#""añb".find("z")"#), "nil");
+        assert_eq!(string(r#""añb"[1]"#), "ñ");
+        assert_eq!(string(r#""Straße".upper()"#), "STRASSE");
+        assert_eq!(string(r#""ÀB".lower()"#), "àb");
+        assert_eq!(string(r#""é🎉".chars()"#), r#"["é", "🎉"]"#);
+        assert_eq!(string(r#""Aé".codes()"#), "[65, 233]");
+        assert_eq!(string("string.from_codes([72, 105, 128075])"), "Hi👋");
+    }
+
+    #[test]
+    fn split_join_and_friends() {
+        assert_eq!(string(r#""a,b,,c".split(",")"#), r#"["a", "b", "", "c"]"#);
+        assert_eq!(string(r#""  one two   three ".split()"#), r#"["one", "two", "three"]"#);
+        assert_eq!(string(r#"", ".join(["x", "y", "z"])"#), "x, y, z");
+        assert_eq!(string(r#""  padded  ".trim() + "|""#), "padded|");
+        assert_eq!(string(r#""a-b-c".replace("-", "+")"#), "a+b+c");
+        assert_eq!(string(r#""ab".repeat(3)"#), "ababab");
+        assert_eq!(string(r#""fnky.toml".ends_with(".toml") and "fnky".starts_with("fn")"#), "true");
+        assert_eq!(string(r#"var parts = "k=v".split("="); parts[1];"#), "v");
+    }
+
+    #[test]
+    fn misuse_raises_catchable_errors() {
+        let kind = |source: &str| match eval(source) {
+            Err(EvalError::Runtime(err)) => err.kind,
+            other => panic!("expected a runtime error, got {:?}", other),
+        };
+        assert_eq!(kind(r#""abc".substring(2, 5)"#), "IndexError");
+        assert_eq!(kind(r#""abc".split("")"#), "ValueError");
+        assert_eq!(kind(r#""ab".repeat(10000000000000000000)"#), "ValueError");
+        assert_eq!(kind(r#"",".join([1, 2])"#), "TypeError");
+        assert_eq!(kind(r#""abc".shout()"#), "TypeError");
+        assert_eq!(kind(r#""abc"[3]"#), "IndexError");
+
+        let source = r#"
+            var message = nil;
+            try { "abc".repeat("x"); } catch (e) { message = e.message; }
+            message;
+        "#;
+        assert_eq!(string(source), "String.repeat() argument 1 must be integer, not String");
+    }
+}
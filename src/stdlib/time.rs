@@ -0,0 +1,190 @@
+use std::sync::OnceLock;
+use std::thread;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use crate::callable::Arity;
+use crate::convert::{argument, IntoValue};
+use crate::date::{Date, Fields};
+use crate::error::RuntimeError;
+use crate::expr::LiteralValue;
+use crate::interpreter::Interpreter;
+use crate::stdlib::{bind, lookup, Methods, ModuleBuilder};
+
+// The fixed point `clock`, `now_ms` and `now_ns` count from.
+fn start() -> Instant {
+    static START: OnceLock<Instant> = OnceLock::new();
+    *START.get_or_init(Instant::now)
+}
+
+/// The `time` module. `clock()` (seconds), `now_ms()` and `now_ns()` read a
+/// monotonic clock that starts at an arbitrary point, for measuring how long
+/// something takes. `now()`, `date(...)`, `parse(text)` and
+/// `from_timestamp(seconds)` create dates, which are always in UTC.
+pub fn module() -> LiteralValue {
+    ModuleBuilder::new("time")
+        .function("clock", || start().elapsed().as_secs_f64())
+        .function("now_ms", || start().elapsed().as_secs_f64() * 1e3)
+        .function("now_ns", || start().elapsed().as_nanos() as f64)
+        .function("sleep", |ms: f64| {
+            if !(ms >= 0.0 && ms.is_finite()) {
+                return Err(value_error(format!("time.sleep() duration must be a non-negative number of milliseconds, not {}", ms)));
+            }
+            let duration = Duration::try_from_secs_f64(ms / 1e3)
+                .map_err(|_| value_error(format!("time.sleep() duration {} is too long", ms)))?;
+            thread::sleep(duration);
+            Ok(())
+        })
+        .function("now", || {
+            let since_epoch = SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map(|elapsed| elapsed.as_millis() as i64)
+                .unwrap_or(0);
+            LiteralValue::Date(Date::from_millis(since_epoch))
+        })
+        // date(year, month, day, hour?, minute?, second?, millisecond?)
+        .native("date", Arity::Range(3, 7), |_, args| {
+            let field = |index: usize| -> Result<u32, RuntimeError> {
+                match args.get(index) {
+                    Some(value) => argument("time.date", index, value),
+                    None => Ok(0),
+                }
+            };
+            let fields = Fields {
+                year: argument("time.date", 0, &args[0])?,
+                month: field(1)?,
+                day: field(2)?,
+                hour: field(3)?,
+                minute: field(4)?,
+                second: field(5)?,
+                millisecond: field(6)?,
+            };
+            Date::from_fields(fields)
+                .map(LiteralValue::Date)
+                .map_err(|reason| value_error(format!("Invalid date: {}", reason)))
+        })
+        .function("parse", |text: String| Date::parse(&text).map(LiteralValue::Date).map_err(value_error))
+        .function("from_timestamp", |seconds: f64| {
+            millis(seconds)
+                .map(|millis| LiteralValue::Date(Date::from_millis(millis)))
+                .ok_or_else(|| value_error(format!("time.from_timestamp() {} is out of range", seconds)))
+        })
+        .build()
+}
+
+/// The methods of dates and their arities. `method` looks names up here
+/// and completion lists them.
+pub const METHODS: Methods<Date> = &[
+    ("year", Arity::Exact(0), |_, date, _| Ok(date.fields().year.into_value())),
+    ("month", Arity::Exact(0), |_, date, _| Ok(date.fields().month.into_value())),
+    ("day", Arity::Exact(0), |_, date, _| Ok(date.fields().day.into_value())),
+    ("hour", Arity::Exact(0), |_, date, _| Ok(date.fields().hour.into_value())),
+    ("minute", Arity::Exact(0), |_, date, _| Ok(date.fields().minute.into_value())),
+    ("second", Arity::Exact(0), |_, date, _| Ok(date.fields().second.into_value())),
+    ("millisecond", Arity::Exact(0), |_, date, _| Ok(date.fields().millisecond.into_value())),
+    ("weekday", Arity::Exact(0), |_, date, _| Ok(date.weekday().into_value())),
+    ("timestamp", Arity::Exact(0), |_, date, _| Ok((date.millis as f64 / 1e3).into_value())),
+    ("iso", Arity::Exact(0), |_, date, _| Ok(date.to_string().into_value())),
+    ("add_days", Arity::Exact(1), |_, date, args| add(date, args, "add_days", 86_400.0)),
+    ("add_hours", Arity::Exact(1), |_, date, args| add(date, args, "add_hours", 3_600.0)),
+    ("add_minutes", Arity::Exact(1), |_, date, args| add(date, args, "add_minutes", 60.0)),
+    ("add_seconds", Arity::Exact(1), |_, date, args| add(date, args, "add_seconds", 1.0)),
+    ("diff", Arity::Exact(1), diff),
+];
+
+/// Looks up the method `name` of a date, as in `d.add_days(1)`. Dates are
+/// values: the `add_*` methods return a new date.
+pub fn method(receiver: &Date, name: &str) -> Option<LiteralValue> {
+    let (arity, method) = lookup(METHODS, name)?;
+    Some(bind(format!("Date.{}", name), *receiver, arity, method))
+}
+
+fn value_error(message: String) -> RuntimeError {
+    RuntimeError::new("ValueError", message, 0)
+}
+
+// `seconds` in whole milliseconds, or `None` for NaN and for times too far
+// from 1970 for a date to hold.
+fn millis(seconds: f64) -> Option<i64> {
+    let millis = (seconds * 1e3).round();
+    // `i64::MAX as f64` rounds up to 2^63, which is already out of range.
+    (millis >= i64::MIN as f64 && millis < i64::MAX as f64).then_some(millis as i64)
+}
+
+fn add(date: &Date, args: &[LiteralValue], name: &str, seconds_per_unit: f64) -> Result<LiteralValue, RuntimeError> {
+    let amount: f64 = argument(&format!("Date.{}", name), 0, &args[0])?;
+    millis(amount * seconds_per_unit)
+        .and_then(|millis| date.add_millis(millis))
+        .map(LiteralValue::Date)
+        .ok_or_else(|| value_error(format!("Date.{}() result is out of range", name)))
+}
+
+// Seconds from `other` to this date.
+fn diff(_: &mut Interpreter, date: &Date, args: &[LiteralValue]) -> Result<LiteralValue, RuntimeError> {
+    match &args[0] {
+        LiteralValue::Date(other) => Ok(((date.millis as i128 - other.millis as i128) as f64 / 1e3).into_value()),
+        other => Err(RuntimeError::type_error(
+            format!("Date.diff() argument 1 must be Date, not {}", other.to_type()),
+            0,
+        )),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::error::EvalError;
+    use crate::interpreter::Interpreter;
+
+    fn eval(source: &str) -> String {
+        Interpreter::new().eval(source).unwrap().to_string()
+    }
+
+    #[test]
+    fn clocks_are_monotonic() {
+        let source = r#"
+            var start = time.now_ns();
+            var begun = time.clock();
+            time.sleep(5);
+            [time.now_ns() - start >= 5000000, time.clock() - begun >= 0.005, time.now_ms() > 0];
+        "#;
+        assert_eq!(eval(source), "[true, true, true]");
+        assert_eq!(eval("time.now().year() >= 2024"), "true");
+    }
+
+    #[test]
+    fn dates_parse_format_and_add_in_utc() {
+        assert_eq!(eval(r#"time.parse("2024-02-28T22:00:00+01:00").add_hours(26)"#), "2024-02-29T23:00:00Z");
+        assert_eq!(eval("time.date(2023, 12, 31).add_days(1).iso()"), "2024-01-01T00:00:00Z");
+        assert_eq!(eval(r#"time.parse("2024-03-10").weekday()"#), "7");
+        assert_eq!(eval(r#"time.parse("2024-01-02").diff(time.parse("2024-01-01T12:00Z"))"#), "43200");
+        assert_eq!(eval("time.from_timestamp(1.5).millisecond()"), "500");
+        assert_eq!(eval("time.date(2024, 5, 6, 7, 8, 9).timestamp()"), "1714979289");
+        assert_eq!(eval(r#"time.date(2024, 1, 1) == time.parse("2024-01-01T00:00:00Z")"#), "true");
+        assert_eq!(eval(r#"time.date(2024, 1, 1) < time.date(2024, 1, 2)"#), "true");
+    }
+
+    #[test]
+    fn invalid_dates_are_value_errors() {
+        let error = |source: &str| match Interpreter::new().eval(source) {
+            Err(EvalError::Runtime(err)) => err,
+            other => panic!("expected a runtime error, got {:?}", other),
+        };
+        assert_eq!(
+            error(r#"time.parse("2024-13-01")"#).message,
+            "Invalid ISO-8601 date '2024-13-01': month 13 is out of range"
+        );
+        assert_eq!(error("time.date(2023, 2, 29)").kind, "ValueError");
+        assert_eq!(error("time.sleep(-1)").kind, "ValueError");
+        assert_eq!(error("time.sleep(math.pow(10, 300))").kind, "ValueError");
+        assert_eq!(error("time.date(299999999, 1, 1)").kind, "ValueError");
+        assert_eq!(error("time.date(1000000000000, 1, 1)").message, "Invalid date: year 1000000000000 is out of range");
+        assert_eq!(error("time.date(2024, 1, 1).add_days(math.pow(10, 300))").kind, "ValueError");
+        assert_eq!(error("time.from_timestamp(-9223372036854775).add_seconds(-1)").kind, "ValueError");
+        assert_eq!(error("time.date(2024, 1, 1).add_hours(math.NAN)").kind, "ValueError");
+        for seconds in ["math.NAN", "math.INF", "-math.INF", "math.pow(10, 300)"] {
+            let from_timestamp = error(&format!("time.from_timestamp({})", seconds));
+            assert_eq!(from_timestamp.kind, "ValueError", "{}", seconds);
+        }
+        assert_eq!(error("time.date(2024, 1, 1).diff(3)").kind, "TypeError");
+        assert_eq!(Interpreter::new().eval("time.date(2024, 1, 1)").unwrap().to_type(), "Date");
+    }
+}
@@ -1,41 +1,155 @@
-use std::fmt::{self, format};
+use std::rc::Rc;
 
 use crate::expr::Expr;
 use crate::scanner::Token;
 
+#[derive(Debug)]
 pub enum Stmt {
     Expression {
         expression: Expr,
     },
     Print {
+        keyword: Token,
         expression: Expr,
     },
     Var {
-        expression: Expr,
+        keyword: Token,
         name: Token,
         initialiser: Expr,
-    },  
+    },
     Block {
+        brace: Token,
         statements: Vec<Stmt>,
     },
+    Function {
+        keyword: Token,
+        name: Token,
+        params: Vec<Token>,
+        body: Rc<Vec<Stmt>>,
+    },
+    If {
+        keyword: Token,
+        condition: Expr,
+        then_branch: Box<Stmt>,
+        else_branch: Option<Box<Stmt>>,
+    },
+    While {
+        keyword: Token,
+        condition: Expr,
+        body: Box<Stmt>,
+    },
+    Return {
+        keyword: Token,
+        value: Option<Expr>,
+    },
+    Throw {
+        keyword: Token,
+        value: Expr,
+    },
+    Import {
+        keyword: Token,
+        path: Token,
+        alias: Token,
+    },
+    ImportFrom {
+        keyword: Token,
+        path: Token,
+        names: Vec<Token>,
+    },
+    Export {
+        keyword: Token,
+        declaration: Box<Stmt>,
+    },
+    Try {
+        keyword: Token,
+        body: Vec<Stmt>,
+        catch_clause: Option<(Token, Vec<Stmt>)>,
+        finally_body: Option<Vec<Stmt>>,
+    },
+    // A statement that did not parse, left in the tree by
+    // `Parser::parse_partial` at the token where the error was found.
+    Error {
+        token: Token,
+        message: String,
+    },
 }
 
+fn block_to_string(statements: &[Stmt]) -> String {
+    statements.iter().map(|stmt| stmt.to_string()).collect::<Vec<String>>().join(",")
+}
 
 impl Stmt {
-    #[allow(dead_code)]
+    #[allow(dead_code, clippy::inherent_to_string)]
     pub fn to_string(&self) -> String {
         use Stmt::*;
         match self {
             Expression { expression } => expression.to_string(),
-            Print { expression } => format!("(print {})", expression.to_string()),
-            Var {
-                expression,
+            Print { keyword: _, expression } => format!("(print {})", expression.to_string()),
+            Var { keyword: _, name, initialiser } => format!("(var {} {})", name.lexeme, initialiser.to_string()),
+            Block { brace: _, statements } => format!("(block {})", block_to_string(statements)),
+            Function {
+                keyword: _,
                 name,
-                initialiser,
-            } => format!("(var {})", name.lexeme),
-            Block {
-                statements
-            } => format!("(block {})", statements.iter().map(|stmt| stmt.to_string()).collect::<Vec<String>>().join(",")),            
+                params,
+                body,
+            } => format!(
+                "(fun {} ({}) (block {}))",
+                name.lexeme,
+                params.iter().map(|param| param.lexeme.clone()).collect::<Vec<String>>().join(" "),
+                block_to_string(body)
+            ),
+            If {
+                keyword: _,
+                condition,
+                then_branch,
+                else_branch,
+            } => match else_branch {
+                Some(else_branch) => format!(
+                    "(if {} {} {})",
+                    condition.to_string(),
+                    then_branch.to_string(),
+                    else_branch.to_string()
+                ),
+                None => format!("(if {} {})", condition.to_string(), then_branch.to_string()),
+            },
+            While { keyword: _, condition, body } => format!("(while {} {})", condition.to_string(), body.to_string()),
+            Return { keyword: _, value } => match value {
+                Some(value) => format!("(return {})", value.to_string()),
+                None => "(return)".to_string(),
+            },
+            Throw { keyword: _, value } => format!("(throw {})", value.to_string()),
+            Import {
+                keyword: _,
+                path,
+                alias,
+            } => format!("(import {} {})", path.lexeme, alias.lexeme),
+            ImportFrom {
+                keyword: _,
+                path,
+                names,
+            } => format!(
+                "(from {} import {})",
+                path.lexeme,
+                names.iter().map(|name| name.lexeme.clone()).collect::<Vec<String>>().join(" ")
+            ),
+            Export { keyword: _, declaration } => format!("(export {})", declaration.to_string()),
+            Try {
+                keyword: _,
+                body,
+                catch_clause,
+                finally_body,
+            } => {
+                let mut result = format!("(try (block {})", block_to_string(body));
+                if let Some((name, statements)) = catch_clause {
+                    result.push_str(&format!(" (catch {} (block {}))", name.lexeme, block_to_string(statements)));
+                }
+                if let Some(statements) = finally_body {
+                    result.push_str(&format!(" (finally (block {}))", block_to_string(statements)));
+                }
+                result.push(')');
+                result
+            }
+            Error { token: _, message } => format!("(error {:?})", message),
         }
     }
-}
\ No newline at end of file
+}
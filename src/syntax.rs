@@ -0,0 +1,386 @@
+//! A lossless syntax tree. Every byte of the source, whitespace, comments
+//! and text that does not scan included, belongs to exactly one token of
+//! the tree, so printing the tree gives back the source. Tools that edit
+//! code can work on it without losing what the parser's tree drops.
+//!
+//! The tree follows statements and brackets rather than the whole grammar:
+//! a statement node holds its tokens up to its `;` or, for `if`, `while`,
+//! `for`, `fun` and `try`, up to its last block, and brackets nest inside
+//! it. Whitespace and comments between statements belong to the enclosing
+//! program or block. Source that does not parse still gets a tree.
+
+use std::fmt;
+use std::ops::Range;
+
+use crate::scanner::{Scanner, TokenType};
+use TokenType::*;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum NodeKind {
+    Program,
+    Statement,
+    /// `{ ... }` holding statements.
+    Block,
+    /// `( ... )`.
+    Parens,
+    /// `[ ... ]`, a list or an index.
+    Brackets,
+    /// `{ ... }` holding map entries.
+    Map,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct SyntaxToken {
+    pub kind: TokenType,
+    pub text: String,
+    /// The byte offset of the token in the source.
+    pub offset: usize,
+}
+
+impl SyntaxToken {
+    pub fn span(&self) -> Range<usize> {
+        self.offset..self.offset + self.text.len()
+    }
+
+    /// Whitespace and comments.
+    pub fn is_trivia(&self) -> bool {
+        matches!(self.kind, WHITESPACE | COMMENT)
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum SyntaxElement {
+    Node(SyntaxNode),
+    Token(SyntaxToken),
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct SyntaxNode {
+    pub kind: NodeKind,
+    pub children: Vec<SyntaxElement>,
+}
+
+impl SyntaxNode {
+    fn new(kind: NodeKind) -> Self {
+        Self { kind, children: vec![] }
+    }
+
+    /// Every token under the node in source order, trivia included.
+    pub fn tokens(&self) -> Vec<&SyntaxToken> {
+        let mut tokens = vec![];
+        self.collect_tokens(&mut tokens);
+        tokens
+    }
+
+    fn collect_tokens<'a>(&'a self, tokens: &mut Vec<&'a SyntaxToken>) {
+        for child in &self.children {
+            match child {
+                SyntaxElement::Node(node) => node.collect_tokens(tokens),
+                SyntaxElement::Token(token) => tokens.push(token),
+            }
+        }
+    }
+
+    /// The bytes of the source the node covers; empty for an empty program.
+    pub fn span(&self) -> Range<usize> {
+        let tokens = self.tokens();
+        match (tokens.first(), tokens.last()) {
+            (Some(first), Some(last)) => first.offset..last.span().end,
+            _ => 0..0,
+        }
+    }
+
+    pub fn nodes(&self) -> impl Iterator<Item = &SyntaxNode> {
+        self.children.iter().filter_map(|child| match child {
+            SyntaxElement::Node(node) => Some(node),
+            SyntaxElement::Token(_) => None,
+        })
+    }
+
+    /// The innermost nodes from the root down to the token at `offset`,
+    /// and that token.
+    pub fn token_at(&self, offset: usize) -> Option<(Vec<&SyntaxNode>, &SyntaxToken)> {
+        let mut path = vec![self];
+        let mut node = self;
+        loop {
+            let child = node.children.iter().find(|child| match child {
+                SyntaxElement::Node(child) => child.span().contains(&offset),
+                SyntaxElement::Token(token) => token.span().contains(&offset),
+            })?;
+            match child {
+                SyntaxElement::Node(child) => {
+                    path.push(child);
+                    node = child;
+                }
+                SyntaxElement::Token(token) => return Some((path, token)),
+            }
+        }
+    }
+
+    /// The offsets of each opening bracket under the node and of the
+    /// bracket that closes it. Brackets never closed are left out.
+    pub fn bracket_pairs(&self) -> Vec<(usize, usize)> {
+        let mut pairs = vec![];
+        self.collect_pairs(&mut pairs);
+        pairs
+    }
+
+    fn collect_pairs(&self, pairs: &mut Vec<(usize, usize)>) {
+        let closer = match self.kind {
+            NodeKind::Parens => Some(RIGHT_PAREN),
+            NodeKind::Brackets => Some(RIGHT_BRACKET),
+            NodeKind::Block | NodeKind::Map => Some(RIGHT_BRACE),
+            NodeKind::Program | NodeKind::Statement => None,
+        };
+        if let (Some(closer), Some(SyntaxElement::Token(first)), Some(SyntaxElement::Token(last))) =
+            (closer, self.children.first(), self.children.last())
+            && last.kind == closer
+            && self.children.len() > 1
+        {
+            pairs.push((first.offset, last.offset));
+        }
+        for node in self.nodes() {
+            node.collect_pairs(pairs);
+        }
+    }
+
+    /// An indented outline of the tree, one node or token per line, for
+    /// debugging and tests.
+    pub fn dump(&self) -> String {
+        let mut out = String::new();
+        self.dump_into(&mut out, 0);
+        out
+    }
+
+    fn dump_into(&self, out: &mut String, depth: usize) {
+        let span = self.span();
+        out.push_str(&format!("{}{:?}@{}..{}\n", "  ".repeat(depth), self.kind, span.start, span.end));
+        for child in &self.children {
+            match child {
+                SyntaxElement::Node(node) => node.dump_into(out, depth + 1),
+                SyntaxElement::Token(token) => {
+                    let span = token.span();
+                    out.push_str(&format!(
+                        "{}{}@{}..{} {:?}\n",
+                        "  ".repeat(depth + 1),
+                        token.kind,
+                        span.start,
+                        span.end,
+                        token.text
+                    ));
+                }
+            }
+        }
+    }
+}
+
+// The source text the node was built from.
+impl fmt::Display for SyntaxNode {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for token in self.tokens() {
+            write!(f, "{}", token.text)?;
+        }
+        Ok(())
+    }
+}
+
+/// Builds the lossless tree of a source file. It never fails.
+pub fn parse(source: &str) -> SyntaxNode {
+    let tokens: Vec<SyntaxToken> = Scanner::lossless(source)
+        .scan_tokens()
+        .expect("a lossless scan does not fail")
+        .into_iter()
+        .filter(|token| token.token_type != EoF)
+        .map(|token| SyntaxToken {
+            kind: token.token_type,
+            text: token.lexeme,
+            offset: token.offset,
+        })
+        .collect();
+
+    let mut builder = Builder {
+        open: vec![SyntaxNode::new(NodeKind::Program)],
+        previous: None,
+    };
+    for index in 0..tokens.len() {
+        let next = tokens[index + 1..].iter().find(|token| !token.is_trivia()).map(|token| token.kind);
+        builder.token(tokens[index].clone(), next);
+    }
+    while builder.open.len() > 1 {
+        builder.close();
+    }
+    builder.open.pop().expect("the program node is never closed")
+}
+
+struct Builder {
+    // The nodes still open, the program first.
+    open: Vec<SyntaxNode>,
+    // The last token that was not trivia.
+    previous: Option<TokenType>,
+}
+
+impl Builder {
+    fn top(&mut self) -> &mut SyntaxNode {
+        self.open.last_mut().expect("the program node is never closed")
+    }
+
+    fn push(&mut self, token: SyntaxToken) {
+        self.top().children.push(SyntaxElement::Token(token));
+    }
+
+    fn close(&mut self) {
+        let node = self.open.pop().expect("the program node is never closed");
+        self.top().children.push(SyntaxElement::Node(node));
+    }
+
+    fn token(&mut self, token: SyntaxToken, next: Option<TokenType>) {
+        if token.is_trivia() {
+            self.push(token);
+            return;
+        }
+
+        let kind = token.kind;
+        let starts_statement = matches!(self.top().kind, NodeKind::Program | NodeKind::Block);
+        if starts_statement && kind != RIGHT_BRACE {
+            self.open.push(SyntaxNode::new(NodeKind::Statement));
+        }
+
+        match kind {
+            LEFT_PAREN | LEFT_BRACKET | LEFT_BRACE => {
+                let node = match kind {
+                    LEFT_PAREN => NodeKind::Parens,
+                    LEFT_BRACKET => NodeKind::Brackets,
+                    // A block statement, or the body of a statement or
+                    // function; any other `{` starts a map.
+                    _ if starts_statement || matches!(self.previous, Some(RIGHT_PAREN | ELSE | TRY | FINALLY)) => {
+                        NodeKind::Block
+                    }
+                    _ => NodeKind::Map,
+                };
+                self.open.push(SyntaxNode::new(node));
+                self.push(token);
+            }
+            RIGHT_PAREN | RIGHT_BRACKET | RIGHT_BRACE => {
+                let matches = |node: &SyntaxNode| match kind {
+                    RIGHT_PAREN => node.kind == NodeKind::Parens,
+                    RIGHT_BRACKET => node.kind == NodeKind::Brackets,
+                    _ => matches!(node.kind, NodeKind::Block | NodeKind::Map),
+                };
+                // A stray closing bracket is an ordinary token.
+                let Some(depth) = self.open.iter().rposition(matches) else {
+                    self.push(token);
+                    self.previous = Some(kind);
+                    return;
+                };
+                while self.open.len() > depth + 1 {
+                    self.close();
+                }
+                self.push(token);
+                let closed = self.open.pop().expect("the bracket node is open");
+                let body = closed.kind == NodeKind::Block && !self.is_lambda_body();
+                self.top().children.push(SyntaxElement::Node(closed));
+                if body {
+                    self.end_statement(next);
+                }
+            }
+            SEMICOLON => {
+                self.push(token);
+                self.end_statement(next);
+            }
+            _ => self.push(token),
+        }
+        self.previous = Some(kind);
+    }
+
+    // Closes the open statement, unless an `else`, `catch` or `finally`
+    // continues it.
+    fn end_statement(&mut self, next: Option<TokenType>) {
+        if self.top().kind == NodeKind::Statement && !matches!(next, Some(ELSE | CATCH | FINALLY)) {
+            self.close();
+        }
+    }
+
+    // Whether a block that just closed follows `fun (...)` in the top node.
+    fn is_lambda_body(&mut self) -> bool {
+        let significant: Vec<&SyntaxElement> = self
+            .top()
+            .children
+            .iter()
+            .filter(|child| !matches!(child, SyntaxElement::Token(token) if token.is_trivia()))
+            .collect();
+        matches!(
+            significant[..],
+            [.., SyntaxElement::Token(SyntaxToken { kind: FUN, .. }), SyntaxElement::Node(SyntaxNode { kind: NodeKind::Parens, .. })]
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn round_trips_any_source() {
+        let sources = [
+            "",
+            "  \n// only a comment\n",
+            "var x = 1; // one\n\nfun add(a, b) {\n\treturn a + b;\n}\r\n",
+            "if (x) { print 1; } else if (y) print 2; else { print 3; }",
+            "try { throw 1; } catch (e) { print e; } finally {}\nvar f = fun (x) { return {\"k\": [x]}; };",
+            "for (var i = 0; i < 3; i = i + 1) { print i; }",
+            "print (1;\n}} ] @ # \"unterminated",
+            "var s = \"é 😀\"; s[0]",
+        ];
+        for source in sources {
+            let tree = parse(source);
+            assert_eq!(tree.to_string(), source);
+            let mut end = 0;
+            for token in tree.tokens() {
+                assert_eq!(token.offset, end);
+                assert_eq!(&source[token.span()], token.text);
+                end = token.span().end;
+            }
+            assert_eq!(end, source.len());
+        }
+    }
+
+    #[test]
+    fn statements_and_brackets() {
+        let source = "fun f(x) {\n  var m = {1: fun () { return x; }};\n  if (x) print 1; else {}\n}\nf(2);";
+        let tree = parse(source);
+        let statements: Vec<String> = tree.nodes().map(|node| node.to_string()).collect();
+        assert_eq!(statements, [&source[..source.len() - 6], "f(2);"]);
+
+        let body = tree.nodes().next().unwrap().nodes().nth(1).unwrap();
+        assert_eq!(body.kind, NodeKind::Block);
+        let inner: Vec<String> = body.nodes().map(|node| node.to_string()).collect();
+        assert_eq!(inner, ["var m = {1: fun () { return x; }};", "if (x) print 1; else {}"]);
+
+        let pairs = parse("f([1], {}); (").bracket_pairs();
+        assert_eq!(pairs, [(1, 9), (2, 4), (7, 8)]);
+
+        let (path, token) = tree.token_at(source.find("return").unwrap() + 2).unwrap();
+        assert_eq!(token.kind, RETURN);
+        let kinds: Vec<NodeKind> = path.iter().map(|node| node.kind).collect();
+        assert_eq!(
+            kinds,
+            [
+                NodeKind::Program,
+                NodeKind::Statement,
+                NodeKind::Block,
+                NodeKind::Statement,
+                NodeKind::Map,
+                NodeKind::Block,
+                NodeKind::Statement
+            ]
+        );
+    }
+
+    #[test]
+    fn dump_shows_trivia() {
+        assert_eq!(
+            parse("x; // c\n").dump(),
+            "Program@0..8\n  Statement@0..2\n    IDENTIFIER@0..1 \"x\"\n    SEMICOLON@1..2 \";\"\n  WHITESPACE@2..3 \" \"\n  COMMENT@3..7 \"// c\"\n  WHITESPACE@7..8 \"\\n\"\n"
+        );
+    }
+}